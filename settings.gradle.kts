@@ -37,3 +37,4 @@ include(":demo-shared")
 include(":demo-android")
 include(":wrywebview")
 include(":webview-compose")
+include(":tests:kotlin")
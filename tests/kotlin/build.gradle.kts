@@ -0,0 +1,15 @@
+plugins {
+    alias(libs.plugins.kotlinJvm)
+}
+
+kotlin {
+    jvmToolchain(17)
+}
+
+dependencies {
+    // Brings the generated bindings and the native library built for the host.
+    testImplementation(project(":wrywebview"))
+    testImplementation(libs.jna)
+    testImplementation(libs.kotlin.testJunit)
+    testImplementation(libs.kotlinx.coroutinesCore)
+}
@@ -0,0 +1,101 @@
+package io.github.kdroidfilter.webview.wry
+
+import com.sun.jna.Native
+import java.awt.Frame
+import java.awt.GraphicsEnvironment
+import kotlinx.coroutines.Dispatchers
+import kotlinx.coroutines.async
+import kotlinx.coroutines.awaitAll
+import kotlinx.coroutines.runBlocking
+import kotlinx.coroutines.withTimeout
+import kotlin.test.Test
+import kotlin.test.assertEquals
+import kotlin.test.assertFailsWith
+import kotlin.test.assertFalse
+import kotlin.test.assertTrue
+
+/**
+ * Calls the UniFFI bindings end to end, through the native library built by Gobley.
+ *
+ * The checks done before any WebView exists run everywhere. The WebView lifecycle test
+ * needs an X11 display and is skipped elsewhere; on CI, run `xvfb-run ./gradlew :tests:kotlin:test`.
+ */
+class WryBindingsTest {
+    @Test
+    fun malformedUrlIsReportedAsInvalidUrl() {
+        val error = assertFailsWith<WebViewException.InvalidUrl> {
+            createWebview(1UL, 0, 0, 100, 100, "not a url")
+        }
+        assertEquals("invalid url: not a url", error.message)
+    }
+
+    @Test
+    fun emptySizeIsReportedAsInvalidArgument() {
+        val error = assertFailsWith<WebViewException.InvalidArgument> {
+            createWebview(1UL, 0, 0, 0, 100, "https://example.com")
+        }
+        assertEquals("invalid argument: invalid size 0x100", error.message)
+    }
+
+    @Test
+    fun invalidZoomIsReportedAsInvalidArgument() {
+        val error = assertFailsWith<WebViewException.InvalidArgument> { setZoom(UNKNOWN_ID, 0.0) }
+        assertEquals("invalid argument: invalid zoom 0", error.message)
+    }
+
+    @Test
+    fun unknownIdIsReportedAsWebViewNotFound() {
+        val error = assertFailsWith<WebViewException.WebViewNotFound> { getUrl(UNKNOWN_ID) }
+        assertEquals("webview $UNKNOWN_ID not found", error.message)
+    }
+
+    @Test
+    fun concurrentCallsDoNotDeadlock() = runBlocking {
+        withTimeout(10_000) {
+            (1..8).map {
+                async(Dispatchers.Default) {
+                    repeat(100) {
+                        listWebviews()
+                        assertFailsWith<WebViewException.WebViewNotFound> { isLoading(UNKNOWN_ID) }
+                    }
+                }
+            }.awaitAll()
+        }
+    }
+
+    @Test
+    fun webViewLifecycle() {
+        if (!System.getProperty("os.name").startsWith("Linux") || GraphicsEnvironment.isHeadless()) {
+            println("skipped: needs an X11 display")
+            return
+        }
+        val frame = Frame("WryBindingsTest").apply {
+            setSize(400, 300)
+            isVisible = true
+        }
+        try {
+            val id = createWebview(Native.getComponentID(frame).toULong(), 0, 0, 400, 300, "about:blank")
+            loadUrl(id, "data:text/html,<title>ok</title>")
+            val deadline = System.currentTimeMillis() + 10_000
+            while (isLoading(id) || !getUrl(id).startsWith("data:")) {
+                assertTrue(System.currentTimeMillis() < deadline, "page did not finish loading")
+                Thread.sleep(50)
+            }
+            // The bindings return platform strings as non-null Kotlin strings.
+            val url: String = getUrl(id)
+            assertTrue(url.startsWith("data:text/html"))
+            assertFalse(isLoading(id))
+            assertEquals(1.0, getZoom(id))
+            setZoom(id, 1.5)
+            assertEquals(1.5, getZoom(id))
+            destroyWebview(id)
+            assertFailsWith<WebViewException.WebViewNotFound> { getUrl(id) }
+        } finally {
+            frame.dispose()
+        }
+    }
+
+    private companion object {
+        const val UNKNOWN_ID = 9_999_999UL
+    }
+}
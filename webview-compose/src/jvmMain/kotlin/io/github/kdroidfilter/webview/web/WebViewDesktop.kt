@@ -10,11 +10,14 @@ import androidx.compose.runtime.remember
 import androidx.compose.runtime.rememberCoroutineScope
 import androidx.compose.runtime.rememberUpdatedState
 import androidx.compose.runtime.setValue
+import androidx.compose.runtime.withFrameNanos
 import androidx.compose.ui.Modifier
 import androidx.compose.ui.awt.SwingPanel
 import io.github.kdroidfilter.webview.cookie.WryCookieManager
 import io.github.kdroidfilter.webview.jsbridge.WebViewJsBridge
 import io.github.kdroidfilter.webview.jsbridge.parseJsMessage
+import io.github.kdroidfilter.webview.util.KLogger
+import kotlinx.coroutines.channels.Channel
 import kotlinx.coroutines.delay
 
 actual class WebViewFactoryParam(
@@ -70,6 +73,26 @@ actual fun ActualWebView(
             (state.cookieManager as? WryCookieManager)?.attach(nativeWebView)
         }
 
+        // Apply the bounds recorded during resizes at most once per frame, and only
+        // on frames following a change, so an idle WebView requests no frames.
+        LaunchedEffect(nativeWebView) {
+            val boundsChanged = Channel<Unit>(Channel.CONFLATED)
+            nativeWebView.onBoundsChanged = { boundsChanged.trySend(Unit) }
+            try {
+                while (true) {
+                    boundsChanged.receive()
+                    withFrameNanos { }
+                    // A failure is also reported again by the setBounds of the next resize.
+                    runCatching { nativeWebView.applyPendingBounds() }
+                        .onFailure {
+                            KLogger.e(it, tag = "WebViewDesktop") { "applyPendingBounds failed" }
+                        }
+                }
+            } finally {
+                nativeWebView.onBoundsChanged = null
+            }
+        }
+
         // Poll native state (URL/loading/title/nav) and drain IPC messages for JS bridge.
         LaunchedEffect(nativeWebView, state, navigator, webViewJsBridge) {
             while (true) {
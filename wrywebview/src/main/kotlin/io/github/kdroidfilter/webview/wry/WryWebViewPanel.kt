@@ -31,8 +31,9 @@ class WryWebViewPanel(
     private var windowsTimer: Timer? = null
     private var skikoInitialized: Boolean = false
     private var lastBounds: Bounds? = null
-    private var pendingBounds: Bounds? = null
-    private var boundsTimer: Timer? = null
+
+    /** Called on the EDT when new bounds are recorded for `applyPendingBounds`. */
+    var onBoundsChanged: (() -> Unit)? = null
 
     init {
         layout = BorderLayout()
@@ -286,6 +287,16 @@ class WryWebViewPanel(
 
     fun isReady(): Boolean = webviewId != null
 
+    /**
+     * Applies the bounds recorded since the last frame; called once per frame.
+     *
+     * Throws if they cannot be applied. The next bounds change reports the failure again.
+     */
+    fun applyPendingBounds() {
+        val id = webviewId ?: return
+        NativeBindings.applyPendingBounds(id)
+    }
+
     fun requestWebViewFocus() {
         val action = { webviewId?.let { NativeBindings.focus(it) } }
         if (SwingUtilities.isEventDispatchThread()) {
@@ -337,6 +348,7 @@ class WryWebViewPanel(
                         NativeBindings.createWebviewWithUserAgent(handleSnapshot, width, height, initialUrl, userAgent)
                     }
                 updateBounds()
+                applyPendingBounds()
                 startGtkPumpIfNeeded()
                 startWindowsPumpIfNeeded()
                 // Apply any pending content that requires an explicit call after creation.
@@ -395,6 +407,7 @@ class WryWebViewPanel(
                 }
                 webviewId = createdId
                 updateBounds()
+                applyPendingBounds()
                 startGtkPumpIfNeeded()
                 startWindowsPumpIfNeeded()
                 // Apply any pending content that requires an explicit call after creation.
@@ -425,7 +438,6 @@ class WryWebViewPanel(
         stopDestroyTimer()
         stopGtkPump()
         stopWindowsPump()
-        stopBoundsTimer()
         webviewId?.let {
             log("destroy id=$it")
             NativeBindings.destroyWebview(it)
@@ -439,29 +451,12 @@ class WryWebViewPanel(
     private fun updateBounds() {
         val id = webviewId ?: return
         val bounds = boundsInParent()
-        if (IS_LINUX || IS_MAC) {
-            pendingBounds = bounds
-            if (boundsTimer == null) {
-                boundsTimer = Timer(16) {
-                    val currentId = webviewId ?: return@Timer
-                    val toSend = pendingBounds ?: return@Timer
-                    pendingBounds = null
-                    if (toSend != lastBounds) {
-                        lastBounds = toSend
-                        log("setBounds id=$currentId pos=(${toSend.x}, ${toSend.y}) size=${toSend.width}x${toSend.height}")
-                        NativeBindings.setBounds(currentId, toSend.x, toSend.y, toSend.width, toSend.height)
-                    }
-                    if (pendingBounds == null) {
-                        stopBoundsTimer()
-                    }
-                }.apply { start() }
-            }
-            return
-        }
         if (bounds == lastBounds) return
-        lastBounds = bounds
         log("setBounds id=$id pos=(${bounds.x}, ${bounds.y}) size=${bounds.width}x${bounds.height}")
+        // Only recorded natively: applyPendingBounds applies the latest bounds once per frame.
         NativeBindings.setBounds(id, bounds.x, bounds.y, bounds.width, bounds.height)
+        lastBounds = bounds
+        onBoundsChanged?.invoke()
     }
 
     private fun startGtkPumpIfNeeded() {
@@ -520,12 +515,6 @@ class WryWebViewPanel(
         destroyTimer = null
     }
 
-    private fun stopBoundsTimer() {
-        boundsTimer?.stop()
-        boundsTimer = null
-        pendingBounds = null
-    }
-
     private fun componentHandle(component: Component): ULong {
         return try {
             Native.getComponentID(component).toULong()
@@ -643,7 +632,7 @@ class WryWebViewPanel(
 
 private object NativeBindings {
     fun createWebview(parentHandle: ULong, width: Int, height: Int, url: String): ULong {
-        return io.github.kdroidfilter.webview.wry.createWebview(parentHandle, width, height, url)
+        return io.github.kdroidfilter.webview.wry.createWebview(parentHandle, 0, 0, width, height, url)
     }
 
     fun createWebviewWithUserAgent(
@@ -655,6 +644,8 @@ private object NativeBindings {
     ): ULong {
         return io.github.kdroidfilter.webview.wry.createWebviewWithUserAgent(
             parentHandle,
+            0,
+            0,
             width,
             height,
             url,
@@ -666,6 +657,10 @@ private object NativeBindings {
         io.github.kdroidfilter.webview.wry.setBounds(id, x, y, width, height)
     }
 
+    fun applyPendingBounds(id: ULong) {
+        io.github.kdroidfilter.webview.wry.applyPendingBounds(id)
+    }
+
     fun loadUrl(id: ULong, url: String) {
         io.github.kdroidfilter.webview.wry.loadUrl(id, url)
     }
@@ -0,0 +1,44 @@
+//! X.509 decoding of the certificates reported by the platform WebViews.
+
+use sha2::{Digest, Sha256};
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::CertificateInfo;
+
+/// Returns the SHA-256 fingerprint of a certificate as colon-separated hex bytes.
+fn fingerprint_sha256(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Decodes a DER certificate.
+///
+/// A certificate that cannot be parsed keeps its bytes and fingerprint, with
+/// empty names and zero validity dates.
+pub fn certificate_info(der: Vec<u8>) -> CertificateInfo {
+    let fingerprint_sha256 = fingerprint_sha256(&der);
+    let (subject, issuer, not_before_ms, not_after_ms) = match X509Certificate::from_der(&der) {
+        Ok((_, cert)) => (
+            cert.subject().to_string(),
+            cert.issuer().to_string(),
+            cert.validity().not_before.timestamp() * 1000,
+            cert.validity().not_after.timestamp() * 1000,
+        ),
+        Err(e) => {
+            eprintln!("[wrywebview] certificate parse failed: {}", e);
+            (String::new(), String::new(), 0, 0)
+        }
+    };
+
+    CertificateInfo {
+        subject,
+        issuer,
+        not_before_ms,
+        not_after_ms,
+        fingerprint_sha256,
+        der_bytes: der,
+    }
+}
@@ -1,32 +1,80 @@
 //! Error types for the WebView library.
 
 /// Errors that can occur when working with WebViews.
+///
+/// Foreign callers receive the variant and its message; Rust callers can also
+/// walk `source()` down to the underlying `wry::Error`. New variants may be
+/// added, so matches need a wildcard arm.
 #[derive(Debug, thiserror::Error, uniffi::Error)]
+#[uniffi(flat_error)]
+#[non_exhaustive]
 pub enum WebViewError {
     #[error("unsupported platform for native webview")]
     UnsupportedPlatform,
 
-    #[error("invalid parent window handle")]
-    InvalidWindowHandle,
+    #[error("invalid parent window handle 0x{0:x}")]
+    InvalidWindowHandle(u64),
 
     #[error("webview {0} not found")]
     WebViewNotFound(u64),
 
+    #[error("webview group {0} not found")]
+    GroupNotFound(u64),
+
+    #[error("webview {0} does not use the data store of group {1}")]
+    DataStoreMismatch(u64, u64),
+
+    #[error("no element matches {0}")]
+    ElementNotFound(String),
+
     #[error("webview {0} must be accessed from the creating thread")]
     WrongThread(u64),
 
     #[error("wry error: {0}")]
-    WryError(String),
+    Wry(#[from] wry::Error),
+
+    #[error("platform initialization failed: {0}")]
+    PlatformInitFailed(String),
+
+    #[error("conflicts with the settings shared with other webviews: {0}")]
+    SharedSettingsConflict(String),
+
+    #[error("this setting can only be applied when the webview is created")]
+    NotSupportedAtRuntime,
+
+    #[error("devtools are not enabled for this webview")]
+    DevToolsDisabled,
+
+    #[error("page is not served over HTTPS")]
+    NotSecure,
+
+    #[error("the UI thread did not respond within {0} ms")]
+    MainThreadTimeout(u64),
 
-    #[error("gtk initialization failed: {0}")]
-    GtkInit(String),
+    #[error("the webview was not created within {0} ms")]
+    InitializationTimeout(u64),
+
+    #[error("this must be done before the first webview is created")]
+    AlreadyInitialized,
+
+    #[error("too many webviews: {0} are already open")]
+    TooManyWebViews(u32),
+
+    #[error("no webview ids are left")]
+    IdSpaceExhausted,
+
+    #[error("invalid url: {0}")]
+    InvalidUrl(String),
+
+    #[error("webview {0} has not received a page response yet")]
+    NoMainResponse(u64),
+
+    #[error("the previous bounds could not be applied: {0}")]
+    BoundsNotApplied(String),
+
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
 
     #[error("internal error: {0}")]
     Internal(String),
 }
-
-impl From<wry::Error> for WebViewError {
-    fn from(error: wry::Error) -> Self {
-        WebViewError::WryError(error.to_string())
-    }
-}
@@ -20,36 +20,73 @@ use wry::raw_window_handle::Win32WindowHandle;
 use crate::error::WebViewError;
 
 /// Wrapper around a raw window handle for WebView creation.
+///
+/// The handle comes from the host as an integer, so no Rust lifetime can tie it
+/// to the native window: the host must keep the window alive while a WebView
+/// is being created in it.
 pub struct RawWindow {
     pub raw: RawWindowHandle,
 }
 
 impl HasWindowHandle for RawWindow {
     fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        // SAFETY: the handle is only lent to `build_as_child`, which runs
+        // synchronously on the UI thread while the host's call into
+        // `create_webview` is blocked, so the parent window cannot be destroyed
+        // meanwhile. Sentinel values were rejected by `raw_window_handle_from`.
+        // The WebView itself keeps its own reference to the parent afterwards.
         unsafe { Ok(WindowHandle::borrow_raw(self.raw)) }
     }
 }
 
 /// Creates a `Rect` with the given position and size, ensuring minimum dimensions.
-pub fn make_bounds(x: i32, y: i32, width: i32, height: i32) -> Rect {
-    let width = width.max(1);
-    let height = height.max(1);
-    Rect {
-        position: LogicalPosition::new(x, y).into(),
-        size: LogicalSize::new(width, height).into(),
+///
+/// Coordinates are logical pixels. With a `scale_factor` they are converted to
+/// physical pixels here instead of by the platform, using that factor.
+pub fn make_bounds(x: i32, y: i32, width: i32, height: i32, scale_factor: Option<f64>) -> Rect {
+    make_bounds_f64(
+        x.into(),
+        y.into(),
+        width.into(),
+        height.into(),
+        scale_factor,
+    )
+}
+
+/// Like `make_bounds`, for the fractional logical coordinates of non-integer
+/// scale factors (e.g. 125%). Physical coordinates are rounded to the nearest pixel.
+pub fn make_bounds_f64(x: f64, y: f64, width: f64, height: f64, scale_factor: Option<f64>) -> Rect {
+    let width = width.max(1.0);
+    let height = height.max(1.0);
+    match scale_factor {
+        Some(scale) => Rect {
+            position: LogicalPosition::new(x, y).to_physical::<i32>(scale).into(),
+            size: LogicalSize::new(width, height).to_physical::<u32>(scale).into(),
+        },
+        None => Rect {
+            position: LogicalPosition::new(x, y).into(),
+            size: LogicalSize::new(width, height).into(),
+        },
     }
 }
 
+/// Handle values that callers pass for "no window yet" rather than a real window.
+const SENTINEL_HANDLES: [u64; 3] = [0, 0xDEAD_BEEF, u64::MAX];
+
 /// Converts a platform-specific handle to a `RawWindowHandle`.
+///
+/// Sentinel values fail with `InvalidWindowHandle`, naming the value, here
+/// instead of crashing in the platform WebView builder.
 pub fn raw_window_handle_from(parent_handle: u64) -> Result<RawWindowHandle, WebViewError> {
-    if parent_handle == 0 {
-        return Err(WebViewError::InvalidWindowHandle);
+    if SENTINEL_HANDLES.contains(&parent_handle) {
+        eprintln!("[wrywebview] raw_window_handle invalid=0x{:x}", parent_handle);
+        return Err(WebViewError::InvalidWindowHandle(parent_handle));
     }
 
     #[cfg(target_os = "windows")]
     {
         let hwnd = NonZeroIsize::new(parent_handle as isize)
-            .ok_or(WebViewError::InvalidWindowHandle)?;
+            .ok_or(WebViewError::InvalidWindowHandle(parent_handle))?;
         let handle = RawWindowHandle::Win32(Win32WindowHandle::new(hwnd));
         eprintln!("[wrywebview] raw_window_handle Win32=0x{:x}", parent_handle);
         return Ok(handle);
@@ -79,3 +116,37 @@ pub fn raw_window_handle_from(parent_handle: u64) -> Result<RawWindowHandle, Web
         Err(WebViewError::UnsupportedPlatform)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wry::dpi::Size;
+
+    fn physical_size(rect: Rect) -> (u32, u32) {
+        match rect.size {
+            Size::Physical(size) => (size.width, size.height),
+            size => panic!("expected a physical size, got {:?}", size),
+        }
+    }
+
+    #[test]
+    fn rejects_sentinel_handles() {
+        for handle in SENTINEL_HANDLES {
+            match raw_window_handle_from(handle) {
+                Err(error @ WebViewError::InvalidWindowHandle(rejected)) => {
+                    assert_eq!(rejected, handle);
+                    assert!(error.to_string().contains(&format!("0x{:x}", handle)));
+                }
+                other => panic!("handle 0x{:x} gave {:?}", handle, other),
+            }
+        }
+    }
+
+    #[test]
+    fn scale_factor_multiplies_the_physical_size() {
+        let at_1x = physical_size(make_bounds_f64(0.0, 0.0, 100.0, 50.0, Some(1.0)));
+        let at_2x = physical_size(make_bounds_f64(0.0, 0.0, 100.0, 50.0, Some(2.0)));
+        assert_eq!(at_1x, (100, 50));
+        assert_eq!(at_2x, (200, 100));
+    }
+}
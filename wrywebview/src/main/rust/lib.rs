@@ -3,38 +3,48 @@
 //! This library provides a cross-platform WebView implementation
 //! exposed through UniFFI for use from Kotlin/Swift.
 
+mod certificate;
 mod error;
 mod handle;
 mod platform;
+mod scripts;
 mod state;
 
+use std::collections::HashSet;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::atomic::Ordering;
+use std::sync::mpsc;
 use std::sync::Arc;
+use std::sync::{Mutex, MutexGuard};
 use std::sync::OnceLock;
+use std::time::Duration;
+#[cfg(target_os = "windows")]
+use std::time::Instant;
 
+use base64::Engine;
 use wry::cookie::time::OffsetDateTime;
 use wry::cookie::{Cookie, Expiration, SameSite};
 use wry::http::header::HeaderName;
 use wry::http::{HeaderMap, HeaderValue};
-use wry::WebViewBuilder;
+use wry::{NewWindowFeatures, NewWindowResponse, WebViewBuilder};
 
 pub use error::WebViewError;
 
-use handle::{make_bounds, raw_window_handle_from, RawWindow};
-use state::{get_state, register, unregister, with_webview, WebViewState};
+use handle::{make_bounds, make_bounds_f64, raw_window_handle_from, RawWindow};
+use state::{
+    get_state, register, unregister, with_webview, with_webview_infallible, PendingWebView,
+    WebViewState,
+};
 
 #[cfg(target_os = "linux")]
-use platform::linux::{ensure_gtk_initialized, run_on_gtk_thread};
+use platform::linux::{ensure_gtk_initialized, run_on_gtk_thread, run_on_gtk_thread_timeout};
 
 #[cfg(target_os = "linux")]
 use wry::WebViewExtUnix;
 
 #[cfg(not(target_os = "linux"))]
-use platform::run_on_main_thread;
-
-#[cfg(target_os = "macos")]
-use platform::macos::{DispatchQueue, MainThreadMarker};
+use platform::{run_on_main_thread, run_on_main_thread_timeout};
 
 // =============================================================================
 // Public records/enums (UniFFI)
@@ -69,6 +79,422 @@ pub struct WebViewCookie {
     pub is_http_only: Option<bool>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum PermissionResource {
+    Camera,
+    Microphone,
+    Geolocation,
+    Notifications,
+    ClipboardRead,
+    ClipboardWrite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum PermissionResponse {
+    Allow,
+    Deny,
+    /// Lets the platform apply its default behavior (usually prompting the user).
+    AskUser,
+}
+
+/// Where links that request a new window (`target="_blank"`, `window.open`) are opened.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Enum)]
+pub enum LinkOpenMode {
+    /// Loads the link in the WebView that requested it.
+    SameWebView,
+    /// Creates a new WebView in `parent_handle` and loads the link there.
+    NewWebView {
+        parent_handle: u64,
+        width: i32,
+        height: i32,
+    },
+    /// Opens the link in the default browser of the OS.
+    SystemBrowser,
+    /// Ignores the link.
+    Block,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, uniffi::Record)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, uniffi::Record)]
+pub struct ShiftSource {
+    /// CSS selector of the shifted element; empty if it was removed from the DOM.
+    pub element_selector: String,
+    pub current_rect: Rect,
+    pub previous_rect: Rect,
+}
+
+/// A `layout-shift` performance entry; `value` adds to the Cumulative Layout Shift
+/// unless `had_recent_input` is set.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, uniffi::Record)]
+pub struct LayoutShiftEntry {
+    pub value: f64,
+    pub had_recent_input: bool,
+    pub sources: Vec<ShiftSource>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum TlsDecision {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum SslWarningType {
+    /// Passive content (images, media) was loaded over HTTP on an HTTPS page.
+    MixedContentDisplayed,
+    /// Active content (scripts, iframes) was loaded over HTTP on an HTTPS page.
+    MixedContentRan,
+    /// A certificate could not be verified.
+    InvalidCertificate,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct SslWarning {
+    pub url: String,
+    pub warning_type: SslWarningType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum ProxyType {
+    Http,
+    Https,
+    Socks4,
+    Socks5,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct ProxyConfig {
+    pub proxy_type: ProxyType,
+    pub host: String,
+    pub port: u16,
+    /// Hosts that are reached directly, e.g. `localhost` or `*.example.com`.
+    pub bypass_list: Vec<String>,
+    /// Sent when the proxy asks for authentication (Linux and macOS).
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl ProxyConfig {
+    /// Returns the proxy as a URL, e.g. `socks5://proxy.example.com:1080`.
+    pub(crate) fn url(&self) -> String {
+        let scheme = match self.proxy_type {
+            ProxyType::Http => "http",
+            ProxyType::Https => "https",
+            ProxyType::Socks4 => "socks4",
+            ProxyType::Socks5 => "socks5",
+        };
+        format!("{}://{}:{}", scheme, self.host, self.port)
+    }
+
+    pub(crate) fn credentials(&self) -> Option<Credentials> {
+        Some(Credentials {
+            username: self.username.clone()?,
+            password: self.password.clone().unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum AutoplayPolicy {
+    /// Media may play with sound without a user gesture.
+    Allow,
+    /// Only muted media may play without a user gesture.
+    AllowMuted,
+    /// Media never plays without a user gesture.
+    Deny,
+}
+
+/// Value reported to the page by the `prefers-color-scheme` media feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+    /// Follows the system (or, on macOS, the window) appearance.
+    Auto,
+}
+
+/// Options applied when a WebView is created.
+///
+/// Some of them, like the proxy, cannot be changed afterwards on every platform.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct WebViewConfig {
+    pub url: String,
+    #[uniffi(default = None)]
+    pub user_agent: Option<String>,
+    /// Proxy for the traffic of the WebView. The engines apply it to state that
+    /// several WebViews can share, so WebViews sharing it must use the same proxy,
+    /// or creation fails with `SharedSettingsConflict`:
+    /// - Linux: a WebView with a proxy and no data store gets a web context for
+    ///   that proxy, with its own cookies and storage; with `data_store_identifier`
+    ///   or `group_id`, the context of the data store.
+    /// - Windows: the WebView2 environment of the data store, whose GPU and file
+    ///   access settings must match too.
+    /// - macOS: the `WKWebsiteDataStore` (macOS 14 and later).
+    #[uniffi(default = None)]
+    pub proxy: Option<ProxyConfig>,
+    #[uniffi(default = None)]
+    pub autoplay_policy: Option<AutoplayPolicy>,
+    #[uniffi(default = true)]
+    pub javascript_enabled: bool,
+    /// Whether touchpad and touchscreen pinches zoom the page (see `set_pinch_to_zoom_enabled`).
+    #[uniffi(default = true)]
+    pub pinch_to_zoom: bool,
+    /// Whether the page is rendered on the GPU, when `set_hardware_acceleration_enabled`
+    /// allows it.
+    #[uniffi(default = true)]
+    pub hardware_acceleration: bool,
+    /// Whether `file://` pages may read other local files (see `set_allow_file_access`).
+    #[uniffi(default = false)]
+    pub allow_file_access: bool,
+    /// Whether `open_devtools` may open the inspector. Popups inherit it.
+    #[uniffi(default = false)]
+    pub devtools_enabled: bool,
+    /// Whether the page background is transparent, letting the native UI below show
+    /// through where the page paints nothing. On Linux this needs a compositing
+    /// window manager.
+    #[uniffi(default = false)]
+    pub transparent: bool,
+    /// How long `create_webview_with_config` waits for the UI thread to create
+    /// the WebView, in milliseconds; 0 waits forever.
+    #[uniffi(default = 30000)]
+    pub initialization_timeout_ms: u64,
+    /// Script run once, when the first page finishes loading (after any redirect).
+    /// Popups opened by the WebView do not inherit it.
+    #[uniffi(default = None)]
+    pub initial_script: Option<String>,
+    /// Name of the data store holding cookies, cache and storage. WebViews with
+    /// the same name share them; `None` uses the default store. Requires macOS 14
+    /// on macOS.
+    #[uniffi(default = None)]
+    pub data_store_identifier: Option<String>,
+    /// Group the WebView joins once created (see `create_webview_group`). It then
+    /// uses the data store of the group, so `data_store_identifier` must be left
+    /// unset.
+    #[uniffi(default = None)]
+    pub group_id: Option<u64>,
+}
+
+/// Default of `WebViewConfig::initialization_timeout_ms`.
+const DEFAULT_INITIALIZATION_TIMEOUT_MS: u64 = 30_000;
+
+impl WebViewConfig {
+    /// Returns the default configuration for a WebView loading `url`.
+    pub(crate) fn from_url(url: String) -> Self {
+        Self {
+            url,
+            user_agent: None,
+            proxy: None,
+            autoplay_policy: None,
+            javascript_enabled: true,
+            pinch_to_zoom: true,
+            hardware_acceleration: true,
+            allow_file_access: false,
+            devtools_enabled: false,
+            transparent: false,
+            initialization_timeout_ms: DEFAULT_INITIALIZATION_TIMEOUT_MS,
+            initial_script: None,
+            data_store_identifier: None,
+            group_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, uniffi::Enum)]
+#[serde(rename_all = "lowercase")]
+pub enum ConsoleLevel {
+    /// `console.log` and `console.debug`.
+    Log,
+    Info,
+    Warn,
+    Error,
+}
+
+/// A console call posted by the console script.
+#[derive(serde::Deserialize)]
+struct ConsoleMessage {
+    level: ConsoleLevel,
+    message: String,
+    source: String,
+    line: u32,
+}
+
+/// A history position posted by the history script.
+#[derive(serde::Deserialize)]
+struct HistoryPosition {
+    length: u32,
+    index: i32,
+}
+
+/// Snapshot of the navigation state of a WebView.
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct WebViewInfo {
+    pub url: String,
+    pub title: String,
+    pub is_loading: bool,
+    pub can_go_back: bool,
+    pub can_go_forward: bool,
+    /// Estimated load progress between 0.0 and 1.0. Only WebKitGTK reports
+    /// intermediate values; the other platforms go from 0.0 to 1.0.
+    pub progress: f64,
+    /// When the WebView was created, in milliseconds since the UNIX epoch.
+    pub created_at: u64,
+    /// `history.length` of the page. Like `history_offset`, it is only
+    /// refreshed when a page finishes loading, so it lags behind in-page
+    /// navigations until then.
+    pub history_length: u32,
+    /// Index of the current history entry, or -1 where the engine does not
+    /// expose it (WebKit without the Navigation API).
+    pub history_offset: i32,
+}
+
+/// An event of the stream returned by `subscribe_events`.
+#[derive(Debug, Clone, PartialEq, uniffi::Enum)]
+pub enum WebViewEvent {
+    NavigationStarted { url: String },
+    PageLoaded { url: String },
+    TitleChanged { title: String },
+    /// A message posted by the page with `window.ipc.postMessage`.
+    IpcMessage { message: String },
+    /// The main frame failed to load.
+    Error { message: String },
+    /// Load progress between 0.0 and 1.0.
+    LoadProgress { progress: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct ContextMenuItem {
+    pub title: String,
+    /// Identifies the item: reported by `on_item_selected` for custom items, and set
+    /// to the platform identifier (when there is one) for default items.
+    pub action_id: String,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Enum)]
+pub enum ContextMenuDecision {
+    /// No menu is shown.
+    Suppress,
+    /// The platform menu is shown unchanged.
+    ShowDefault,
+    /// The given items replace the platform menu.
+    ShowCustom(Vec<ContextMenuItem>),
+}
+
+/// A certificate of the TLS chain of the current page.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub not_before_ms: i64,
+    pub not_after_ms: i64,
+    /// Colon-separated uppercase hex, e.g. `AB:CD:…`.
+    pub fingerprint_sha256: String,
+    pub der_bytes: Vec<u8>,
+}
+
+/// Kind of resource a request loads, as reported by the platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum ResourceType {
+    Document,
+    Stylesheet,
+    Image,
+    Media,
+    Font,
+    Script,
+    XmlHttpRequest,
+    Fetch,
+    Other,
+}
+
+/// Selects the requests passed to a `RequestInterceptor`.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct RequestFilter {
+    /// URL pattern in which `*` matches any run of characters, e.g.
+    /// `https://ads.example.com/*`.
+    pub url_pattern: String,
+    /// Resource types to intercept; empty intercepts all of them.
+    pub resource_types: Vec<ResourceType>,
+}
+
+impl RequestFilter {
+    pub(crate) fn matches(&self, url: &str, resource_type: ResourceType) -> bool {
+        (self.resource_types.is_empty() || self.resource_types.contains(&resource_type))
+            && wildcard_match(&self.url_pattern, url)
+    }
+}
+
+/// Matches `text` against `pattern`, in which `*` matches any run of characters.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = text.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*`: the whole text must match.
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// A request passed to a `RequestInterceptor`.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct InterceptedRequest {
+    pub url: String,
+    pub method: String,
+    pub headers: Vec<HttpHeader>,
+    /// Upload body, if the platform exposes one.
+    pub body: Option<Vec<u8>>,
+    pub resource_type: ResourceType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum InterceptAction {
+    /// The request goes ahead.
+    Allow,
+    /// The request fails.
+    Block,
+    /// The request is redirected to `modified_url`.
+    Redirect,
+    /// The request is answered with `injected_body` instead of reaching the network.
+    ModifyResponse,
+}
+
+/// Decision of a `RequestInterceptor`.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct InterceptedResponse {
+    pub action: InterceptAction,
+    /// Target of `Redirect`; a redirect without one blocks the request.
+    #[uniffi(default = None)]
+    pub modified_url: Option<String>,
+    /// With `Allow`, headers set on the outgoing request; with `ModifyResponse`,
+    /// the headers of the response.
+    #[uniffi(default = None)]
+    pub modified_headers: Option<Vec<HttpHeader>>,
+    /// With `ModifyResponse`, the body of the response.
+    #[uniffi(default = None)]
+    pub injected_body: Option<Vec<u8>>,
+}
+
 fn header_map_from(headers: Vec<HttpHeader>) -> Result<HeaderMap, WebViewError> {
     let mut map = HeaderMap::new();
     for header in headers {
@@ -147,6 +573,15 @@ fn cookie_from_record(cookie: WebViewCookie) -> Result<Cookie<'static>, WebViewE
     Ok(builder.build())
 }
 
+fn header_pairs_from(headers: Vec<HttpHeader>) -> Result<Vec<(String, String)>, WebViewError> {
+    // Validate names and values the same way `load_url_with_headers` does.
+    header_map_from(headers.clone())?;
+    Ok(headers
+        .into_iter()
+        .map(|header| (header.name, header.value))
+        .collect())
+}
+
 fn log_enabled() -> bool {
     static LOG_ENABLED: OnceLock<bool> = OnceLock::new();
     *LOG_ENABLED.get_or_init(|| {
@@ -160,523 +595,3404 @@ fn log_enabled() -> bool {
 }
 
 // ============================================================================
-// WebView Creation
+// Callback interfaces
 // ============================================================================
 
-fn create_webview_inner(
-    parent_handle: u64,
-    width: i32,
-    height: i32,
-    url: String,
-    user_agent: Option<String>,
-) -> Result<u64, WebViewError> {
-    let user_agent =
-        user_agent.and_then(|ua| {
-            let trimmed = ua.trim().to_string();
-            if trimmed.is_empty() { None } else { Some(trimmed) }
-        });
+#[uniffi::export(with_foreign)]
+pub trait AnimationFrameHandler: Send + Sync {
+    /// Called with the `requestAnimationFrame` timestamp, at most 60 times per second.
+    fn on_frame(&self, timestamp_ms: f64);
+}
 
-    eprintln!(
-        "[wrywebview] create_webview handle=0x{:x} size={}x{} url={} user_agent={}",
-        parent_handle,
-        width,
-        height,
-        url,
-        user_agent.as_deref().unwrap_or("<default>")
-    );
+#[uniffi::export(with_foreign)]
+pub trait PermissionHandler: Send + Sync {
+    /// Decides whether the page of WebView `id` may use `resource`.
+    fn on_request(&self, id: u64, resource: PermissionResource) -> PermissionResponse;
+}
 
-    let raw = raw_window_handle_from(parent_handle)?;
-    let window = RawWindow { raw };
+#[uniffi::export(with_foreign)]
+pub trait DownloadHandler: Send + Sync {
+    /// Returns the absolute path to save the download to, or an empty string to cancel it.
+    ///
+    /// `mime_type` is empty when the platform does not report it before the download starts.
+    fn on_start(&self, id: u64, url: String, suggested_filename: String, mime_type: String) -> String;
+    /// Reports download progress; `total` is `None` when the size is unknown.
+    /// Not reported on macOS.
+    fn on_progress(&self, id: u64, received: u64, total: Option<u64>);
+    fn on_complete(&self, id: u64, path: String);
+    fn on_error(&self, id: u64, error: String);
+}
 
-    #[cfg(target_os = "linux")]
-    ensure_gtk_initialized()?;
+#[uniffi::export(with_foreign)]
+pub trait NewWindowOpenedListener: Send + Sync {
+    /// Called when `LinkOpenMode::NewWebView` created WebView `new_id` for a link.
+    fn on_new_window_opened(&self, new_id: u64);
+}
 
-    let state = Arc::new(WebViewState::new(url.clone()));
-    let state_for_nav = Arc::clone(&state);
-    let state_for_load = Arc::clone(&state);
-    let state_for_title = Arc::clone(&state);
-    let state_for_ipc = Arc::clone(&state);
+#[uniffi::export(with_foreign)]
+pub trait NewWindowHandler: Send + Sync {
+    /// Called when the page of WebView `id` opens a new window (e.g. `window.open`).
+    ///
+    /// `features` uses the `window.open` syntax (`width=…,height=…,left=…,top=…`).
+    /// Returns the ID of an existing WebView that loads `url`, or `None` to cancel.
+    fn on_new_window(&self, id: u64, url: String, features: String) -> Option<u64>;
+}
 
-    let mut builder = WebViewBuilder::new()
-        .with_url(&url)
-        .with_bounds(make_bounds(0, 0, width, height));
+#[uniffi::export(with_foreign)]
+pub trait RequestInterceptor: Send + Sync {
+    /// Decides what happens to a request of WebView `id` matching the filter.
+    ///
+    /// Called on the UI thread while the request waits, so it must answer quickly.
+    fn on_request(&self, id: u64, request: InterceptedRequest) -> InterceptedResponse;
+}
 
-    if let Some(ua) = user_agent {
-        builder = builder.with_user_agent(ua);
-    }
+#[uniffi::export(with_foreign)]
+pub trait CertificateChainCallback: Send + Sync {
+    /// Receives the chain starting with the server certificate.
+    fn on_chain(&self, certs: Vec<CertificateInfo>);
+}
 
-    let webview = builder
-        .with_navigation_handler(move |new_url| {
-            eprintln!("[wrywebview] navigation_handler url={}", new_url);
-            state_for_nav.is_loading.store(true, Ordering::SeqCst);
-            if let Err(e) = state_for_nav.update_current_url(new_url.clone()) {
-                eprintln!("[wrywebview] navigation_handler state update failed: {}", e);
-            }
-            true
-        })
-        .with_on_page_load_handler(move |event, url| {
-            match event {
-                wry::PageLoadEvent::Started => {
-                    eprintln!("[wrywebview] page_load_handler event=Started url={}", url);
-                    state_for_load.is_loading.store(true, Ordering::SeqCst);
-                }
-                wry::PageLoadEvent::Finished => {
-                    eprintln!("[wrywebview] page_load_handler event=Finished url={}", url);
-                    state_for_load.is_loading.store(false, Ordering::SeqCst);
-                    if let Err(e) = state_for_load.update_current_url(url.clone()) {
-                        eprintln!("[wrywebview] page_load_handler state update failed: {}", e);
-                    }
-                }
-            }
-        })
-        .with_document_title_changed_handler(move |title| {
-            eprintln!("[wrywebview] title_changed title={}", title);
-            if let Err(e) = state_for_title.update_page_title(title) {
-                eprintln!("[wrywebview] title_changed state update failed: {}", e);
-            }
-        })
-        .with_ipc_handler(move |request| {
-            let url = request.uri().to_string();
-            let message = request.into_body();
-            eprintln!("[wrywebview] ipc url={} body_len={}", url, message.len());
-            if let Err(e) = state_for_ipc.push_ipc_message(message) {
-                eprintln!("[wrywebview] ipc queue push failed: {}", e);
-            }
-        })
-        .build_as_child(&window)?;
+#[uniffi::export(with_foreign)]
+pub trait JsDialogHandler: Send + Sync {
+    fn on_alert(&self, id: u64, message: String);
+    /// Also called for `beforeunload` confirmations.
+    fn on_confirm(&self, id: u64, message: String) -> bool;
+    /// Returns the entered text, or `None` to cancel the prompt.
+    fn on_prompt(&self, id: u64, message: String, default_value: String) -> Option<String>;
+}
 
-    // On Linux, set up focus handling for the GTK widget
-    #[cfg(target_os = "linux")]
-    {
-        use gdkx11::glib::translate::ToGlibPtr;
-        use gdkx11::glib::Cast;
-        use gdkx11::X11Display;
-        use gtk::prelude::WidgetExt;
+#[uniffi::export(with_foreign)]
+pub trait LayoutShiftCallback: Send + Sync {
+    fn on_layout_shift(&self, entry: LayoutShiftEntry);
+}
 
-        let gtk_widget = webview.webview();
-        gtk_widget.set_can_focus(true);
+#[uniffi::export(with_foreign)]
+pub trait FileChooserHandler: Send + Sync {
+    /// Returns the absolute paths of the chosen files, or an empty list if the
+    /// user cancelled. `accept` is the comma-separated `accept` attribute of the
+    /// input (empty on macOS, which does not expose it).
+    fn on_choose(&self, id: u64, accept: String, multiple: bool) -> Vec<String>;
+}
 
-        // Connect to button-press-event to grab focus when clicked using X11
-        gtk_widget.connect_button_press_event(|widget, _event| {
-            eprintln!("[wrywebview] button_press_event -> grab_focus");
+#[uniffi::export(with_foreign)]
+pub trait TlsErrorHandler: Send + Sync {
+    /// Decides whether to proceed despite an invalid server certificate for `url`.
+    fn on_error(&self, id: u64, url: String, error_description: String) -> TlsDecision;
+}
 
-            // Use X11 focus directly for proper keyboard input
-            if let Some(gdk_window) = widget.window() {
-                if let Some(display) = gdk::Display::default() {
-                    if let Ok(x11_display) = display.downcast::<X11Display>() {
-                        unsafe {
-                            let gdk_window_ptr: *mut gdk::ffi::GdkWindow = gdk_window.to_glib_none().0;
-                            let xid = gdkx11::ffi::gdk_x11_window_get_xid(
-                                gdk_window_ptr as *mut gdkx11::ffi::GdkX11Window,
-                            );
+#[uniffi::export(with_foreign)]
+pub trait AuthChallengeHandler: Send + Sync {
+    /// Returns the credentials for an HTTP Basic/Digest challenge, or `None` to cancel.
+    fn on_challenge(&self, id: u64, host: String, realm: String) -> Option<Credentials>;
+}
 
-                            if xid != 0 {
-                                let x11_display_ptr: *mut gdkx11::ffi::GdkX11Display = x11_display.to_glib_none().0;
-                                let x_display = gdkx11::ffi::gdk_x11_display_get_xdisplay(x11_display_ptr);
+#[uniffi::export(with_foreign)]
+pub trait SslWarningObserver: Send + Sync {
+    /// Informational only: the navigation is not affected.
+    fn on_ssl_warning(&self, url: String, warning_type: SslWarningType);
+}
 
-                                if !x_display.is_null() {
-                                    x11::xlib::XSetInputFocus(
-                                        x_display as *mut x11::xlib::Display,
-                                        xid,
-                                        x11::xlib::RevertToParent,
-                                        x11::xlib::CurrentTime,
-                                    );
-                                    eprintln!("[wrywebview] button_press XSetInputFocus xid=0x{:x}", xid);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+/// Notified when a page fails to load and the platform shows its own error page.
+#[uniffi::export(with_foreign)]
+pub trait ErrorPageHandler: Send + Sync {
+    /// `error_code` is the platform error: a `WebErrorStatus` on Windows, an
+    /// `NSURLError` code on macOS and a `GError` code on Linux.
+    fn on_error_page(&self, url: String, error_code: i32);
+}
 
-            widget.grab_focus();
-            gtk::glib::Propagation::Proceed
-        });
-        eprintln!("[wrywebview] gtk focus handling configured with X11 support");
-    }
+/// Notified when the page enters or leaves fullscreen (`Element.requestFullscreen()`).
+///
+/// The host is expected to resize the WebView to fill the display while fullscreen.
+#[uniffi::export(with_foreign)]
+pub trait FullscreenHandler: Send + Sync {
+    fn on_enter_fullscreen(&self, id: u64);
+    fn on_exit_fullscreen(&self, id: u64);
+}
 
-    let id = register(webview, state)?;
-    eprintln!("[wrywebview] create_webview success id={}", id);
-    Ok(id)
+#[uniffi::export(with_foreign)]
+pub trait ContextMenuHandler: Send + Sync {
+    /// Decides which menu to show at `x`, `y` (in WebView coordinates); `items`
+    /// describes the platform menu.
+    fn on_context_menu(
+        &self,
+        id: u64,
+        x: i32,
+        y: i32,
+        items: Vec<ContextMenuItem>,
+    ) -> ContextMenuDecision;
+    /// Called when a custom item is chosen.
+    fn on_item_selected(&self, id: u64, action_id: String);
 }
 
-#[uniffi::export]
-pub fn create_webview(
-    parent_handle: u64,
-    width: i32,
-    height: i32,
-    url: String,
-) -> Result<u64, WebViewError> {
-    #[cfg(target_os = "linux")]
-    {
-        return run_on_gtk_thread(move || {
-            create_webview_inner(parent_handle, width, height, url, None)
-        });
-    }
+#[uniffi::export(with_foreign)]
+pub trait ConsoleHandler: Send + Sync {
+    /// Receives a console call of the page; `source` and `line` locate the caller
+    /// when known (empty and 0 otherwise).
+    fn on_message(&self, id: u64, level: ConsoleLevel, message: String, source: String, line: u32);
+}
 
-    #[cfg(not(target_os = "linux"))]
-    run_on_main_thread(move || create_webview_inner(parent_handle, width, height, url, None))
+#[uniffi::export(with_foreign)]
+pub trait ResponseHeadersCallback: Send + Sync {
+    fn on_headers(&self, status: u16, headers: Vec<HttpHeader>);
 }
 
-#[uniffi::export]
-pub fn create_webview_with_user_agent(
-    parent_handle: u64,
-    width: i32,
-    height: i32,
-    url: String,
-    user_agent: Option<String>,
-) -> Result<u64, WebViewError> {
-    #[cfg(target_os = "linux")]
-    {
-        return run_on_gtk_thread(move || {
-            create_webview_inner(parent_handle, width, height, url, user_agent)
-        });
+/// Handles an IPC message posted by one of the injected scripts.
+fn handle_internal_message(state: &WebViewState, kind: &str, payload: &str) {
+    match kind {
+        "frame" => {
+            let Some(handler) = state.animation_frame_handler.get() else {
+                return;
+            };
+            match payload.parse::<f64>() {
+                Ok(timestamp_ms) => handler.on_frame(timestamp_ms),
+                Err(_) => eprintln!("[wrywebview] invalid frame timestamp: {}", payload),
+            }
+        }
+        "layout-shift" => {
+            let Some(callback) = state.layout_shift_callback.get() else {
+                return;
+            };
+            match serde_json::from_str::<LayoutShiftEntry>(payload) {
+                Ok(entry) => callback.on_layout_shift(entry),
+                Err(e) => eprintln!("[wrywebview] invalid layout shift entry: {}", e),
+            }
+        }
+        "file-chooser" => handle_file_chooser_message(state, payload),
+        "history" => match serde_json::from_str::<HistoryPosition>(payload) {
+            Ok(position) => state.set_history_position(position.length, position.index),
+            Err(e) => eprintln!("[wrywebview] invalid history position: {}", e),
+        },
+        "console" => match serde_json::from_str::<ConsoleMessage>(payload) {
+            Ok(message) => report_console_message(
+                state,
+                message.level,
+                message.message,
+                message.source,
+                message.line,
+            ),
+            Err(e) => eprintln!("[wrywebview] invalid console message: {}", e),
+        },
+        _ => eprintln!("[wrywebview] unknown internal message kind={}", kind),
     }
-
-    #[cfg(not(target_os = "linux"))]
-    run_on_main_thread(move || create_webview_inner(parent_handle, width, height, url, user_agent))
 }
 
 // ============================================================================
-// Bounds Management
+// Downloads
 // ============================================================================
 
-fn set_bounds_inner(id: u64, x: i32, y: i32, width: i32, height: i32) -> Result<(), WebViewError> {
-    if log_enabled() {
-        eprintln!(
-            "[wrywebview] set_bounds id={} pos=({}, {}) size={}x{}",
-            id, x, y, width, height
-        );
+/// Lets the download handler pick the destination of a download; returns `false` to cancel.
+fn handle_download_started(state: &WebViewState, url: String, path: &mut PathBuf) -> bool {
+    let Some(handler) = state.download_handler.get() else {
+        return true;
+    };
+    let suggested_filename = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    eprintln!(
+        "[wrywebview] download_started url={} suggested={}",
+        url, suggested_filename
+    );
+    let target = handler.on_start(state.id(), url, suggested_filename, String::new());
+    if target.is_empty() {
+        eprintln!("[wrywebview] download cancelled by handler");
+        return false;
     }
-    let bounds = make_bounds(x, y, width, height);
-    with_webview(id, |webview| webview.set_bounds(bounds).map_err(WebViewError::from))
+    *path = PathBuf::from(target);
+    true
 }
 
-#[uniffi::export]
-pub fn set_bounds(id: u64, x: i32, y: i32, width: i32, height: i32) -> Result<(), WebViewError> {
-    #[cfg(target_os = "macos")]
-    {
-        if MainThreadMarker::new().is_some() {
-            return set_bounds_inner(id, x, y, width, height);
-        }
-        DispatchQueue::main().exec_async(move || {
-            let _ = set_bounds_inner(id, x, y, width, height);
-        });
-        return Ok(());
+fn handle_download_completed(state: &WebViewState, url: String, path: Option<PathBuf>, success: bool) {
+    eprintln!("[wrywebview] download_completed url={} success={}", url, success);
+    let Some(handler) = state.download_handler.get() else {
+        return;
+    };
+    match path {
+        Some(path) if success => handler.on_complete(state.id(), path.to_string_lossy().into_owned()),
+        _ => handler.on_error(state.id(), format!("download of {} failed", url)),
     }
+}
 
-    #[cfg(target_os = "linux")]
-    {
-        return run_on_gtk_thread(move || set_bounds_inner(id, x, y, width, height));
-    }
+/// Registers the handler choosing download destinations and receiving download events.
+///
+/// Without a handler, downloads are saved to the platform's default location.
+#[uniffi::export]
+pub fn set_download_handler(id: u64, handler: Arc<dyn DownloadHandler>) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_download_handler id={}", id);
+    get_state(id)?.download_handler.set(Some(handler))
+}
 
-    #[cfg(target_os = "windows")]
-    {
-        run_on_main_thread(move || set_bounds_inner(id, x, y, width, height))
-    }
+#[uniffi::export]
+pub fn clear_download_handler(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] clear_download_handler id={}", id);
+    get_state(id)?.download_handler.set(None)
 }
 
 // ============================================================================
-// Navigation
+// New Windows
 // ============================================================================
 
-fn load_url_inner(id: u64, url: String) -> Result<(), WebViewError> {
-    eprintln!("[wrywebview] load_url id={} url={}", id, url);
-    if let Ok(state) = get_state(id) {
-        state.is_loading.store(true, Ordering::SeqCst);
+/// Formats the features of a new-window request using the `window.open` syntax.
+fn window_features_string(features: &NewWindowFeatures) -> String {
+    let mut parts = Vec::new();
+    if let Some(size) = features.size {
+        parts.push(format!("width={}", size.width.round() as i64));
+        parts.push(format!("height={}", size.height.round() as i64));
     }
-    with_webview(id, |webview| webview.load_url(&url).map_err(WebViewError::from))
-}
-
-#[uniffi::export]
-pub fn load_url(id: u64, url: String) -> Result<(), WebViewError> {
-    #[cfg(target_os = "linux")]
-    {
-        return run_on_gtk_thread(move || load_url_inner(id, url));
+    if let Some(position) = features.position {
+        parts.push(format!("left={}", position.x.round() as i64));
+        parts.push(format!("top={}", position.y.round() as i64));
     }
+    parts.join(",")
+}
 
-    #[cfg(not(target_os = "linux"))]
+/// Routes a new-window request to the `NewWindowHandler`, or else according to
+/// the WebView's `LinkOpenMode`.
+fn handle_new_window_request(
+    state: &WebViewState,
+    url: String,
+    features: NewWindowFeatures,
+) -> NewWindowResponse {
+    if let Some(handler) = state.new_window_handler.get() {
+        let features = window_features_string(&features);
+        eprintln!("[wrywebview] new_window url={} features={}", url, features);
+        match handler.on_new_window(state.id(), url.clone(), features) {
+            Some(target_id) => {
+                if let Err(e) = load_url_inner(target_id, url) {
+                    eprintln!("[wrywebview] new_window load into id={} failed: {}", target_id, e);
+                }
+            }
+            None => eprintln!("[wrywebview] new_window cancelled"),
+        }
+        return NewWindowResponse::Deny;
+    }
+
+    let mode = match state.link_open_mode() {
+        Ok(Some(mode)) => mode,
+        Ok(None) => return NewWindowResponse::Allow,
+        Err(e) => {
+            eprintln!("[wrywebview] new_window link mode unavailable: {}", e);
+            return NewWindowResponse::Allow;
+        }
+    };
+    eprintln!("[wrywebview] new_window url={} mode={:?}", url, mode);
+
+    match mode {
+        LinkOpenMode::SameWebView => {
+            if let Err(e) = load_url_inner(state.id(), url) {
+                eprintln!("[wrywebview] new_window same webview failed: {}", e);
+            }
+        }
+        LinkOpenMode::NewWebView {
+            parent_handle,
+            width,
+            height,
+        } => {
+            // WebView2 cannot create a WebView whose browser arguments differ from
+            // the shared environment, so the popup keeps the settings of its opener.
+            let config = WebViewConfig {
+                proxy: state.proxy().unwrap_or_default(),
+                autoplay_policy: state.autoplay_policy().unwrap_or_default(),
+                javascript_enabled: state.javascript_enabled.load(Ordering::Acquire),
+                pinch_to_zoom: state.pinch_zoom_enabled.load(Ordering::Acquire),
+                hardware_acceleration: state.creation_config.hardware_acceleration,
+                allow_file_access: state.allow_file_access.load(Ordering::Acquire),
+                devtools_enabled: state.creation_config.devtools_enabled,
+                data_store_identifier: state.creation_config.data_store_identifier.clone(),
+                group_id: state.group_id().unwrap_or_default(),
+                ..WebViewConfig::from_url(url)
+            };
+            match create_webview_inner(parent_handle, 0, 0, width, height, config) {
+                Ok(new_id) => {
+                    if let Some(listener) = state.new_window_opened_listener.get() {
+                        listener.on_new_window_opened(new_id);
+                    }
+                }
+                Err(e) => eprintln!("[wrywebview] new_window create failed: {}", e),
+            }
+        }
+        LinkOpenMode::SystemBrowser => {
+            if let Err(e) = platform::open_in_system_browser(&url) {
+                eprintln!("[wrywebview] new_window system browser failed: {}", e);
+            }
+        }
+        LinkOpenMode::Block => {}
+    }
+
+    NewWindowResponse::Deny
+}
+
+/// Controls where links requesting a new window are opened.
+///
+/// Until a mode is set, the platform default of the WebView applies.
+#[uniffi::export]
+pub fn set_link_open_mode(id: u64, mode: LinkOpenMode) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_link_open_mode id={} mode={:?}", id, mode);
+    get_state(id)?.set_link_open_mode(Some(mode))
+}
+
+/// Registers the listener notified when `LinkOpenMode::NewWebView` creates a WebView.
+#[uniffi::export]
+pub fn set_new_window_opened_listener(
+    id: u64,
+    listener: Arc<dyn NewWindowOpenedListener>,
+) -> Result<(), WebViewError> {
+    get_state(id)?.new_window_opened_listener.set(Some(listener))
+}
+
+/// Registers a handler deciding where `window.open()` and `target="_blank"` links load.
+///
+/// Takes precedence over the `LinkOpenMode` of the WebView.
+#[uniffi::export]
+pub fn set_new_window_handler(
+    id: u64,
+    handler: Arc<dyn NewWindowHandler>,
+) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_new_window_handler id={}", id);
+    get_state(id)?.new_window_handler.set(Some(handler))
+}
+
+/// Removes the new-window handler; the `LinkOpenMode` applies again.
+#[uniffi::export]
+pub fn clear_new_window_handler(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] clear_new_window_handler id={}", id);
+    get_state(id)?.new_window_handler.set(None)
+}
+
+// ============================================================================
+// WebView Creation
+// ============================================================================
+
+/// Re-evaluates the scripts that must survive navigations (see `WebViewState::page_scripts`).
+fn apply_page_scripts(state: &WebViewState) {
+    let scripts = match state.page_scripts() {
+        Ok(scripts) => scripts,
+        Err(e) => {
+            eprintln!("[wrywebview] page scripts unavailable: {}", e);
+            return;
+        }
+    };
+    if scripts.is_empty() {
+        return;
+    }
+    let result = with_webview(state.id(), |webview| {
+        for script in &scripts {
+            webview.evaluate_script(script)?;
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        eprintln!("[wrywebview] page scripts failed: {}", e);
+    }
+}
+
+fn handle_navigation(state: &WebViewState, url: String) -> bool {
+    eprintln!("[wrywebview] navigation_handler url={}", url);
+    #[cfg(not(target_os = "windows"))]
+    if !intercept_navigation(state, &url) {
+        return false;
+    }
+    state.is_loading.store(true, Ordering::Release);
+    state.emit_event(WebViewEvent::NavigationStarted { url: url.clone() });
+    if let Err(e) = state.update_current_url(url) {
+        eprintln!("[wrywebview] navigation_handler state update failed: {}", e);
+    }
+    true
+}
+
+fn handle_page_load(state: &WebViewState, event: wry::PageLoadEvent, url: String) {
+    match event {
+        wry::PageLoadEvent::Started => {
+            eprintln!("[wrywebview] page_load_handler event=Started url={}", url);
+            state.is_loading.store(true, Ordering::Release);
+            state.set_load_progress(0.0);
+            if let Err(e) = state.clear_ssl_warnings() {
+                eprintln!("[wrywebview] page_load_handler ssl reset failed: {}", e);
+            }
+            if let Err(e) = state.set_main_response(None) {
+                eprintln!("[wrywebview] page_load_handler response reset failed: {}", e);
+            }
+        }
+        wry::PageLoadEvent::Finished => {
+            eprintln!("[wrywebview] page_load_handler event=Finished url={}", url);
+            state.is_loading.store(false, Ordering::Release);
+            state.set_load_progress(1.0);
+            if let Err(e) = state.update_current_url(url.clone()) {
+                eprintln!("[wrywebview] page_load_handler state update failed: {}", e);
+            }
+            if let Err(e) = state.reset_auth_attempts() {
+                eprintln!("[wrywebview] page_load_handler auth reset failed: {}", e);
+            }
+            apply_page_scripts(state);
+            if let Some(script) = state.take_initial_script() {
+                if let Err(e) = run_script(state.id(), script) {
+                    eprintln!("[wrywebview] initial script failed: {}", e);
+                }
+            }
+            if let Err(e) = run_script(state.id(), &scripts::history_script()) {
+                eprintln!("[wrywebview] page_load_handler history query failed: {}", e);
+            }
+            report_page_security(state, &url);
+            state.emit_event(WebViewEvent::PageLoaded { url });
+        }
+    }
+}
+
+/// Dispatches a message posted by the page: internal ones to their handler,
+/// the others to the queue read by `drain_ipc_messages`.
+fn handle_ipc_message(state: &WebViewState, request: wry::http::Request<String>) {
+    let url = request.uri().to_string();
+    let message = request.into_body();
+    if let Some((kind, payload)) = scripts::parse_internal_message(&message) {
+        handle_internal_message(state, kind, payload);
+        return;
+    }
+    eprintln!("[wrywebview] ipc url={} body_len={}", url, message.len());
+    state.emit_event(WebViewEvent::IpcMessage { message: message.clone() });
+    if let Err(e) = state.push_ipc_message(message) {
+        eprintln!("[wrywebview] ipc queue push failed: {}", e);
+    }
+}
+
+/// Rejects a malformed URL with `InvalidUrl`, and a width or height below 1 with
+/// `InvalidArgument`; shared by every path that creates a WebView.
+fn check_creation(url: &str, width: i32, height: i32) -> Result<(), WebViewError> {
+    if url::Url::parse(url).is_err() {
+        return Err(WebViewError::InvalidUrl(url.to_string()));
+    }
+    check_size(width.into(), height.into())
+}
+
+/// Names the web context of a WebView (see `state::web_context`), or `None` for
+/// the default one. WebKitGTK sets the proxy on the whole context, so on Linux a
+/// WebView with a proxy and no data store gets a context for that proxy instead
+/// of rerouting the WebViews of the default context.
+fn web_context_key(config: &WebViewConfig) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    if config.data_store_identifier.is_none() {
+        if let Some(proxy) = &config.proxy {
+            return Some(format!("proxy {}", proxy.url()));
+        }
+    }
+    config.data_store_identifier.clone()
+}
+
+/// Fails with `SharedSettingsConflict` if a live WebView sharing the web context
+/// (Linux) or the WebView2 environment (Windows) of `config` was set up with
+/// other settings: applying `config` would reroute it on Linux, and WebView2
+/// would refuse the creation with an opaque error on Windows.
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+fn check_shared_settings(config: &WebViewConfig) -> Result<(), WebViewError> {
+    #[cfg(target_os = "windows")]
+    let browser_args = |config: &WebViewConfig| {
+        platform::browser_args(
+            config.proxy.as_ref(),
+            config.hardware_acceleration && platform::hardware_acceleration_enabled(),
+            config.allow_file_access,
+        )
+    };
+    let context_key = web_context_key(config);
+    for other in state::registered_states() {
+        if web_context_key(&other.creation_config) != context_key {
+            continue;
+        }
+        #[cfg(target_os = "linux")]
+        if other.proxy()? != config.proxy {
+            return Err(WebViewError::SharedSettingsConflict(format!(
+                "webview {} uses the same web context with another proxy",
+                other.id()
+            )));
+        }
+        #[cfg(target_os = "windows")]
+        if browser_args(&other.creation_config) != browser_args(config) {
+            return Err(WebViewError::SharedSettingsConflict(format!(
+                "webview {} uses the same WebView2 environment with another proxy, GPU or \
+                 file access setting",
+                other.id()
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn create_webview_inner(
+    parent_handle: u64,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    mut config: WebViewConfig,
+) -> Result<u64, WebViewError> {
+    check_creation(&config.url, width, height)?;
+    if let Some(group_id) = config.group_id {
+        config.data_store_identifier = Some(group_data_store(group_id, &config)?);
+    }
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    check_shared_settings(&config)?;
+    let context_key = web_context_key(&config);
+    let state = Arc::new(WebViewState::new(&config));
+    let WebViewConfig {
+        url,
+        user_agent,
+        proxy,
+        autoplay_policy,
+        javascript_enabled,
+        pinch_to_zoom,
+        hardware_acceleration,
+        allow_file_access,
+        devtools_enabled,
+        transparent,
+        initialization_timeout_ms: _,
+        initial_script: _,
+        data_store_identifier: _,
+        group_id,
+    } = config;
+    let hardware_acceleration =
+        hardware_acceleration && platform::hardware_acceleration_enabled();
+    let user_agent =
+        user_agent.and_then(|ua| {
+            let trimmed = ua.trim().to_string();
+            if trimmed.is_empty() { None } else { Some(trimmed) }
+        });
+
+    eprintln!(
+        "[wrywebview] create_webview handle=0x{:x} size={}x{} url={} user_agent={}",
+        parent_handle,
+        width,
+        height,
+        url,
+        user_agent.as_deref().unwrap_or("<default>")
+    );
+
+    let raw = raw_window_handle_from(parent_handle)?;
+    let window = RawWindow { raw };
+
+    #[cfg(target_os = "linux")]
+    ensure_gtk_initialized()?;
+
+    state::ensure_capacity()?;
+    state.set_parent_handle(parent_handle)?;
+
+    let mut web_context = context_key.as_deref().map(state::web_context).transpose()?;
+    let mut builder = match web_context.as_deref_mut() {
+        Some(context) => WebViewBuilder::new_with_web_context(context),
+        None => WebViewBuilder::new(),
+    };
+    builder = builder
+        .with_url(&url)
+        .with_bounds(make_bounds(x, y, width, height, None));
+
+    if let Some(ua) = user_agent {
+        builder = builder.with_user_agent(ua);
+    }
+
+    if autoplay_policy == Some(AutoplayPolicy::Allow) {
+        builder = builder.with_autoplay(true);
+    }
+
+    if !javascript_enabled {
+        builder = builder.with_javascript_disabled();
+    }
+
+    if devtools_enabled || platform::remote_debugging_port().is_some() {
+        builder = builder.with_devtools(true);
+    }
+
+    if transparent {
+        builder = builder.with_transparent(true);
+    }
+
+    #[cfg(target_os = "macos")]
+    if let Some(identifier) = &context_key {
+        builder = platform::with_data_store_identifier(builder, identifier);
+    }
+    #[cfg(target_os = "macos")]
+    if let Some(proxy) = &proxy {
+        builder = platform::with_proxy(builder, proxy)?;
+    }
+    #[cfg(target_os = "macos")]
+    if !hardware_acceleration {
+        eprintln!("[wrywebview] create_webview hardware acceleration cannot be disabled on macOS");
+    }
+    #[cfg(target_os = "windows")]
+    {
+        builder = platform::with_browser_args(
+            builder,
+            proxy.as_ref(),
+            hardware_acceleration,
+            allow_file_access,
+        );
+    }
+
+    let webview = builder
+        .with_navigation_handler({
+            let state = Arc::clone(&state);
+            move |url| handle_navigation(&state, url)
+        })
+        .with_on_page_load_handler({
+            let state = Arc::clone(&state);
+            move |event, url| handle_page_load(&state, event, url)
+        })
+        .with_document_title_changed_handler({
+            let state = Arc::clone(&state);
+            move |title| {
+                eprintln!("[wrywebview] title_changed title={}", title);
+                state.emit_event(WebViewEvent::TitleChanged { title: title.clone() });
+                if let Err(e) = state.update_page_title(title) {
+                    eprintln!("[wrywebview] title_changed state update failed: {}", e);
+                }
+            }
+        })
+        .with_ipc_handler({
+            let state = Arc::clone(&state);
+            move |request| handle_ipc_message(&state, request)
+        })
+        .with_new_window_req_handler({
+            let state = Arc::clone(&state);
+            move |url, features| handle_new_window_request(&state, url, features)
+        })
+        .with_download_started_handler({
+            let state = Arc::clone(&state);
+            move |url, path| handle_download_started(&state, url, path)
+        })
+        .with_download_completed_handler({
+            let state = Arc::clone(&state);
+            move |url, path, success| handle_download_completed(&state, url, path, success)
+        })
+        .build_as_child(&window)?;
+    // Guards the WebView until it is registered, so every early return below destroys it.
+    let webview = PendingWebView::new(webview);
+
+    // On Linux, set up focus handling for the GTK widget
+    #[cfg(target_os = "linux")]
+    {
+        use gdkx11::glib::translate::ToGlibPtr;
+        use gdkx11::glib::Cast;
+        use gdkx11::X11Display;
+        use gtk::prelude::WidgetExt;
+
+        let gtk_widget = webview.webview();
+        gtk_widget.set_can_focus(true);
+
+        // Connect to button-press-event to grab focus when clicked using X11
+        gtk_widget.connect_button_press_event(|widget, _event| {
+            eprintln!("[wrywebview] button_press_event -> grab_focus");
+
+            // Use X11 focus directly for proper keyboard input
+            if let Some(gdk_window) = widget.window() {
+                if let Some(display) = gdk::Display::default() {
+                    if let Ok(x11_display) = display.downcast::<X11Display>() {
+                        unsafe {
+                            let gdk_window_ptr: *mut gdk::ffi::GdkWindow = gdk_window.to_glib_none().0;
+                            let xid = gdkx11::ffi::gdk_x11_window_get_xid(
+                                gdk_window_ptr as *mut gdkx11::ffi::GdkX11Window,
+                            );
+
+                            if xid != 0 {
+                                let x11_display_ptr: *mut gdkx11::ffi::GdkX11Display = x11_display.to_glib_none().0;
+                                let x_display = gdkx11::ffi::gdk_x11_display_get_xdisplay(x11_display_ptr);
+
+                                if !x_display.is_null() {
+                                    x11::xlib::XSetInputFocus(
+                                        x_display as *mut x11::xlib::Display,
+                                        xid,
+                                        x11::xlib::RevertToParent,
+                                        x11::xlib::CurrentTime,
+                                    );
+                                    eprintln!("[wrywebview] button_press XSetInputFocus xid=0x{:x}", xid);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            widget.grab_focus();
+            gtk::glib::Propagation::Proceed
+        });
+        eprintln!("[wrywebview] gtk focus handling configured with X11 support");
+    }
+
+    // WebKitGTK takes the proxy from the web context, which exists once the WebView is built.
+    #[cfg(target_os = "linux")]
+    if let Some(proxy) = &proxy {
+        platform::set_proxy(&webview, proxy)?;
+    } else if web_context.is_some() {
+        // A destroyed WebView of the same data store may have left its proxy behind.
+        platform::clear_proxy(&webview)?;
+    }
+    #[cfg(target_os = "linux")]
+    if let Some(policy) = autoplay_policy {
+        platform::set_autoplay_policy(&webview, policy)?;
+    }
+    #[cfg(target_os = "linux")]
+    if !hardware_acceleration {
+        platform::disable_hardware_acceleration(&webview)?;
+    }
+    #[cfg(target_os = "linux")]
+    if transparent {
+        platform::set_transparent(&webview)?;
+    }
+    #[cfg(not(target_os = "windows"))]
+    if allow_file_access {
+        platform::set_allow_file_access(&webview, true)?;
+    }
+    if !pinch_to_zoom {
+        if let Err(e) = platform::set_pinch_zoom_enabled(&webview, false) {
+            eprintln!("[wrywebview] create_webview pinch zoom not disabled: {}", e);
+        }
+    }
+
+    platform::install_handlers(&webview, &state)?;
+
+    let id = register(webview, Arc::clone(&state))?;
+    if let Some(group_id) = group_id {
+        if let Err(e) = state::join_group(group_id, &state) {
+            let _ = state::unregister(id);
+            return Err(e);
+        }
+    }
+    eprintln!("[wrywebview] create_webview success id={}", id);
+    Ok(id)
+}
+
+#[uniffi::export]
+pub fn create_webview(
+    parent_handle: u64,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    url: String,
+) -> Result<u64, WebViewError> {
+    create_webview_with_config(parent_handle, x, y, width, height, WebViewConfig::from_url(url))
+}
+
+#[uniffi::export]
+pub fn create_webview_with_user_agent(
+    parent_handle: u64,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    url: String,
+    user_agent: Option<String>,
+) -> Result<u64, WebViewError> {
+    let config = WebViewConfig { user_agent, ..WebViewConfig::from_url(url) };
+    create_webview_with_config(parent_handle, x, y, width, height, config)
+}
+
+/// Outcome of a creation posted to the UI thread, settled by whichever side gets
+/// there first: the UI thread once the WebView is registered, or the caller once
+/// it stops waiting.
+enum Creation {
+    Pending,
+    Created(u64),
+    Abandoned,
+}
+
+/// Creates a WebView with `config`.
+///
+/// A malformed URL fails with `InvalidUrl`, and a width or height below 1 with
+/// `InvalidArgument`, before anything runs on the UI thread.
+///
+/// If the UI thread has not created the WebView within
+/// `config.initialization_timeout_ms`, the call fails with
+/// `InitializationTimeout`. A creation that is still running then completes,
+/// and the WebView is destroyed right away instead of being leaked.
+#[uniffi::export]
+pub fn create_webview_with_config(
+    parent_handle: u64,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    config: WebViewConfig,
+) -> Result<u64, WebViewError> {
+    check_creation(&config.url, width, height)?;
+
+    let timeout_ms = config.initialization_timeout_ms;
+    let creation = Arc::new(Mutex::new(Creation::Pending));
+    let lock_poisoned = || WebViewError::Internal("creation lock poisoned".to_string());
+    let task = {
+        let creation = Arc::clone(&creation);
+        move || {
+            let id = create_webview_inner(parent_handle, x, y, width, height, config)?;
+            let mut creation = creation.lock().map_err(|_| lock_poisoned())?;
+            if matches!(*creation, Creation::Abandoned) {
+                drop(creation);
+                eprintln!("[wrywebview] create_webview timed out, destroying id={}", id);
+                destroy_webview_inner(id)?;
+                return Err(WebViewError::InitializationTimeout(timeout_ms));
+            }
+            *creation = Creation::Created(id);
+            Ok(id)
+        }
+    };
+
+    #[cfg(target_os = "linux")]
+    let result = run_on_gtk_thread_timeout(task, timeout_ms);
+    #[cfg(not(target_os = "linux"))]
+    let result = run_on_main_thread_timeout(task, timeout_ms);
+
+    match result {
+        Err(WebViewError::MainThreadTimeout(_)) => {
+            let mut creation = creation.lock().map_err(|_| lock_poisoned())?;
+            if let Creation::Created(id) = *creation {
+                return Ok(id);
+            }
+            *creation = Creation::Abandoned;
+            Err(WebViewError::InitializationTimeout(timeout_ms))
+        }
+        result => result,
+    }
+}
+
+/// Sets how many WebViews may exist at once (64 by default, at least 1).
+///
+/// Creating a WebView beyond the limit fails with `TooManyWebViews`, which
+/// catches runaway creation before it exhausts the native window handles.
+#[uniffi::export]
+pub fn set_max_webviews(max: u32) {
+    eprintln!("[wrywebview] set_max_webviews max={}", max);
+    state::set_max_webviews(max);
+}
+
+/// Fluent Rust API over `WebViewConfig` and `create_webview_with_config`, for
+/// Rust callers that do not go through the UniFFI bindings.
+///
+/// Without `with_bounds`, the WebView is created at (0, 0) with a size of 800x600.
+#[derive(Debug, Clone)]
+pub struct NativeWebViewBuilder {
+    config: WebViewConfig,
+    bounds: (i32, i32, i32, i32),
+}
+
+impl Default for NativeWebViewBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NativeWebViewBuilder {
+    /// Starts from the default configuration, loading `about:blank`.
+    pub fn new() -> Self {
+        Self {
+            config: WebViewConfig::from_url("about:blank".to_string()),
+            bounds: (0, 0, 800, 600),
+        }
+    }
+
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.config.url = url.to_string();
+        self
+    }
+
+    pub fn with_bounds(mut self, x: i32, y: i32, width: i32, height: i32) -> Self {
+        self.bounds = (x, y, width, height);
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: &str) -> Self {
+        self.config.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    pub fn with_javascript_enabled(mut self, enabled: bool) -> Self {
+        self.config.javascript_enabled = enabled;
+        self
+    }
+
+    pub fn with_proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.config.proxy = Some(proxy);
+        self
+    }
+
+    /// Adds the WebView to a group, whose data store it then uses.
+    pub fn with_group(mut self, group_id: u64) -> Self {
+        self.config.group_id = Some(group_id);
+        self
+    }
+
+    /// Replaces the whole configuration, for the options without a dedicated method.
+    pub fn with_config(mut self, config: WebViewConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Creates the WebView in `parent_handle` and returns its ID.
+    pub fn build(self, parent_handle: u64) -> Result<u64, WebViewError> {
+        let (x, y, width, height) = self.bounds;
+        create_webview_with_config(parent_handle, x, y, width, height, self.config)
+    }
+}
+
+// ============================================================================
+// WebView Groups
+// ============================================================================
+
+/// Creates a group of WebViews sharing cookies and storage, e.g. the tabs of a
+/// browser session.
+///
+/// Each group owns a data store (`WKWebsiteDataStore`, `CoreWebView2Profile` or
+/// `WebKitWebContext`), isolated from the default store and from other groups.
+/// WebViews use it when they are created with `WebViewConfig::group_id`.
+#[uniffi::export]
+pub fn create_webview_group() -> Result<u64, WebViewError> {
+    let group_id = state::create_group()?;
+    eprintln!("[wrywebview] create_webview_group group_id={}", group_id);
+    Ok(group_id)
+}
+
+/// Returns the data store identifier of the group a WebView is created in, or
+/// fails if the group does not exist or `config` names another store.
+fn group_data_store(group_id: u64, config: &WebViewConfig) -> Result<String, WebViewError> {
+    state::group_members(group_id)?;
+    let identifier = state::group_data_store_identifier(group_id);
+    match &config.data_store_identifier {
+        Some(other) if *other != identifier => Err(WebViewError::InvalidArgument(format!(
+            "data store {} conflicts with the store of group {}",
+            other, group_id
+        ))),
+        _ => Ok(identifier),
+    }
+}
+
+/// Adds a WebView to a group, moving it out of its previous group.
+///
+/// The data store of a WebView is fixed at creation, so only WebViews created in
+/// the group (see `WebViewConfig::group_id`) can join it again after leaving it;
+/// others fail with `DataStoreMismatch`.
+#[uniffi::export]
+pub fn add_webview_to_group(group_id: u64, id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] add_webview_to_group group_id={} id={}", group_id, id);
+    let state = get_state(id)?;
+    state::group_members(group_id)?;
+    let identifier = state::group_data_store_identifier(group_id);
+    if state.creation_config.data_store_identifier.as_deref() != Some(identifier.as_str()) {
+        return Err(WebViewError::DataStoreMismatch(id, group_id));
+    }
+    state::join_group(group_id, &state)
+}
+
+#[uniffi::export]
+pub fn remove_webview_from_group(group_id: u64, id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] remove_webview_from_group group_id={} id={}", group_id, id);
+    state::leave_group(group_id, &get_state(id)?)
+}
+
+// ============================================================================
+// Bounds Management
+// ============================================================================
+
+/// Rejects the empty or negative sizes that layout passes can produce.
+///
+/// Only host-provided sizes are checked: sizes derived from the parent, such as
+/// auto-resize, are clamped to 1x1 by `make_bounds` instead.
+fn check_size(width: f64, height: f64) -> Result<(), WebViewError> {
+    let valid = width.is_finite() && width >= 1.0 && height.is_finite() && height >= 1.0;
+    if !valid {
+        return Err(WebViewError::InvalidArgument(format!("invalid size {}x{}", width, height)));
+    }
+    Ok(())
+}
+
+/// Applies logical bounds, with the size clamped to the WebView's min and max sizes.
+fn apply_bounds(id: u64, x: f64, y: f64, width: f64, height: f64) -> Result<(), WebViewError> {
+    let state = get_state(id)?;
+    let (width, height) = state.clamp_size(width, height);
+    let bounds = make_bounds_f64(x, y, width, height, state.scale_factor()?);
+    with_webview(id, |webview| webview.set_bounds(bounds).map_err(WebViewError::from))
+}
+
+fn set_bounds_inner(id: u64, x: i32, y: i32, width: i32, height: i32) -> Result<(), WebViewError> {
+    if log_enabled() {
+        eprintln!(
+            "[wrywebview] set_bounds id={} pos=({}, {}) size={}x{}",
+            id, x, y, width, height
+        );
+    }
+    get_state(id)?.set_pending_bounds((x, y, width, height))
+}
+
+/// Moves and resizes the WebView. `x` and `y` may be negative, e.g. to render
+/// off-screen; a width or height below 1 fails with `InvalidArgument`.
+///
+/// The bounds are only recorded: they take effect on the next
+/// `apply_pending_bounds`, so that the many calls of a resize animation cost a
+/// single native layout per frame. If applying the previous bounds failed, the
+/// call fails once with `BoundsNotApplied` instead, without recording the new ones.
+#[uniffi::export]
+pub fn set_bounds(id: u64, x: i32, y: i32, width: i32, height: i32) -> Result<(), WebViewError> {
+    check_size(width.into(), height.into())?;
+    if let Some(message) = get_state(id)?.take_bounds_error()? {
+        return Err(WebViewError::BoundsNotApplied(message));
+    }
+    set_bounds_inner(id, x, y, width, height)
+}
+
+fn apply_pending_bounds_inner(id: u64, bounds: (i32, i32, i32, i32)) -> Result<(), WebViewError> {
+    let (x, y, width, height) = bounds;
+    if log_enabled() {
+        eprintln!(
+            "[wrywebview] apply_pending_bounds id={} pos=({}, {}) size={}x{}",
+            id, x, y, width, height
+        );
+    }
+    apply_bounds(id, x.into(), y.into(), width.into(), height.into())
+}
+
+/// Applies the latest bounds given to `set_bounds`, if any are pending.
+///
+/// Meant to be called from the host's frame clock on the frames following a
+/// `set_bounds`. Without pending bounds it returns right away, without waiting
+/// for the UI thread.
+///
+/// A failure is returned here and reported again by the next `set_bounds`, for
+/// callers that only see the `set_bounds` results. On macOS, off the main
+/// thread, the bounds are applied asynchronously so the caller never waits for
+/// the main thread, and a failure is only reported by the next `set_bounds`.
+#[uniffi::export]
+pub fn apply_pending_bounds(id: u64) -> Result<(), WebViewError> {
+    let state = get_state(id)?;
+    let Some(bounds) = state.take_pending_bounds()? else {
+        return Ok(());
+    };
+
+    #[cfg(target_os = "macos")]
+    if platform::macos::MainThreadMarker::new().is_none() {
+        platform::macos::DispatchQueue::main().exec_async(move || {
+            if let Err(error) = apply_pending_bounds_inner(id, bounds) {
+                eprintln!("[wrywebview] apply_pending_bounds id={} failed: {}", id, error);
+                let _ = state.set_bounds_error(error.to_string());
+            }
+        });
+        return Ok(());
+    }
+
+    #[cfg(target_os = "linux")]
+    let result = run_on_gtk_thread(move || apply_pending_bounds_inner(id, bounds));
+    #[cfg(not(target_os = "linux"))]
+    let result = run_on_main_thread(move || apply_pending_bounds_inner(id, bounds));
+
+    if let Err(error) = &result {
+        state.set_bounds_error(error.to_string())?;
+    }
+    result
+}
+
+fn set_bounds_f64_inner(
+    id: u64,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+) -> Result<(), WebViewError> {
+    if log_enabled() {
+        eprintln!(
+            "[wrywebview] set_bounds_f64 id={} pos=({}, {}) size={}x{}",
+            id, x, y, width, height
+        );
+    }
+    // These bounds are newer than any pending ones, which must not override them.
+    get_state(id)?.take_pending_bounds()?;
+    apply_bounds(id, x, y, width, height)
+}
+
+/// Like `set_bounds`, with fractional logical coordinates, as laid out by
+/// Compose at non-integer scale factors.
+#[uniffi::export]
+pub fn set_bounds_f64(
+    id: u64,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+) -> Result<(), WebViewError> {
+    check_size(width, height)?;
+    if !x.is_finite() || !y.is_finite() {
+        return Err(WebViewError::InvalidArgument(format!("invalid position ({}, {})", x, y)));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_bounds_f64_inner(id, x, y, width, height));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_bounds_f64_inner(id, x, y, width, height))
+}
+
+/// Fits the WebView into a parent of the given logical size, minus the auto-resize insets.
+pub(crate) fn resize_to_parent(id: u64, parent_width: i32, parent_height: i32) -> Result<(), WebViewError> {
+    let state = get_state(id)?;
+    let (top, left, bottom, right) = state.auto_resize_insets()?;
+    // Saturating, as insets larger than the parent must not overflow: the size is
+    // then clamped to 1x1.
+    apply_bounds(
+        id,
+        left.into(),
+        top.into(),
+        parent_width.saturating_sub(left).saturating_sub(right).into(),
+        parent_height.saturating_sub(top).saturating_sub(bottom).into(),
+    )
+}
+
+fn fit_to_parent(id: u64) -> Result<(), WebViewError> {
+    let parent_handle = get_state(id)?.parent_handle()?;
+    let parent_size =
+        with_webview_infallible(id, |webview| platform::parent_size(webview, parent_handle))?;
+    match parent_size {
+        Some((width, height)) => resize_to_parent(id, width, height),
+        None => Ok(()),
+    }
+}
+
+fn set_auto_resize_to_parent_inner(id: u64, enabled: bool) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_auto_resize_to_parent id={} enabled={}", id, enabled);
+    let state = get_state(id)?;
+    let parent_handle = state.parent_handle()?;
+    with_webview(id, |webview| platform::set_auto_resize(webview, id, parent_handle, enabled))?;
+    state.auto_resize.store(enabled, Ordering::Release);
+    if enabled {
+        fit_to_parent(id)?;
+    }
+    Ok(())
+}
+
+/// Makes the WebView follow the size of its parent window.
+///
+/// While enabled, the WebView fills the parent minus the insets configured with
+/// `set_auto_resize_insets`, so `set_bounds` no longer needs to be called on resize.
+#[uniffi::export]
+pub fn set_auto_resize_to_parent(id: u64, enabled: bool) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_auto_resize_to_parent_inner(id, enabled));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_auto_resize_to_parent_inner(id, enabled))
+}
+
+fn set_auto_resize_insets_inner(
+    id: u64,
+    top: i32,
+    left: i32,
+    bottom: i32,
+    right: i32,
+) -> Result<(), WebViewError> {
+    eprintln!(
+        "[wrywebview] set_auto_resize_insets id={} top={} left={} bottom={} right={}",
+        id, top, left, bottom, right
+    );
+    let state = get_state(id)?;
+    state.set_auto_resize_insets((top, left, bottom, right))?;
+    if state.auto_resize.load(Ordering::Acquire) {
+        fit_to_parent(id)?;
+    }
+    Ok(())
+}
+
+/// Sets the margins kept between the parent edges and an auto-resized WebView.
+///
+/// A negative inset fails with `InvalidArgument`.
+#[uniffi::export]
+pub fn set_auto_resize_insets(
+    id: u64,
+    top: i32,
+    left: i32,
+    bottom: i32,
+    right: i32,
+) -> Result<(), WebViewError> {
+    if top < 0 || left < 0 || bottom < 0 || right < 0 {
+        return Err(WebViewError::InvalidArgument(format!(
+            "negative insets top={} left={} bottom={} right={}",
+            top, left, bottom, right
+        )));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_auto_resize_insets_inner(id, top, left, bottom, right));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_auto_resize_insets_inner(id, top, left, bottom, right))
+}
+
+fn set_scale_factor_inner(id: u64, scale: f64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_scale_factor id={} scale={}", id, scale);
+    let state = get_state(id)?;
+    state.set_scale_factor((scale != 0.0).then_some(scale))?;
+    if state.auto_resize.load(Ordering::Acquire) {
+        fit_to_parent(id)?;
+    }
+    Ok(())
+}
+
+/// Sets the factor used to convert the logical bounds given to `set_bounds` into
+/// physical pixels, for parents whose scale the platform does not report correctly.
+///
+/// It applies from the next `set_bounds` call (or right away when auto-resizing).
+/// A scale of 0 restores the platform's scale factor.
+#[uniffi::export]
+pub fn set_scale_factor(id: u64, scale: f64) -> Result<(), WebViewError> {
+    if !scale.is_finite() || scale < 0.0 {
+        return Err(WebViewError::InvalidArgument(format!("invalid scale factor {}", scale)));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_scale_factor_inner(id, scale));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_scale_factor_inner(id, scale))
+}
+
+fn check_size_limit(width: i32, height: i32) -> Result<(), WebViewError> {
+    if width < 0 || height < 0 {
+        return Err(WebViewError::InvalidArgument(format!("invalid size {}x{}", width, height)));
+    }
+    Ok(())
+}
+
+fn set_min_size_inner(id: u64, min_width: i32, min_height: i32) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_min_size id={} size={}x{}", id, min_width, min_height);
+    let state = get_state(id)?;
+    state.set_min_size(min_width, min_height);
+    if state.auto_resize.load(Ordering::Acquire) {
+        fit_to_parent(id)?;
+    }
+    Ok(())
+}
+
+/// Sets the smallest size the WebView may take; 0 removes the limit.
+///
+/// Bounds set afterwards, including by auto-resize, are silently clamped
+/// rather than rejected; the limit applies from the next `set_bounds` call
+/// (or right away when auto-resizing). If it exceeds the maximum size, the
+/// minimum wins.
+#[uniffi::export]
+pub fn set_min_size(id: u64, min_width: i32, min_height: i32) -> Result<(), WebViewError> {
+    check_size_limit(min_width, min_height)?;
+
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_min_size_inner(id, min_width, min_height));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_min_size_inner(id, min_width, min_height))
+}
+
+fn set_max_size_inner(id: u64, max_width: i32, max_height: i32) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_max_size id={} size={}x{}", id, max_width, max_height);
+    let state = get_state(id)?;
+    state.set_max_size(max_width, max_height);
+    if state.auto_resize.load(Ordering::Acquire) {
+        fit_to_parent(id)?;
+    }
+    Ok(())
+}
+
+/// Sets the largest size the WebView may take; 0 removes the limit. Clamps like
+/// `set_min_size`.
+#[uniffi::export]
+pub fn set_max_size(id: u64, max_width: i32, max_height: i32) -> Result<(), WebViewError> {
+    check_size_limit(max_width, max_height)?;
+
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_max_size_inner(id, max_width, max_height));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_max_size_inner(id, max_width, max_height))
+}
+
+fn move_to_parent_inner(id: u64, new_parent_handle: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] move_to_parent id={} parent=0x{:x}", id, new_parent_handle);
+    raw_window_handle_from(new_parent_handle)?;
+    let state = get_state(id)?;
+    let old_parent_handle = state.parent_handle()?;
+    if old_parent_handle == new_parent_handle {
+        return Ok(());
+    }
+
+    let auto_resize = state.auto_resize.load(Ordering::Acquire);
+    with_webview(id, |webview| {
+        if auto_resize {
+            platform::set_auto_resize(webview, id, old_parent_handle, false)?;
+        }
+        platform::move_to_parent(webview, old_parent_handle, new_parent_handle)?;
+        if auto_resize {
+            platform::set_auto_resize(webview, id, new_parent_handle, true)?;
+        }
+        Ok(())
+    })?;
+    state.set_parent_handle(new_parent_handle)?;
+    if auto_resize {
+        fit_to_parent(id)?;
+    }
+    Ok(())
+}
+
+/// Moves the WebView into another parent window, keeping its page and state.
+///
+/// The bounds are kept relative to the new parent; an auto-resized WebView is
+/// fitted to the new parent instead. Fails with `InvalidWindowHandle` if the
+/// new handle is not a valid window.
+#[uniffi::export]
+pub fn move_to_parent(id: u64, new_parent_handle: u64) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || move_to_parent_inner(id, new_parent_handle));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || move_to_parent_inner(id, new_parent_handle))
+}
+
+fn restack_inner(id: u64, to_front: bool) -> Result<(), WebViewError> {
+    let state = get_state(id)?;
+    let parent_handle = state.parent_handle()?;
+    with_webview(id, |webview| platform::restack(webview, parent_handle, to_front))?;
+    let z_index = if to_front {
+        state::next_front_z_index()
+    } else {
+        state::next_back_z_index()
+    };
+    state.z_index.store(z_index, Ordering::Relaxed);
+    eprintln!("[wrywebview] restack id={} z_index={}", id, z_index);
+    Ok(())
+}
+
+/// Shows the WebView above the other WebViews (and native children) of its parent.
+#[uniffi::export]
+pub fn bring_to_front(id: u64) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || restack_inner(id, true));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || restack_inner(id, true))
+}
+
+/// Shows the WebView below the other WebViews (and native children) of its parent.
+#[uniffi::export]
+pub fn send_to_back(id: u64) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || restack_inner(id, false));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || restack_inner(id, false))
+}
+
+// ============================================================================
+// Navigation
+// ============================================================================
+
+fn load_url_inner(id: u64, url: String) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] load_url id={} url={}", id, url);
+    let state = get_state(id)?;
+    state.is_loading.store(true, Ordering::Release);
+    // Main-frame requests are not covered by the injected fetch/XHR hooks, so
+    // the header filter is applied to explicit loads here.
+    let headers = state.request_headers()?;
+    if !headers.is_empty() {
+        let header_map = header_map_from(
+            headers
+                .into_iter()
+                .map(|(name, value)| HttpHeader { name, value })
+                .collect(),
+        )?;
+        return with_webview(id, |webview| {
+            webview
+                .load_url_with_headers(&url, header_map)
+                .map_err(WebViewError::from)
+        });
+    }
+    with_webview(id, |webview| webview.load_url(&url).map_err(WebViewError::from))
+}
+
+#[uniffi::export]
+pub fn load_url(id: u64, url: String) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || load_url_inner(id, url));
+    }
+
+    #[cfg(not(target_os = "linux"))]
     run_on_main_thread(move || load_url_inner(id, url))
 }
 
-fn load_url_with_headers_inner(
-    id: u64,
-    url: String,
-    headers: Vec<HttpHeader>,
-) -> Result<(), WebViewError> {
-    eprintln!(
-        "[wrywebview] load_url_with_headers id={} url={} headers={}",
-        id,
-        url,
-        headers.len()
-    );
-    if let Ok(state) = get_state(id) {
-        state.is_loading.store(true, Ordering::SeqCst);
-    }
-    let header_map = header_map_from(headers)?;
+fn load_url_with_headers_inner(
+    id: u64,
+    url: String,
+    headers: Vec<HttpHeader>,
+) -> Result<(), WebViewError> {
+    eprintln!(
+        "[wrywebview] load_url_with_headers id={} url={} headers={}",
+        id,
+        url,
+        headers.len()
+    );
+    if let Ok(state) = get_state(id) {
+        state.is_loading.store(true, Ordering::Release);
+    }
+    let header_map = header_map_from(headers)?;
+    with_webview(id, |webview| {
+        webview
+            .load_url_with_headers(&url, header_map)
+            .map_err(WebViewError::from)
+    })
+}
+
+#[uniffi::export]
+pub fn load_url_with_headers(
+    id: u64,
+    url: String,
+    headers: Vec<HttpHeader>,
+) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || load_url_with_headers_inner(id, url, headers));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || load_url_with_headers_inner(id, url, headers))
+}
+
+fn load_request_inner(
+    id: u64,
+    method: String,
+    url: String,
+    headers: Vec<HttpHeader>,
+    body: Option<Vec<u8>>,
+) -> Result<(), WebViewError> {
+    let body = body.filter(|body| !body.is_empty());
+    if method.eq_ignore_ascii_case("GET") && body.is_none() {
+        return if headers.is_empty() {
+            load_url_inner(id, url)
+        } else {
+            load_url_with_headers_inner(id, url, headers)
+        };
+    }
+    eprintln!(
+        "[wrywebview] load_request id={} method={} url={} headers={} body_len={}",
+        id,
+        method,
+        url,
+        headers.len(),
+        body.as_ref().map_or(0, Vec::len)
+    );
+    let method = wry::http::Method::from_bytes(method.as_bytes())
+        .map_err(|_| WebViewError::InvalidArgument(format!("invalid method {}", method)))?;
+    let state = get_state(id)?;
+    // Applied like in `load_url`, with the explicit headers last so they win.
+    let mut pairs = state.request_headers()?;
+    pairs.extend(header_pairs_from(headers)?);
+    state.is_loading.store(true, Ordering::Release);
+    with_webview(id, |webview| {
+        platform::load_request(webview, method.as_str(), &url, &pairs, body.as_deref())
+    })
+}
+
+/// Loads `url` with any HTTP method, e.g. to submit a form with `POST`.
+///
+/// A `GET` without a body behaves exactly like `load_url` (or
+/// `load_url_with_headers` when headers are given). WebKitGTK cannot send a
+/// body with a navigation, so a request with one returns `UnsupportedPlatform`
+/// on Linux.
+#[uniffi::export]
+pub fn load_request(
+    id: u64,
+    method: String,
+    url: String,
+    headers: Vec<HttpHeader>,
+    body: Option<Vec<u8>>,
+) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || load_request_inner(id, method, url, headers, body));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || load_request_inner(id, method, url, headers, body))
+}
+
+fn load_html_inner(id: u64, html: String) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] load_html id={} bytes={}", id, html.len());
+    if let Ok(state) = get_state(id) {
+        state.is_loading.store(true, Ordering::Release);
+    }
+    with_webview(id, |webview| webview.load_html(&html).map_err(WebViewError::from))
+}
+
+#[uniffi::export]
+pub fn load_html(id: u64, html: String) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || load_html_inner(id, html));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || load_html_inner(id, html))
+}
+
+/// Evaluates `script` in the page without waiting for it to run.
+///
+/// `evaluate_script` never blocks on any platform: WebKitGTK
+/// (`webkit_web_view_evaluate_javascript`), WKWebView
+/// (`evaluateJavaScript:completionHandler:`) and WebView2 (`ExecuteScript`) all
+/// queue the script on the engine and return, so this is cheap enough for
+/// animation frames.
+fn run_script(id: u64, script: &str) -> Result<(), WebViewError> {
+    with_webview(id, |webview| webview.evaluate_script(script).map_err(WebViewError::from))
+}
+
+fn stop_loading_inner(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] stop_loading id={}", id);
+    if let Ok(state) = get_state(id) {
+        state.is_loading.store(false, Ordering::Release);
+    }
+    run_script(id, "window.stop && window.stop();")
+}
+
+#[uniffi::export]
+pub fn stop_loading(id: u64) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || stop_loading_inner(id));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || stop_loading_inner(id))
+}
+
+#[uniffi::export(callback_interface)]
+pub trait JavaScriptCallback: Send + Sync {
+    fn on_result(&self, result: String);
+}
+
+fn evaluate_javascript_inner(
+    id: u64,
+    script: String,
+    callback: Box<dyn JavaScriptCallback>,
+) -> Result<(), WebViewError> {
+    with_webview(id, |webview| {
+        webview
+            .evaluate_script_with_callback(&script, move |result| {
+                callback.on_result(result);
+            })
+            .map_err(WebViewError::from)
+    })
+}
+
+#[uniffi::export]
+pub fn evaluate_javascript(
+    id: u64,
+    script: String,
+    callback: Box<dyn JavaScriptCallback>,
+) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || evaluate_javascript_inner(id, script, callback));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || evaluate_javascript_inner(id, script, callback))
+}
+
+fn go_back_inner(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] go_back id={}", id);
+    // The native history works with JavaScript disabled and keeps the engine's
+    // own back/forward bookkeeping, unlike `window.history`.
+    if with_webview(id, platform::go_back)? {
+        get_state(id)?.is_loading.store(true, Ordering::Release);
+    }
+    Ok(())
+}
+
+#[uniffi::export]
+pub fn go_back(id: u64) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || go_back_inner(id));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || go_back_inner(id))
+}
+
+fn go_forward_inner(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] go_forward id={}", id);
+    if with_webview(id, platform::go_forward)? {
+        get_state(id)?.is_loading.store(true, Ordering::Release);
+    }
+    Ok(())
+}
+
+#[uniffi::export]
+pub fn go_forward(id: u64) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || go_forward_inner(id));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || go_forward_inner(id))
+}
+
+/// Largest history offset accepted by `go_to_history_index`, in either direction.
+const MAX_HISTORY_OFFSET: i32 = 100;
+
+/// Strips the fragment, which entries of the same document may differ by.
+fn without_fragment(url: &str) -> &str {
+    url.split_once('#').map_or(url, |(document, _)| document)
+}
+
+fn go_to_history_index_inner(id: u64, offset: i32) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] go_to_history_index id={} offset={}", id, offset);
+    let state = get_state(id)?;
+    // Only a navigation to another document finishes with a page load; an offset
+    // past either end does nothing, and a fragment change loads nothing.
+    let loads_document = match state.history_entry(offset)? {
+        Some(_) if offset == 0 => true,
+        Some(target) => {
+            let current = state.current_url.load();
+            without_fragment(&target) != without_fragment(current.as_str())
+        }
+        None => false,
+    };
+    with_webview(id, |webview| {
+        webview
+            .evaluate_script(&format!("window.history.go({})", offset))
+            .map_err(WebViewError::from)
+    })?;
+    if loads_document {
+        state.is_loading.store(true, Ordering::Release);
+    }
+    Ok(())
+}
+
+/// Moves `offset` entries through the session history; negative offsets go back.
+///
+/// An offset of 0 reloads the page and an offset past either end of the history
+/// is ignored. Fails with `InvalidArgument` outside of `-100..=100`.
+///
+/// `is_loading` only turns true for an entry of the tracked history in another
+/// document, whose page load then resets it: moving to another fragment of the
+/// same document loads nothing.
+#[uniffi::export]
+pub fn go_to_history_index(id: u64, offset: i32) -> Result<(), WebViewError> {
+    if !(-MAX_HISTORY_OFFSET..=MAX_HISTORY_OFFSET).contains(&offset) {
+        return Err(WebViewError::InvalidArgument(format!(
+            "history offset {} is outside of -{max}..={max}",
+            offset,
+            max = MAX_HISTORY_OFFSET
+        )));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || go_to_history_index_inner(id, offset));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || go_to_history_index_inner(id, offset))
+}
+
+fn reload_inner(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] reload id={}", id);
+    if let Ok(state) = get_state(id) {
+        state.is_loading.store(true, Ordering::Release);
+    }
+    // wry reloads through the engine (webkit_web_view_reload, -[WKWebView reload],
+    // ICoreWebView2::Reload), which works with JavaScript disabled.
+    with_webview(id, |webview| webview.reload().map_err(WebViewError::from))
+}
+
+#[uniffi::export]
+pub fn reload(id: u64) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || reload_inner(id));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || reload_inner(id))
+}
+
+fn reload_bypass_cache_inner(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] reload_bypass_cache id={}", id);
+    if let Ok(state) = get_state(id) {
+        state.is_loading.store(true, Ordering::Release);
+    }
+    with_webview(id, platform::reload_bypass_cache)
+}
+
+/// Reloads the page and all of its resources from the network, skipping caches.
+///
+/// This is noticeably more expensive than `reload`; keep it for cases where
+/// stale resources must not be reused.
+#[uniffi::export]
+pub fn reload_bypass_cache(id: u64) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || reload_bypass_cache_inner(id));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || reload_bypass_cache_inner(id))
+}
+
+// ============================================================================
+// Request Headers
+// ============================================================================
+
+fn add_request_header_filter_inner(id: u64, headers: Vec<HttpHeader>) -> Result<(), WebViewError> {
+    eprintln!(
+        "[wrywebview] add_request_header_filter id={} headers={}",
+        id,
+        headers.len()
+    );
+    let pairs = header_pairs_from(headers)?;
+    let state = get_state(id)?;
+    state.add_request_headers(pairs)?;
+    // WebView2 reads the headers from the state on every request; other
+    // platforms need the fetch/XHR hooks refreshed in the current page.
+    if cfg!(target_os = "windows") {
+        return Ok(());
+    }
+    let headers = state.request_headers()?;
+    with_webview(id, |webview| {
+        webview
+            .evaluate_script(&scripts::request_headers_script(&headers))
+            .map_err(WebViewError::from)
+    })
+}
+
+/// Adds HTTP headers to every request issued by the WebView.
+///
+/// On Windows the headers are set natively on all requests. On macOS and Linux
+/// they are applied to `load_url` navigations and to `fetch`/`XMLHttpRequest`
+/// calls made by the page.
+#[uniffi::export]
+pub fn add_request_header_filter(id: u64, headers: Vec<HttpHeader>) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || add_request_header_filter_inner(id, headers));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || add_request_header_filter_inner(id, headers))
+}
+
+fn remove_request_header_filter_inner(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] remove_request_header_filter id={}", id);
+    let state = get_state(id)?;
+    state.clear_request_headers()?;
+    if cfg!(target_os = "windows") {
+        return Ok(());
+    }
+    with_webview(id, |webview| {
+        webview
+            .evaluate_script(&scripts::request_headers_script(&[]))
+            .map_err(WebViewError::from)
+    })
+}
+
+/// Removes all headers previously added with `add_request_header_filter`.
+#[uniffi::export]
+pub fn remove_request_header_filter(id: u64) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || remove_request_header_filter_inner(id));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || remove_request_header_filter_inner(id))
+}
+
+// ============================================================================
+// Request Interception
+// ============================================================================
+
+/// Asks the `RequestInterceptor` of the WebView about `request`, if its filter
+/// matches.
+pub(crate) fn intercept_request(
+    state: &WebViewState,
+    request: InterceptedRequest,
+) -> Option<InterceptedResponse> {
+    let interceptor = state.request_interceptor(&request.url, request.resource_type)?;
+    let url = request.url.clone();
+    let response = interceptor.on_request(state.id(), request);
+    if log_enabled() {
+        eprintln!("[wrywebview] intercept url={} action={:?}", url, response.action);
+    }
+    Some(response)
+}
+
+/// Applies the `RequestInterceptor` to a navigation. Returns whether the
+/// navigation goes ahead.
+///
+/// WebView2 intercepts navigations with the other requests, in
+/// `platform::windows`.
+#[cfg(not(target_os = "windows"))]
+fn intercept_navigation(state: &WebViewState, url: &str) -> bool {
+    let request = InterceptedRequest {
+        url: url.to_string(),
+        method: "GET".to_string(),
+        headers: Vec::new(),
+        body: None,
+        resource_type: ResourceType::Document,
+    };
+    let Some(response) = intercept_request(state, request) else {
+        return true;
+    };
+    match response.action {
+        InterceptAction::Allow => true,
+        InterceptAction::Block => false,
+        InterceptAction::Redirect => {
+            match response.modified_url {
+                Some(target) => {
+                    if let Err(e) = load_url_inner(state.id(), target) {
+                        eprintln!("[wrywebview] intercept redirect failed: {}", e);
+                    }
+                }
+                None => eprintln!("[wrywebview] intercept redirect without url, blocked {}", url),
+            }
+            false
+        }
+        InterceptAction::ModifyResponse => {
+            eprintln!("[wrywebview] intercept cannot modify responses here, allowed {}", url);
+            true
+        }
+    }
+}
+
+/// Passes the requests matching `filter` to `handler`, which lets each one
+/// through, blocks it, redirects it or answers it itself, e.g. for ad blocking.
+/// Replaces any previous interceptor.
+///
+/// - Windows: every request of the WebView, with its method, headers and body.
+/// - macOS and Linux: only navigations, reported as `GET` requests without
+///   headers or body, as the engines do not expose the other requests.
+///   `ModifyResponse` and the headers of `Allow` are ignored there.
+#[uniffi::export]
+pub fn intercept_network_request(
+    id: u64,
+    filter: RequestFilter,
+    handler: Arc<dyn RequestInterceptor>,
+) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] intercept_network_request id={} filter={:?}", id, filter);
+    get_state(id)?.set_request_interceptor(Some((filter, handler)))
+}
+
+/// Removes the interceptor installed with `intercept_network_request`.
+#[uniffi::export]
+pub fn clear_network_request_interceptor(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] clear_network_request_interceptor id={}", id);
+    get_state(id)?.set_request_interceptor(None)
+}
+
+// ============================================================================
+// Animation Frames
+// ============================================================================
+
+fn set_animation_frame_handler_inner(
+    id: u64,
+    handler: Option<Arc<dyn AnimationFrameHandler>>,
+) -> Result<(), WebViewError> {
+    let enabled = handler.is_some();
+    eprintln!("[wrywebview] animation_frame_handler id={} enabled={}", id, enabled);
+    get_state(id)?.animation_frame_handler.set(handler)?;
+    with_webview(id, |webview| {
+        webview
+            .evaluate_script(&scripts::animation_frame_script(enabled))
+            .map_err(WebViewError::from)
+    })
+}
+
+/// Calls `handler` on every rendered animation frame of the page (throttled to 60 fps).
+///
+/// The frame loop is re-installed after each navigation until
+/// `stop_animation_frame_handler` is called.
+#[uniffi::export]
+pub fn set_animation_frame_handler(
+    id: u64,
+    handler: Arc<dyn AnimationFrameHandler>,
+) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_animation_frame_handler_inner(id, Some(handler)));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_animation_frame_handler_inner(id, Some(handler)))
+}
+
+#[uniffi::export]
+pub fn stop_animation_frame_handler(id: u64) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_animation_frame_handler_inner(id, None));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_animation_frame_handler_inner(id, None))
+}
+
+// ============================================================================
+// Console
+// ============================================================================
+
+/// Hands a console call of the page to the `ConsoleHandler`, if any.
+pub(crate) fn report_console_message(
+    state: &WebViewState,
+    level: ConsoleLevel,
+    message: String,
+    source: String,
+    line: u32,
+) {
+    if let Some(handler) = state.console_handler.get() {
+        handler.on_message(state.id(), level, message, source, line);
+    }
+}
+
+fn set_console_handler_inner(
+    id: u64,
+    handler: Option<Arc<dyn ConsoleHandler>>,
+) -> Result<(), WebViewError> {
+    let enabled = handler.is_some();
+    eprintln!("[wrywebview] console_handler id={} enabled={}", id, enabled);
+    get_state(id)?.console_handler.set(handler)?;
+    // WebView2 reports console calls through the DevTools protocol instead.
+    if cfg!(target_os = "windows") {
+        return Ok(());
+    }
+    with_webview(id, |webview| {
+        webview
+            .evaluate_script(&scripts::console_script(enabled))
+            .map_err(WebViewError::from)
+    })
+}
+
+/// Forwards the `console.log`, `info`, `warn`, `error` and `debug` calls of the
+/// page to `handler`.
+///
+/// On Linux and macOS the calls are captured by a page script, installed again
+/// after each navigation, so calls made while a page loads are missed. Windows
+/// reports every call through the DevTools protocol.
+#[uniffi::export]
+pub fn register_console_message_handler(
+    id: u64,
+    handler: Arc<dyn ConsoleHandler>,
+) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_console_handler_inner(id, Some(handler)));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_console_handler_inner(id, Some(handler)))
+}
+
+#[uniffi::export]
+pub fn clear_console_message_handler(id: u64) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_console_handler_inner(id, None));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_console_handler_inner(id, None))
+}
+
+// ============================================================================
+// Layout Shift
+// ============================================================================
+
+fn set_layout_shift_callback_inner(
+    id: u64,
+    callback: Option<Arc<dyn LayoutShiftCallback>>,
+) -> Result<(), WebViewError> {
+    let enabled = callback.is_some();
+    eprintln!("[wrywebview] layout_shift id={} enabled={}", id, enabled);
+    get_state(id)?.layout_shift_callback.set(callback)?;
+    with_webview(id, |webview| {
+        webview
+            .evaluate_script(&scripts::layout_shift_script(enabled))
+            .map_err(WebViewError::from)
+    })
+}
+
+/// Reports every layout shift of the page to `callback`, including the shifts
+/// buffered before the call, to monitor the Cumulative Layout Shift.
+///
+/// The Layout Instability API is only implemented by Chromium, so shifts are
+/// reported on Windows only. Monitoring continues across navigations until
+/// `stop_measuring_layout_shift` is called.
+#[uniffi::export]
+pub fn measure_layout_shift(
+    id: u64,
+    callback: Arc<dyn LayoutShiftCallback>,
+) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_layout_shift_callback_inner(id, Some(callback)));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_layout_shift_callback_inner(id, Some(callback)))
+}
+
+#[uniffi::export]
+pub fn stop_measuring_layout_shift(id: u64) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_layout_shift_callback_inner(id, None));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_layout_shift_callback_inner(id, None))
+}
+
+// ============================================================================
+// SSL Warnings
+// ============================================================================
+
+/// Records an SSL warning of the current page and notifies the observer.
+pub(crate) fn report_ssl_warning(state: &WebViewState, url: String, warning_type: SslWarningType) {
+    eprintln!("[wrywebview] ssl_warning url={} type={:?}", url, warning_type);
+    let warning = SslWarning { url, warning_type };
+    if let Err(e) = state.push_ssl_warning(warning.clone()) {
+        eprintln!("[wrywebview] ssl_warning state update failed: {}", e);
+    }
+    if let Some(observer) = state.ssl_warning_observer.get() {
+        observer.on_ssl_warning(warning.url, warning.warning_type);
+    }
+}
+
+/// Reports the warnings only known once an HTTPS page has finished loading.
+fn report_page_security(state: &WebViewState, url: &str) {
+    if !url.starts_with("https:") {
+        return;
+    }
+    match with_webview_infallible(state.id(), platform::page_security_warnings) {
+        Ok(warnings) => {
+            for warning_type in warnings {
+                report_ssl_warning(state, url.to_string(), warning_type);
+            }
+        }
+        Err(e) => eprintln!("[wrywebview] page security check failed: {}", e),
+    }
+}
+
+/// Registers an observer notified of SSL issues that do not block the page,
+/// such as mixed content or subresources with invalid certificates.
+#[uniffi::export]
+pub fn set_ssl_warning_observer(
+    id: u64,
+    handler: Arc<dyn SslWarningObserver>,
+) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_ssl_warning_observer id={}", id);
+    get_state(id)?.ssl_warning_observer.set(Some(handler))
+}
+
+#[uniffi::export]
+pub fn clear_ssl_warning_observer(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] clear_ssl_warning_observer id={}", id);
+    get_state(id)?.ssl_warning_observer.set(None)
+}
+
+/// Returns the SSL warnings recorded since the current page started loading.
+#[uniffi::export]
+pub fn get_ssl_warnings(id: u64) -> Result<Vec<SslWarning>, WebViewError> {
+    get_state(id)?.ssl_warnings()
+}
+
+// ============================================================================
+// TLS Errors
+// ============================================================================
+
+/// Asks the `TlsErrorHandler` whether to accept an invalid certificate.
+///
+/// Without a handler the certificate is rejected.
+pub(crate) fn decide_tls_error(
+    state: &WebViewState,
+    url: String,
+    error_description: String,
+) -> TlsDecision {
+    let Some(handler) = state.tls_error_handler.get() else {
+        return TlsDecision::Deny;
+    };
+    eprintln!("[wrywebview] tls_error url={} error={}", url, error_description);
+    let decision = handler.on_error(state.id(), url, error_description);
+    eprintln!("[wrywebview] tls_error decision={:?}", decision);
+    decision
+}
+
+/// Registers the handler deciding whether pages with invalid certificates
+/// (self-signed, expired, wrong host) may load.
+///
+/// **Warning:** returning `TlsDecision::Allow` disables the protection TLS
+/// provides for that connection; only do so in debug builds, e.g. for local
+/// development servers. Without a handler every invalid certificate is denied.
+#[uniffi::export]
+pub fn set_tls_error_handler(
+    id: u64,
+    handler: Arc<dyn TlsErrorHandler>,
+) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_tls_error_handler id={}", id);
+    get_state(id)?.tls_error_handler.set(Some(handler))
+}
+
+#[uniffi::export]
+pub fn clear_tls_error_handler(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] clear_tls_error_handler id={}", id);
+    get_state(id)?.tls_error_handler.set(None)
+}
+
+// ============================================================================
+// Authentication
+// ============================================================================
+
+/// Number of times wrong credentials are retried before a challenge is cancelled.
+const MAX_AUTH_RETRIES: u32 = 3;
+
+/// How a platform should answer an HTTP authentication challenge.
+pub(crate) enum AuthAnswer {
+    /// No handler is set: keep the platform's default handling.
+    Default,
+    Cancel,
+    Respond(Credentials),
+}
+
+/// Asks the `AuthChallengeHandler` for credentials.
+///
+/// Proxy challenges are answered with the credentials of the WebView's
+/// `ProxyConfig` when it has some. A challenge repeated for the same host and
+/// realm means the previous credentials were wrong; after `MAX_AUTH_RETRIES`
+/// retries it is cancelled without asking the handler, until the next page load
+/// finishes.
+pub(crate) fn answer_auth_challenge(
+    state: &WebViewState,
+    host: String,
+    realm: String,
+    for_proxy: bool,
+) -> AuthAnswer {
+    let proxy_credentials = match state.proxy() {
+        Ok(Some(proxy)) if for_proxy => proxy.credentials(),
+        _ => None,
+    };
+    let handler = state.auth_challenge_handler.get();
+    if proxy_credentials.is_none() && handler.is_none() {
+        return AuthAnswer::Default;
+    }
+    let previous_attempts = match state.record_auth_attempt(&host, &realm) {
+        Ok(previous_attempts) => previous_attempts,
+        Err(e) => {
+            eprintln!("[wrywebview] auth_challenge state update failed: {}", e);
+            return AuthAnswer::Cancel;
+        }
+    };
+    if previous_attempts > MAX_AUTH_RETRIES {
+        eprintln!(
+            "[wrywebview] auth_challenge host={} cancelled after {} retries",
+            host, MAX_AUTH_RETRIES
+        );
+        return AuthAnswer::Cancel;
+    }
+
+    eprintln!(
+        "[wrywebview] auth_challenge host={} realm={} proxy={}",
+        host, realm, for_proxy
+    );
+    if let Some(credentials) = proxy_credentials {
+        return AuthAnswer::Respond(credentials);
+    }
+    match handler.map(|handler| handler.on_challenge(state.id(), host, realm)) {
+        Some(Some(credentials)) => AuthAnswer::Respond(credentials),
+        Some(None) => AuthAnswer::Cancel,
+        None => AuthAnswer::Default,
+    }
+}
+
+/// Registers the handler providing credentials for HTTP Basic/Digest authentication.
+#[uniffi::export]
+pub fn set_auth_challenge_handler(
+    id: u64,
+    handler: Arc<dyn AuthChallengeHandler>,
+) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_auth_challenge_handler id={}", id);
+    get_state(id)?.auth_challenge_handler.set(Some(handler))
+}
+
+#[uniffi::export]
+pub fn clear_auth_challenge_handler(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] clear_auth_challenge_handler id={}", id);
+    get_state(id)?.auth_challenge_handler.set(None)
+}
+
+// ============================================================================
+// File Chooser
+// ============================================================================
+
+/// Asks the `FileChooserHandler` for files, or returns `None` if no handler is set.
+///
+/// Only the first path is kept when the input does not accept multiple files.
+pub(crate) fn choose_files(
+    state: &WebViewState,
+    accept: String,
+    multiple: bool,
+) -> Option<Vec<String>> {
+    let handler = state.file_chooser_handler.get()?;
+    let mut paths = handler.on_choose(state.id(), accept, multiple);
+    if !multiple {
+        paths.truncate(1);
+    }
+    eprintln!("[wrywebview] file_chooser id={} files={}", state.id(), paths.len());
+    Some(paths)
+}
+
+#[derive(serde::Deserialize)]
+struct FileChooserRequest {
+    accept: String,
+    multiple: bool,
+}
+
+/// Answers a click on a file input intercepted by `scripts::file_chooser_script`.
+fn handle_file_chooser_message(state: &WebViewState, payload: &str) {
+    let request = match serde_json::from_str::<FileChooserRequest>(payload) {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("[wrywebview] invalid file chooser request: {}", e);
+            return;
+        }
+    };
+    let paths = choose_files(state, request.accept, request.multiple).unwrap_or_default();
+
+    let mut files = Vec::new();
+    for path in paths {
+        let path = PathBuf::from(path);
+        match std::fs::read(&path) {
+            Ok(bytes) => {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                files.push((name, base64::engine::general_purpose::STANDARD.encode(bytes)));
+            }
+            Err(e) => {
+                eprintln!("[wrywebview] file_chooser read {} failed: {}", path.display(), e)
+            }
+        }
+    }
+
+    let script = scripts::file_chooser_result_script(&files);
+    if let Err(e) = with_webview(state.id(), |webview| {
+        webview.evaluate_script(&script).map_err(WebViewError::from)
+    }) {
+        eprintln!("[wrywebview] file_chooser result failed: {}", e);
+    }
+}
+
+fn set_file_chooser_handler_inner(
+    id: u64,
+    handler: Option<Arc<dyn FileChooserHandler>>,
+) -> Result<(), WebViewError> {
+    let enabled = handler.is_some();
+    eprintln!("[wrywebview] set_file_chooser_handler id={} enabled={}", id, enabled);
+    get_state(id)?.file_chooser_handler.set(handler)?;
+    if cfg!(target_os = "windows") {
+        with_webview(id, |webview| {
+            webview
+                .evaluate_script(&scripts::file_chooser_script(enabled))
+                .map_err(WebViewError::from)
+        })?;
+    }
+    Ok(())
+}
+
+/// Lets `handler` pick the files for `<input type="file">` elements.
+///
+/// On Windows the input clicks are intercepted in the page and the chosen files
+/// are read natively and handed to the input, so they should stay reasonably small.
+#[uniffi::export]
+pub fn set_file_chooser_handler(
+    id: u64,
+    handler: Arc<dyn FileChooserHandler>,
+) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_file_chooser_handler_inner(id, Some(handler)));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_file_chooser_handler_inner(id, Some(handler)))
+}
+
+/// Restores the native file picker.
+#[uniffi::export]
+pub fn clear_file_chooser_handler(id: u64) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_file_chooser_handler_inner(id, None));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_file_chooser_handler_inner(id, None))
+}
+
+// ============================================================================
+// Permissions
+// ============================================================================
+
+/// Registers the handler deciding camera, microphone, geolocation and
+/// notification permission requests.
+///
+/// Supported on Windows and Linux; macOS keeps the WebKit defaults. Without a
+/// handler the platform default applies.
+#[uniffi::export]
+pub fn set_permission_handler(
+    id: u64,
+    handler: Arc<dyn PermissionHandler>,
+) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_permission_handler id={}", id);
+    get_state(id)?.permission_handler.set(Some(handler))
+}
+
+#[uniffi::export]
+pub fn clear_permission_handler(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] clear_permission_handler id={}", id);
+    get_state(id)?.permission_handler.set(None)
+}
+
+// ============================================================================
+// JavaScript Dialogs
+// ============================================================================
+
+fn set_js_dialog_handler_inner(
+    id: u64,
+    handler: Option<Arc<dyn JsDialogHandler>>,
+) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_js_dialog_handler id={} enabled={}", id, handler.is_some());
+    let intercepted = handler.is_some();
+    get_state(id)?.js_dialog_handler.set(handler)?;
+    with_webview(id, |webview| platform::set_script_dialogs_intercepted(webview, intercepted))
+}
+
+/// Replaces the native `alert()`, `confirm()` and `prompt()` dialogs with `handler`.
+///
+/// The handler is called synchronously from the platform dialog callback, so the
+/// page stays blocked until it returns.
+#[uniffi::export]
+pub fn set_js_dialog_handler(id: u64, handler: Arc<dyn JsDialogHandler>) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_js_dialog_handler_inner(id, Some(handler)));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_js_dialog_handler_inner(id, Some(handler)))
+}
+
+/// Restores the native JavaScript dialogs.
+#[uniffi::export]
+pub fn clear_js_dialog_handler(id: u64) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_js_dialog_handler_inner(id, None));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_js_dialog_handler_inner(id, None))
+}
+
+// ============================================================================
+// Error Pages
+// ============================================================================
+
+/// Notifies the `ErrorPageHandler` that the main frame failed to load.
+///
+/// Platforms call this only for failures that replace the page with an error
+/// page, not for cancelled or interrupted navigations.
+pub(crate) fn report_error_page(state: &WebViewState, url: String, error_code: i32) {
+    eprintln!(
+        "[wrywebview] error_page id={} url={} code={}",
+        state.id(),
+        url,
+        error_code
+    );
+    state.emit_event(WebViewEvent::Error {
+        message: format!("{} failed to load (code {})", url, error_code),
+    });
+    if let Some(handler) = state.error_page_handler.get() {
+        handler.on_error_page(url, error_code);
+    }
+}
+
+/// Registers the handler notified when the platform error page replaces a page
+/// that failed to load, so the host can show its own error UI.
+///
+/// macOS has no built-in error page; the handler is called when the main frame
+/// fails to load and the WebView keeps showing the previous content.
+#[uniffi::export]
+pub fn set_error_page_handler(
+    id: u64,
+    handler: Arc<dyn ErrorPageHandler>,
+) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_error_page_handler id={}", id);
+    get_state(id)?.error_page_handler.set(Some(handler))
+}
+
+#[uniffi::export]
+pub fn clear_error_page_handler(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] clear_error_page_handler id={}", id);
+    get_state(id)?.error_page_handler.set(None)
+}
+
+// ============================================================================
+// Response Headers
+// ============================================================================
+
+/// Caches the response of the main document, as reported by the platform.
+pub(crate) fn record_main_response(
+    state: &WebViewState,
+    status: u16,
+    headers: Vec<(String, String)>,
+) {
+    eprintln!("[wrywebview] main_response id={} status={}", state.id(), status);
+    if let Err(e) = state.set_main_response(Some((status, headers))) {
+        eprintln!("[wrywebview] main_response state update failed: {}", e);
+    }
+}
+
+/// Delivers the status and headers of the response for the current main document.
+///
+/// Fails with `NoMainResponse`, without calling `callback`, until a response
+/// arrives: right after creation, after a navigation starts, and for pages
+/// without one such as `about:blank`.
+#[uniffi::export]
+pub fn get_response_headers_for_main_resource(
+    id: u64,
+    callback: Arc<dyn ResponseHeadersCallback>,
+) -> Result<(), WebViewError> {
+    let (status, headers) = get_state(id)?
+        .main_response()?
+        .ok_or(WebViewError::NoMainResponse(id))?;
+    let headers = headers
+        .into_iter()
+        .map(|(name, value)| HttpHeader { name, value })
+        .collect();
+    callback.on_headers(status, headers);
+    Ok(())
+}
+
+// ============================================================================
+// Certificates
+// ============================================================================
+
+fn read_certificate_chain_inner(
+    id: u64,
+    callback: Arc<dyn CertificateChainCallback>,
+) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] read_certificate_chain id={}", id);
+    let url = get_url(id)?;
+    let is_https = wry::http::Uri::from_str(&url)
+        .ok()
+        .and_then(|uri| uri.scheme_str().map(|scheme| scheme.eq_ignore_ascii_case("https")))
+        .unwrap_or(false);
+    if !is_https {
+        return Err(WebViewError::NotSecure);
+    }
+
+    with_webview(id, move |webview| {
+        platform::certificate_chain(
+            webview,
+            &url,
+            Box::new(move |chain| {
+                let certs = chain.into_iter().map(certificate::certificate_info).collect();
+                callback.on_chain(certs);
+            }),
+        )
+    })
+}
+
+/// Reads the TLS certificate chain of the current page.
+///
+/// Returns `WebViewError::NotSecure` if the page is not served over HTTPS.
+#[uniffi::export]
+pub fn read_certificate_chain(
+    id: u64,
+    callback: Arc<dyn CertificateChainCallback>,
+) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || read_certificate_chain_inner(id, callback));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || read_certificate_chain_inner(id, callback))
+}
+
+// ============================================================================
+// Page Source
+// ============================================================================
+
+/// Evaluates `script` and waits for its JSON-encoded result, for as long as
+/// `set_main_thread_timeout` allows.
+///
+/// Fails with `WrongThread` on the thread running the WebView event loop (the
+/// GTK thread on Linux, the main thread on macOS): it delivers the result, so
+/// waiting there would block until the timeout. On Windows the message queue is
+/// pumped while waiting instead.
+fn evaluate_script_blocking(id: u64, script: String) -> Result<String, WebViewError> {
+    #[cfg(target_os = "linux")]
+    let on_event_loop = is_on_gtk_thread();
+    #[cfg(target_os = "macos")]
+    let on_event_loop = platform::macos::MainThreadMarker::new().is_some();
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    let on_event_loop = false;
+    if on_event_loop {
+        return Err(WebViewError::WrongThread(id));
+    }
+
+    let (result_tx, result_rx) = mpsc::channel::<String>();
+    let evaluate = move || {
+        with_webview(id, |webview| {
+            webview
+                .evaluate_script_with_callback(&script, move |result| {
+                    let _ = result_tx.send(result);
+                })
+                .map_err(WebViewError::from)
+        })
+    };
+
+    #[cfg(target_os = "linux")]
+    run_on_gtk_thread(evaluate)?;
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(evaluate)?;
+
+    let timeout_ms = platform::main_thread_timeout_ms();
+    let dropped = || WebViewError::Internal("script result was dropped".to_string());
+    #[cfg(target_os = "windows")]
+    {
+        let deadline = (timeout_ms > 0).then(|| Instant::now() + Duration::from_millis(timeout_ms));
+        loop {
+            match result_rx.try_recv() {
+                Ok(result) => return Ok(result),
+                Err(mpsc::TryRecvError::Disconnected) => return Err(dropped()),
+                Err(mpsc::TryRecvError::Empty)
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) =>
+                {
+                    return Err(WebViewError::MainThreadTimeout(timeout_ms))
+                }
+                Err(mpsc::TryRecvError::Empty) => {
+                    platform::windows::pump_events(None);
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        if timeout_ms == 0 {
+            return result_rx.recv().map_err(|_| dropped());
+        }
+        result_rx
+            .recv_timeout(Duration::from_millis(timeout_ms))
+            .map_err(|e| match e {
+                mpsc::RecvTimeoutError::Timeout => WebViewError::MainThreadTimeout(timeout_ms),
+                mpsc::RecvTimeoutError::Disconnected => dropped(),
+            })
+    }
+}
+
+/// Returns the outer HTML of the main frame's document (iframes are not included).
+///
+/// Blocks until the WebView has serialized the document, which can take a while
+/// for large pages, and fails with `WrongThread` on the UI thread.
+#[uniffi::export]
+pub fn get_page_source(id: u64) -> Result<String, WebViewError> {
+    eprintln!("[wrywebview] get_page_source id={}", id);
+    let result = evaluate_script_blocking(id, scripts::PAGE_SOURCE_SCRIPT.to_string())?;
+    // The result arrives as a JSON string literal.
+    serde_json::from_str(&result)
+        .map_err(|e| WebViewError::Internal(format!("invalid page source: {}", e)))
+}
+
+// ============================================================================
+// Text Selection
+// ============================================================================
+
+/// Evaluates a script that looks up `selector` and evaluates to "ok", "missing"
+/// or "invalid" (see `scripts::select_text_script`), and maps the outcome to
+/// `ElementNotFound` or `InvalidArgument`.
+fn evaluate_selector_script(id: u64, selector: String, script: String) -> Result<(), WebViewError> {
+    let result = evaluate_script_blocking(id, script)?;
+    match serde_json::from_str::<String>(&result).as_deref() {
+        Ok("ok") => Ok(()),
+        Ok("missing") => Err(WebViewError::ElementNotFound(selector)),
+        Ok("invalid") => {
+            Err(WebViewError::InvalidArgument(format!("invalid selector {}", selector)))
+        }
+        _ => Err(WebViewError::Internal(format!("unexpected selector script result {}", result))),
+    }
+}
+
+/// Selects the text content of the first element matching the CSS `selector`,
+/// replacing the current selection, e.g. for accessibility tools and tests.
+///
+/// Fails with `ElementNotFound` if nothing matches. Blocks until the page has
+/// run the script, and fails with `WrongThread` on the UI thread.
+#[uniffi::export]
+pub fn select_text(id: u64, selector: String) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] select_text id={} selector={}", id, selector);
+    let script = scripts::select_text_script(&selector);
+    evaluate_selector_script(id, selector, script)
+}
+
+/// Returns the text currently selected in the page, or an empty string.
+///
+/// Blocks until the page has answered, and fails with `WrongThread` on the UI
+/// thread.
+#[uniffi::export]
+pub fn get_selected_text(id: u64) -> Result<String, WebViewError> {
+    let result = evaluate_script_blocking(id, scripts::SELECTED_TEXT_SCRIPT.to_string())?;
+    // The result arrives as a JSON string literal.
+    serde_json::from_str(&result)
+        .map_err(|e| WebViewError::Internal(format!("invalid selected text: {}", e)))
+}
+
+fn clear_selection_inner(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] clear_selection id={}", id);
     with_webview(id, |webview| {
         webview
-            .load_url_with_headers(&url, header_map)
+            .evaluate_script(scripts::CLEAR_SELECTION_SCRIPT)
             .map_err(WebViewError::from)
     })
 }
 
+/// Deselects any text selected in the page.
 #[uniffi::export]
-pub fn load_url_with_headers(
-    id: u64,
-    url: String,
-    headers: Vec<HttpHeader>,
-) -> Result<(), WebViewError> {
+pub fn clear_selection(id: u64) -> Result<(), WebViewError> {
     #[cfg(target_os = "linux")]
     {
-        return run_on_gtk_thread(move || load_url_with_headers_inner(id, url, headers));
+        return run_on_gtk_thread(move || clear_selection_inner(id));
     }
 
     #[cfg(not(target_os = "linux"))]
-    run_on_main_thread(move || load_url_with_headers_inner(id, url, headers))
+    run_on_main_thread(move || clear_selection_inner(id))
 }
 
-fn load_html_inner(id: u64, html: String) -> Result<(), WebViewError> {
-    eprintln!("[wrywebview] load_html id={} bytes={}", id, html.len());
-    if let Ok(state) = get_state(id) {
-        state.is_loading.store(true, Ordering::SeqCst);
+// ============================================================================
+// Scrolling
+// ============================================================================
+
+/// Scrolls the first element matching the CSS `selector` to the top of the
+/// viewport, animated if `smooth`, e.g. to jump to a section or a search result.
+///
+/// The selector only ever reaches the page as an escaped string literal, so it
+/// cannot inject script; a selector that does not parse fails with
+/// `InvalidArgument`, and one that matches nothing with `ElementNotFound`.
+/// Blocks until the page has run the script, and fails with `WrongThread` on
+/// the UI thread.
+#[uniffi::export]
+pub fn scroll_to_element(id: u64, selector: String, smooth: bool) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] scroll_to_element id={} selector={} smooth={}", id, selector, smooth);
+    let script = scripts::scroll_to_element_script(&selector, smooth);
+    evaluate_selector_script(id, selector, script)
+}
+
+// ============================================================================
+// Persistent Scripts
+// ============================================================================
+
+fn evaluate_script_on_every_navigation_inner(id: u64, script: String) -> Result<u64, WebViewError> {
+    eprintln!("[wrywebview] evaluate_script_on_every_navigation id={} bytes={}", id, script.len());
+    let state = get_state(id)?;
+    let script_id = state.add_persistent_script(script.clone())?;
+    // A page that is still loading gets the script on `PageLoadEvent::Finished`.
+    if !state.is_loading.load(Ordering::Acquire) {
+        with_webview(id, |webview| webview.evaluate_script(&script).map_err(WebViewError::from))?;
     }
-    with_webview(id, |webview| webview.load_html(&html).map_err(WebViewError::from))
+    Ok(script_id)
 }
 
+/// Runs `script` now and again after every page load, until it is removed with
+/// `remove_persistent_script`.
+///
+/// Unlike initialization scripts, the script runs once the page has finished
+/// loading, so the document is complete. Returns the script ID.
 #[uniffi::export]
-pub fn load_html(id: u64, html: String) -> Result<(), WebViewError> {
+pub fn evaluate_script_on_every_navigation(id: u64, script: String) -> Result<u64, WebViewError> {
     #[cfg(target_os = "linux")]
     {
-        return run_on_gtk_thread(move || load_html_inner(id, html));
+        return run_on_gtk_thread(move || evaluate_script_on_every_navigation_inner(id, script));
     }
 
     #[cfg(not(target_os = "linux"))]
-    run_on_main_thread(move || load_html_inner(id, html))
+    run_on_main_thread(move || evaluate_script_on_every_navigation_inner(id, script))
 }
 
-fn stop_loading_inner(id: u64) -> Result<(), WebViewError> {
-    eprintln!("[wrywebview] stop_loading id={}", id);
-    if let Ok(state) = get_state(id) {
-        state.is_loading.store(false, Ordering::SeqCst);
-    }
+/// Stops running a script registered with `evaluate_script_on_every_navigation`.
+#[uniffi::export]
+pub fn remove_persistent_script(id: u64, script_id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] remove_persistent_script id={} script_id={}", id, script_id);
+    get_state(id)?.remove_persistent_script(script_id)
+}
+
+// ============================================================================
+// Scrollbars
+// ============================================================================
+
+fn set_scrollbar_visibility_inner(id: u64, visible: bool) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_scrollbar_visibility id={} visible={}", id, visible);
+    get_state(id)?.show_scrollbars.store(visible, Ordering::Release);
     with_webview(id, |webview| {
         webview
-            .evaluate_script("window.stop && window.stop();")
+            .evaluate_script(&scripts::scrollbar_script(visible))
             .map_err(WebViewError::from)
     })
 }
 
+/// Shows or hides the scrollbars of the page; hidden scrollbars still let the
+/// content scroll. The setting is re-applied after each navigation.
 #[uniffi::export]
-pub fn stop_loading(id: u64) -> Result<(), WebViewError> {
+pub fn set_scrollbar_visibility(id: u64, visible: bool) -> Result<(), WebViewError> {
     #[cfg(target_os = "linux")]
     {
-        return run_on_gtk_thread(move || stop_loading_inner(id));
+        return run_on_gtk_thread(move || set_scrollbar_visibility_inner(id, visible));
     }
 
     #[cfg(not(target_os = "linux"))]
-    run_on_main_thread(move || stop_loading_inner(id))
+    run_on_main_thread(move || set_scrollbar_visibility_inner(id, visible))
 }
 
-#[uniffi::export(callback_interface)]
-pub trait JavaScriptCallback: Send + Sync {
-    fn on_result(&self, result: String);
-}
+// ============================================================================
+// Content Insets
+// ============================================================================
 
-fn evaluate_javascript_inner(
+fn set_content_insets_inner(
     id: u64,
-    script: String,
-    callback: Box<dyn JavaScriptCallback>,
+    top: i32,
+    left: i32,
+    bottom: i32,
+    right: i32,
 ) -> Result<(), WebViewError> {
+    eprintln!(
+        "[wrywebview] set_content_insets id={} top={} left={} bottom={} right={}",
+        id, top, left, bottom, right
+    );
+    let insets = (top, left, bottom, right);
+    get_state(id)?.set_content_insets(insets)?;
     with_webview(id, |webview| {
-        let _ = webview.evaluate_script_with_callback(&script, move |result| {
-            callback.on_result(result);
-        });
-        Ok(())
+        webview
+            .evaluate_script(&scripts::content_insets_script(insets))
+            .map_err(WebViewError::from)
     })
 }
 
+/// Sets the safe area insets (notch, system bars) the page should keep clear, in
+/// logical pixels.
+///
+/// They are exposed to the page as the `--safe-area-inset-top`, `-left`, `-bottom`
+/// and `-right` CSS variables, re-applied after each navigation. None of the
+/// desktop WebViews has a native inset API backing `env(safe-area-inset-*)`.
 #[uniffi::export]
-pub fn evaluate_javascript(
+pub fn set_content_insets(
     id: u64,
-    script: String,
-    callback: Box<dyn JavaScriptCallback>,
+    top: i32,
+    left: i32,
+    bottom: i32,
+    right: i32,
 ) -> Result<(), WebViewError> {
     #[cfg(target_os = "linux")]
     {
-        return run_on_gtk_thread(move || evaluate_javascript_inner(id, script, callback));
+        return run_on_gtk_thread(move || set_content_insets_inner(id, top, left, bottom, right));
     }
 
     #[cfg(not(target_os = "linux"))]
-    run_on_main_thread(move || evaluate_javascript_inner(id, script, callback))
+    run_on_main_thread(move || set_content_insets_inner(id, top, left, bottom, right))
 }
 
-fn go_back_inner(id: u64) -> Result<(), WebViewError> {
-    eprintln!("[wrywebview] go_back id={}", id);
-    if let Ok(state) = get_state(id) {
-        state.is_loading.store(true, Ordering::SeqCst);
+// ============================================================================
+// Color Scheme
+// ============================================================================
+
+fn set_color_scheme_preference_inner(id: u64, scheme: ColorScheme) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_color_scheme_preference id={} scheme={:?}", id, scheme);
+    get_state(id)?.set_color_scheme(scheme)?;
+    with_webview(id, |webview| platform::set_color_scheme(webview, scheme))
+}
+
+/// Sets the color scheme the page sees through `prefers-color-scheme`.
+///
+/// The preference is kept across navigations. On Linux and Windows it is applied
+/// by a page script, so style sheets that cannot be read by the page (cross-origin)
+/// and style sheets added after the page load keep the system scheme.
+#[uniffi::export]
+pub fn set_color_scheme_preference(id: u64, scheme: ColorScheme) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_color_scheme_preference_inner(id, scheme));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_color_scheme_preference_inner(id, scheme))
+}
+
+// ============================================================================
+// Settings
+// ============================================================================
+
+fn set_javascript_enabled_inner(id: u64, enabled: bool) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_javascript_enabled id={} enabled={}", id, enabled);
+    with_webview(id, |webview| platform::set_javascript_enabled(webview, enabled))?;
+    get_state(id)?.javascript_enabled.store(enabled, Ordering::Release);
+    Ok(())
+}
+
+/// Enables or disables the scripts of the page (see also `WebViewConfig::javascript_enabled`).
+///
+/// The change may only apply from the next navigation, so call `reload` to apply it
+/// to the current page. Scripts evaluated by the host keep running on macOS.
+#[uniffi::export]
+pub fn set_javascript_enabled(id: u64, enabled: bool) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_javascript_enabled_inner(id, enabled));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_javascript_enabled_inner(id, enabled))
+}
+
+fn set_web_sql_enabled_inner(id: u64, enabled: bool) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_web_sql_enabled id={} enabled={}", id, enabled);
+    with_webview(id, |webview| platform::set_web_sql_enabled(webview, enabled))
+}
+
+/// Enables or disables the deprecated Web SQL database.
+///
+/// Web SQL has been removed from recent engines, so this may stop working at any
+/// time: macOS relies on a private `WKPreferences` flag, and on Windows the
+/// feature can only be enabled by a browser argument when the WebView2 process
+/// starts, so `UnsupportedPlatform` is returned there.
+#[uniffi::export]
+pub fn set_web_sql_enabled(id: u64, enabled: bool) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_web_sql_enabled_inner(id, enabled));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_web_sql_enabled_inner(id, enabled))
+}
+
+/// Allows or forbids GPU rendering for the WebViews created afterwards, e.g. on
+/// virtual machines without a usable GPU where pages stay blank.
+///
+/// `WebViewConfig::hardware_acceleration` can only turn it off for a single
+/// WebView. On Windows this passes `--disable-gpu` to the WebView2 environment,
+/// which all WebViews of a data store share, so they must all use the same
+/// setting (see `WebViewConfig::proxy`). macOS
+/// offers no control over WebKit's GPU use, so the setting is ignored there.
+#[uniffi::export]
+pub fn set_hardware_acceleration_enabled(enabled: bool) {
+    eprintln!("[wrywebview] set_hardware_acceleration_enabled enabled={}", enabled);
+    platform::set_hardware_acceleration_enabled(enabled);
+}
+
+/// Lets a remote DevTools client inspect the WebViews, e.g. to debug a page in
+/// a released app. Fails with `AlreadyInitialized` once a WebView was created.
+///
+/// - Windows: the WebView2 browser listens on `port`; open
+///   `edge://inspect` or `http://localhost:<port>`.
+/// - Linux: WebKitGTK serves its inspector on `http://127.0.0.1:<port>`.
+/// - macOS: the port is unused; the WebViews become inspectable from Safari's
+///   Develop menu.
+#[uniffi::export]
+pub fn enable_remote_debugging(port: u16) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] enable_remote_debugging port={}", port);
+    if state::any_created() {
+        return Err(WebViewError::AlreadyInitialized);
+    }
+    platform::set_remote_debugging_port(port)
+}
+
+fn set_pinch_to_zoom_enabled_inner(id: u64, enabled: bool) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_pinch_to_zoom_enabled id={} enabled={}", id, enabled);
+    with_webview(id, |webview| platform::set_pinch_zoom_enabled(webview, enabled))?;
+    get_state(id)?.pinch_zoom_enabled.store(enabled, Ordering::Release);
+    Ok(())
+}
+
+/// Enables or disables zooming the page with pinch gestures, e.g. when they
+/// conflict with the gestures of the page (see also `WebViewConfig::pinch_to_zoom`).
+///
+/// The setting is kept across navigations. WebKitGTK has no way to turn pinch
+/// zoom off, so disabling it returns `UnsupportedPlatform` on Linux.
+#[uniffi::export]
+pub fn set_pinch_to_zoom_enabled(id: u64, enabled: bool) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_pinch_to_zoom_enabled_inner(id, enabled));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_pinch_to_zoom_enabled_inner(id, enabled))
+}
+
+fn set_zoom_inner(id: u64, zoom: f64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_zoom id={} zoom={}", id, zoom);
+    with_webview(id, |webview| webview.zoom(zoom).map_err(WebViewError::from))?;
+    get_state(id)?.set_zoom(zoom);
+    Ok(())
+}
+
+/// Zooms the page by `zoom`, where 1.0 is the original size. Must be finite and
+/// greater than 0.
+#[uniffi::export]
+pub fn set_zoom(id: u64, zoom: f64) -> Result<(), WebViewError> {
+    if !zoom.is_finite() || zoom <= 0.0 {
+        return Err(WebViewError::InvalidArgument(format!("invalid zoom {}", zoom)));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_zoom_inner(id, zoom));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_zoom_inner(id, zoom))
+}
+
+/// Returns the zoom factor last set by `set_zoom`, 1.0 by default. Pinch zooming
+/// is not reflected.
+#[uniffi::export]
+pub fn get_zoom(id: u64) -> Result<f64, WebViewError> {
+    Ok(get_state(id)?.zoom())
+}
+
+fn set_allow_file_access_inner(id: u64, enabled: bool) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_allow_file_access id={} enabled={}", id, enabled);
+    with_webview(id, |webview| platform::set_allow_file_access(webview, enabled))?;
+    get_state(id)?.allow_file_access.store(enabled, Ordering::Release);
+    Ok(())
+}
+
+/// Lets pages loaded from `file://` URLs read other local files with `fetch`,
+/// `XMLHttpRequest` and the like (see also `WebViewConfig::allow_file_access`).
+///
+/// Any script in such a page can then read every file the process can read,
+/// and on Linux also any web origin, so only enable it for trusted local
+/// content. On Windows the flag is a browser argument of the WebView2
+/// environment, so `NotSupportedAtRuntime` is returned there.
+#[uniffi::export]
+pub fn set_allow_file_access(id: u64, enabled: bool) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_allow_file_access_inner(id, enabled));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_allow_file_access_inner(id, enabled))
+}
+
+fn set_proxy_inner(id: u64, config: ProxyConfig) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_proxy id={} proxy={}", id, config.url());
+    let state = get_state(id)?;
+    #[cfg(target_os = "linux")]
+    {
+        let context_key = web_context_key(&state.creation_config);
+        if context_key.is_none() {
+            return Err(WebViewError::SharedSettingsConflict(
+                "the default web context is shared by every webview without a proxy".to_string(),
+            ));
+        }
+        let sharing = state::registered_states().into_iter().find(|other| {
+            other.id() != id && web_context_key(&other.creation_config) == context_key
+        });
+        if let Some(other) = sharing {
+            return Err(WebViewError::SharedSettingsConflict(format!(
+                "webview {} uses the same web context",
+                other.id()
+            )));
+        }
+    }
+    with_webview(id, |webview| platform::set_proxy(webview, &config))?;
+    state.set_proxy(Some(config))
+}
+
+/// Routes the traffic of the WebView through a proxy.
+///
+/// Only Linux can change the proxy of an existing WebView, and only if it was
+/// created with a proxy or a data store that no other live WebView uses;
+/// otherwise `SharedSettingsConflict` is returned rather than rerouting the
+/// others. Elsewhere the proxy is fixed when the WebView is created (see
+/// `WebViewConfig::proxy`) and `NotSupportedAtRuntime` is returned.
+#[uniffi::export]
+pub fn set_proxy(id: u64, config: ProxyConfig) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_proxy_inner(id, config));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_proxy_inner(id, config))
+}
+
+fn set_media_autoplay_policy_inner(id: u64, policy: AutoplayPolicy) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_media_autoplay_policy id={} policy={:?}", id, policy);
+    with_webview(id, |webview| platform::set_autoplay_policy(webview, policy))?;
+    get_state(id)?.set_autoplay_policy(Some(policy))
+}
+
+/// Controls whether media may start playing without a user gesture.
+///
+/// Only Linux can change the policy of an existing WebView; media that is already
+/// playing keeps playing. Elsewhere the policy is fixed when the WebView is created
+/// (see `WebViewConfig::autoplay_policy`) and `NotSupportedAtRuntime` is returned.
+/// Without `Allow`, the engine default applies, which usually lets muted media play.
+#[uniffi::export]
+pub fn set_media_autoplay_policy(id: u64, policy: AutoplayPolicy) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_media_autoplay_policy_inner(id, policy));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_media_autoplay_policy_inner(id, policy))
+}
+
+// ============================================================================
+// DevTools
+// ============================================================================
+
+fn set_devtools_open_inner(id: u64, open: bool) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_devtools_open id={} open={}", id, open);
+    let state = get_state(id)?;
+    if !state.creation_config.devtools_enabled {
+        return Err(WebViewError::DevToolsDisabled);
+    }
+    with_webview(id, |webview| {
+        if open {
+            webview.open_devtools();
+        } else {
+            webview.close_devtools();
+        }
+        Ok(())
+    })?;
+    state.devtools_open.store(open, Ordering::Release);
+    Ok(())
+}
+
+fn set_devtools_open(id: u64, open: bool) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_devtools_open_inner(id, open));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_devtools_open_inner(id, open))
+}
+
+/// Opens the inspector of the WebView: the Web Inspector on macOS and Linux,
+/// the Edge DevTools on Windows.
+///
+/// Fails with `DevToolsDisabled` unless the WebView was created with
+/// `WebViewConfig::devtools_enabled`.
+#[uniffi::export]
+pub fn open_devtools(id: u64) -> Result<(), WebViewError> {
+    set_devtools_open(id, true)
+}
+
+/// Closes the inspector opened by `open_devtools`.
+#[uniffi::export]
+pub fn close_devtools(id: u64) -> Result<(), WebViewError> {
+    set_devtools_open(id, false)
+}
+
+/// Returns whether DevTools were opened through `open_devtools` and not closed
+/// since. Closing the inspector window by hand is not reflected.
+#[uniffi::export]
+pub fn is_devtools_open(id: u64) -> Result<bool, WebViewError> {
+    Ok(get_state(id)?.devtools_open.load(Ordering::Acquire))
+}
+
+// ============================================================================
+// Audio
+// ============================================================================
+
+fn set_muted_inner(id: u64, muted: bool) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_muted id={} muted={}", id, muted);
+    with_webview(id, |webview| platform::set_muted(webview, muted))?;
+    get_state(id)?.is_muted.store(muted, Ordering::Release);
+    Ok(())
+}
+
+fn set_muted(id: u64, muted: bool) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    {
+        return run_on_gtk_thread(move || set_muted_inner(id, muted));
     }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || set_muted_inner(id, muted))
+}
+
+/// Silences the audio of the WebView, independently of the system volume.
+///
+/// On macOS versions without the private page muting API, only the media
+/// elements present in the current page are muted.
+#[uniffi::export]
+pub fn mute(id: u64) -> Result<(), WebViewError> {
+    set_muted(id, true)
+}
+
+#[uniffi::export]
+pub fn unmute(id: u64) -> Result<(), WebViewError> {
+    set_muted(id, false)
+}
+
+#[uniffi::export]
+pub fn get_muted(id: u64) -> Result<bool, WebViewError> {
+    Ok(get_state(id)?.is_muted.load(Ordering::Acquire))
+}
+
+// ============================================================================
+// Fullscreen
+// ============================================================================
+
+/// Registers the handler notified of fullscreen changes.
+///
+/// While a handler is set, the platform does not resize any window itself.
+#[uniffi::export]
+pub fn set_fullscreen_handler(
+    id: u64,
+    handler: Arc<dyn FullscreenHandler>,
+) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_fullscreen_handler id={}", id);
+    get_state(id)?.fullscreen_handler.set(Some(handler))
+}
+
+#[uniffi::export]
+pub fn clear_fullscreen_handler(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] clear_fullscreen_handler id={}", id);
+    get_state(id)?.fullscreen_handler.set(None)
+}
+
+fn exit_fullscreen_inner(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] exit_fullscreen id={}", id);
     with_webview(id, |webview| {
         webview
-            .evaluate_script("window.history.back()")
+            .evaluate_script(scripts::EXIT_FULLSCREEN_SCRIPT)
             .map_err(WebViewError::from)
     })
 }
 
+/// Makes the page leave fullscreen; the handler is then notified as usual.
 #[uniffi::export]
-pub fn go_back(id: u64) -> Result<(), WebViewError> {
+pub fn exit_fullscreen(id: u64) -> Result<(), WebViewError> {
     #[cfg(target_os = "linux")]
     {
-        return run_on_gtk_thread(move || go_back_inner(id));
+        return run_on_gtk_thread(move || exit_fullscreen_inner(id));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || exit_fullscreen_inner(id))
+}
+
+// ============================================================================
+// Context Menu
+// ============================================================================
+
+/// Asks the `ContextMenuHandler` which menu to show; `ShowDefault` without handler.
+pub(crate) fn decide_context_menu(
+    state: &WebViewState,
+    x: i32,
+    y: i32,
+    items: Vec<ContextMenuItem>,
+) -> ContextMenuDecision {
+    let Some(handler) = state.context_menu_handler.get() else {
+        return ContextMenuDecision::ShowDefault;
+    };
+    let decision = handler.on_context_menu(state.id(), x, y, items);
+    eprintln!("[wrywebview] context_menu x={} y={} decision={:?}", x, y, decision);
+    decision
+}
+
+pub(crate) fn context_menu_item_selected(state: &WebViewState, action_id: String) {
+    eprintln!("[wrywebview] context_menu item_selected action_id={}", action_id);
+    if let Some(handler) = state.context_menu_handler.get() {
+        handler.on_item_selected(state.id(), action_id);
     }
+}
+
+/// Registers the handler that suppresses, keeps or replaces the context menu.
+#[uniffi::export]
+pub fn set_context_menu_handler(
+    id: u64,
+    handler: Arc<dyn ContextMenuHandler>,
+) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_context_menu_handler id={}", id);
+    get_state(id)?.context_menu_handler.set(Some(handler))
+}
 
-    #[cfg(not(target_os = "linux"))]
-    run_on_main_thread(move || go_back_inner(id))
+#[uniffi::export]
+pub fn clear_context_menu_handler(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] clear_context_menu_handler id={}", id);
+    get_state(id)?.context_menu_handler.set(None)
 }
 
-fn go_forward_inner(id: u64) -> Result<(), WebViewError> {
-    eprintln!("[wrywebview] go_forward id={}", id);
-    if let Ok(state) = get_state(id) {
-        state.is_loading.store(true, Ordering::SeqCst);
+// ============================================================================
+// Focus
+// ============================================================================
+
+fn focus_inner(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] focus id={}", id);
+    let state = get_state(id)?;
+    if !state.pointer_events_enabled.load(Ordering::Acquire) {
+        // A click-through WebView must not take the keyboard from the views below.
+        return Ok(());
     }
     with_webview(id, |webview| {
+        platform::focus(webview)?;
+        // The native focus reaches the WebView; the page still has to focus its document.
         webview
-            .evaluate_script("window.history.forward()")
+            .evaluate_script("document.documentElement.focus(); window.focus();")
             .map_err(WebViewError::from)
-    })
+    })?;
+    state.has_focus.store(true, Ordering::Release);
+    Ok(())
 }
 
+/// Gives the keyboard focus to the WebView and its document.
+///
+/// While pointer events pass through the WebView (`set_pointer_events_passthrough`),
+/// the call does nothing: the focus stays with the views below and `has_focus`
+/// remains false.
 #[uniffi::export]
-pub fn go_forward(id: u64) -> Result<(), WebViewError> {
+pub fn focus(id: u64) -> Result<(), WebViewError> {
     #[cfg(target_os = "linux")]
     {
-        return run_on_gtk_thread(move || go_forward_inner(id));
+        return run_on_gtk_thread(move || focus_inner(id));
     }
 
     #[cfg(not(target_os = "linux"))]
-    run_on_main_thread(move || go_forward_inner(id))
+    run_on_main_thread(move || focus_inner(id))
 }
 
-fn reload_inner(id: u64) -> Result<(), WebViewError> {
-    eprintln!("[wrywebview] reload id={}", id);
-    if let Ok(state) = get_state(id) {
-        state.is_loading.store(true, Ordering::SeqCst);
-    }
+fn blur_inner(id: u64) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] blur id={}", id);
+    let state = get_state(id)?;
+    let parent_handle = state.parent_handle()?;
     with_webview(id, |webview| {
         webview
-            .evaluate_script("window.location.reload()")
-            .map_err(WebViewError::from)
-    })
+            .evaluate_script("document.activeElement && document.activeElement.blur();")
+            .map_err(WebViewError::from)?;
+        platform::blur(webview, parent_handle)
+    })?;
+    state.has_focus.store(false, Ordering::Release);
+    Ok(())
 }
 
+/// Removes the keyboard focus from the WebView and hands it back to the parent
+/// window, e.g. before focusing a native input field.
 #[uniffi::export]
-pub fn reload(id: u64) -> Result<(), WebViewError> {
+pub fn blur(id: u64) -> Result<(), WebViewError> {
     #[cfg(target_os = "linux")]
     {
-        return run_on_gtk_thread(move || reload_inner(id));
+        return run_on_gtk_thread(move || blur_inner(id));
     }
 
     #[cfg(not(target_os = "linux"))]
-    run_on_main_thread(move || reload_inner(id))
+    run_on_main_thread(move || blur_inner(id))
 }
 
-// ============================================================================
-// Focus
-// ============================================================================
-
-fn focus_inner(id: u64) -> Result<(), WebViewError> {
-    eprintln!("[wrywebview] focus id={}", id);
-    with_webview(id, |webview| {
-        // On Linux, we need to use X11 focus directly since the GTK widget
-        // is embedded in a foreign (AWT/Swing) window hierarchy
-        #[cfg(target_os = "linux")]
-        {
-            use gdkx11::glib::translate::ToGlibPtr;
-            use gdkx11::glib::Cast;
-            use gdkx11::X11Display;
-            use gtk::prelude::WidgetExt;
-
-            let gtk_widget = webview.webview();
-            gtk_widget.set_can_focus(true);
-
-            // First, ensure the widget is realized and has a window
-            if !gtk_widget.is_realized() {
-                gtk_widget.realize();
-            }
-
-            // Get the GDK window and use X11 to set focus
-            if let Some(gdk_window) = gtk_widget.window() {
-                // Get the X11 display from GDK
-                if let Some(display) = gdk::Display::default() {
-                    if let Ok(x11_display) = display.downcast::<X11Display>() {
-                        unsafe {
-                            // Get the X11 window ID (XID) from the GDK window
-                            let gdk_window_ptr: *mut gdk::ffi::GdkWindow = gdk_window.to_glib_none().0;
-                            let xid = gdkx11::ffi::gdk_x11_window_get_xid(
-                                gdk_window_ptr as *mut gdkx11::ffi::GdkX11Window,
-                            );
-
-                            if xid != 0 {
-                                // Get the raw X11 display pointer
-                                let x11_display_ptr: *mut gdkx11::ffi::GdkX11Display = x11_display.to_glib_none().0;
-                                let x_display = gdkx11::ffi::gdk_x11_display_get_xdisplay(x11_display_ptr);
-
-                                if !x_display.is_null() {
-                                    // XSetInputFocus: RevertToParent = 2, CurrentTime = 0
-                                    x11::xlib::XSetInputFocus(
-                                        x_display as *mut x11::xlib::Display,
-                                        xid,
-                                        x11::xlib::RevertToParent,
-                                        x11::xlib::CurrentTime,
-                                    );
-                                    eprintln!("[wrywebview] XSetInputFocus xid=0x{:x}", xid);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-
-            // Also call GTK grab_focus as a fallback
-            gtk_widget.grab_focus();
-            eprintln!("[wrywebview] gtk grab_focus called");
-        }
+/// Returns whether the WebView holds the keyboard focus, as last set by `focus`
+/// and `blur`.
+#[uniffi::export]
+pub fn has_focus(id: u64) -> Result<bool, WebViewError> {
+    let state = get_state(id)?;
+    Ok(state.has_focus.load(Ordering::Acquire))
+}
 
-        webview
-            .evaluate_script("document.documentElement.focus(); window.focus();")
-            .map_err(WebViewError::from)
-    })
+fn set_pointer_events_passthrough_inner(id: u64, passthrough: bool) -> Result<(), WebViewError> {
+    eprintln!("[wrywebview] set_pointer_events_passthrough id={} passthrough={}", id, passthrough);
+    let state = get_state(id)?;
+    with_webview(id, |webview| platform::set_pointer_passthrough(webview, passthrough))?;
+    state.pointer_events_enabled.store(!passthrough, Ordering::Release);
+    Ok(())
 }
 
+/// Lets mouse events pass through the WebView to the window below, e.g. for an
+/// informational overlay. While enabled, `focus` does nothing.
 #[uniffi::export]
-pub fn focus(id: u64) -> Result<(), WebViewError> {
+pub fn set_pointer_events_passthrough(id: u64, passthrough: bool) -> Result<(), WebViewError> {
     #[cfg(target_os = "linux")]
     {
-        return run_on_gtk_thread(move || focus_inner(id));
+        return run_on_gtk_thread(move || set_pointer_events_passthrough_inner(id, passthrough));
     }
 
     #[cfg(not(target_os = "linux"))]
-    run_on_main_thread(move || focus_inner(id))
+    run_on_main_thread(move || set_pointer_events_passthrough_inner(id, passthrough))
+}
+
+/// Returns whether mouse events pass through the WebView, as last set by
+/// `set_pointer_events_passthrough`.
+#[uniffi::export]
+pub fn is_pointer_events_passthrough(id: u64) -> Result<bool, WebViewError> {
+    let state = get_state(id)?;
+    Ok(!state.pointer_events_enabled.load(Ordering::Acquire))
 }
 
 // ============================================================================
 // State Queries
 // ============================================================================
 
+/// Returns the IDs of all live WebViews, e.g. to destroy them with their window.
+#[uniffi::export]
+pub fn list_webviews() -> Vec<u64> {
+    state::registered_ids()
+}
+
+/// Returns the version of the browser engine: WebView2 on Windows, WebKit on
+/// macOS and WebKitGTK on Linux, or `"unknown"` if it cannot be read.
+#[uniffi::export]
+pub fn get_webview_engine_version() -> String {
+    wry::webview_version().unwrap_or_else(|e| {
+        eprintln!("[wrywebview] get_webview_engine_version failed: {}", e);
+        "unknown".to_string()
+    })
+}
+
 #[uniffi::export]
 pub fn get_url(id: u64) -> Result<String, WebViewError> {
     let state = get_state(id)?;
-    let url = state
-        .current_url
-        .lock()
-        .map_err(|_| WebViewError::Internal("url lock poisoned".to_string()))?;
-    Ok(url.clone())
+    Ok(state.current_url.load().as_ref().clone())
+}
+
+/// Returns when the WebView was created, in milliseconds since the UNIX epoch.
+#[uniffi::export]
+pub fn get_created_at(id: u64) -> Result<u64, WebViewError> {
+    Ok(get_state(id)?.created_at())
 }
 
 #[uniffi::export]
 pub fn is_loading(id: u64) -> Result<bool, WebViewError> {
     let state = get_state(id)?;
-    Ok(state.is_loading.load(Ordering::SeqCst))
+    Ok(state.is_loading.load(Ordering::Acquire))
 }
 
 #[uniffi::export]
@@ -684,7 +4000,7 @@ pub fn get_title(id: u64) -> Result<String, WebViewError> {
     let state = get_state(id)?;
     let title = state
         .page_title
-        .lock()
+        .read()
         .map_err(|_| WebViewError::Internal("title lock poisoned".to_string()))?;
     Ok(title.clone())
 }
@@ -701,12 +4017,84 @@ pub fn can_go_forward(id: u64) -> Result<bool, WebViewError> {
     state.can_go_forward()
 }
 
+/// Returns the URL, title and navigation state of the WebView in one call.
+///
+/// Only cached state is read, so the fields are consistent with each other and
+/// the call never waits for the page.
+#[uniffi::export]
+pub fn get_webview_info(id: u64) -> Result<WebViewInfo, WebViewError> {
+    get_state(id)?.info()
+}
+
+/// Returns the configuration the WebView was created with, e.g. to check that
+/// an option was applied. Settings changed since creation are not reflected.
+#[uniffi::export]
+pub fn get_creation_config(id: u64) -> Result<WebViewConfig, WebViewError> {
+    Ok(get_state(id)?.creation_config.clone())
+}
+
 #[uniffi::export]
 pub fn drain_ipc_messages(id: u64) -> Result<Vec<String>, WebViewError> {
     let state = get_state(id)?;
     state.drain_ipc_messages()
 }
 
+// ============================================================================
+// Events
+// ============================================================================
+
+/// Receiving end of `subscribe_events`.
+#[derive(uniffi::Object)]
+pub struct WebViewEventReceiver {
+    receiver: Mutex<mpsc::Receiver<WebViewEvent>>,
+}
+
+impl WebViewEventReceiver {
+    fn receiver(&self) -> Result<MutexGuard<'_, mpsc::Receiver<WebViewEvent>>, WebViewError> {
+        self.receiver
+            .lock()
+            .map_err(|_| WebViewError::Internal("event receiver lock poisoned".to_string()))
+    }
+}
+
+#[uniffi::export]
+impl WebViewEventReceiver {
+    /// Returns the next event without blocking, or `None` if there is none yet,
+    /// e.g. to poll from the Compose frame loop.
+    pub fn poll_event(&self) -> Result<Option<WebViewEvent>, WebViewError> {
+        Ok(self.receiver()?.try_recv().ok())
+    }
+
+    /// Returns up to `max` pending events, oldest first, without blocking.
+    pub fn drain_events(&self, max: u32) -> Result<Vec<WebViewEvent>, WebViewError> {
+        let receiver = self.receiver()?;
+        Ok(receiver.try_iter().take(max as usize).collect())
+    }
+
+    /// Waits at most `timeout_ms` for the next event. Returns `None` on timeout,
+    /// or once the WebView is destroyed or another subscriber replaced this one.
+    pub fn recv_timeout(&self, timeout_ms: u64) -> Result<Option<WebViewEvent>, WebViewError> {
+        Ok(self
+            .receiver()?
+            .recv_timeout(Duration::from_millis(timeout_ms))
+            .ok())
+    }
+}
+
+/// Streams the navigation, load, title, IPC and error events of the WebView.
+///
+/// The registered handlers keep being called. There is one subscriber at a
+/// time: subscribing again closes the previous receiver. Events are dropped
+/// while the receiver is full (256 events), so the UI thread never blocks.
+#[uniffi::export]
+pub fn subscribe_events(id: u64) -> Result<Arc<WebViewEventReceiver>, WebViewError> {
+    eprintln!("[wrywebview] subscribe_events id={}", id);
+    let receiver = get_state(id)?.subscribe_events()?;
+    Ok(Arc::new(WebViewEventReceiver {
+        receiver: Mutex::new(receiver),
+    }))
+}
+
 // ============================================================================
 // Cookies
 // ============================================================================
@@ -801,49 +4189,421 @@ pub fn set_cookie(id: u64, cookie: WebViewCookie) -> Result<(), WebViewError> {
 
 fn destroy_webview_inner(id: u64) -> Result<(), WebViewError> {
     eprintln!("[wrywebview] destroy_webview id={}", id);
+    if let Ok(state) = get_state(id) {
+        if let Some(group_id) = state.group_id()? {
+            state::leave_group(group_id, &state)?;
+        }
+    }
     unregister(id)
 }
 
+/// Destroys the WebView and releases its native resources.
+///
+/// Safe to call from any thread, e.g. a Kotlin background coroutine: the call
+/// blocks until the thread that owns the WebViews has destroyed it.
 #[uniffi::export]
 pub fn destroy_webview(id: u64) -> Result<(), WebViewError> {
-    #[cfg(target_os = "macos")]
+    #[cfg(target_os = "linux")]
     {
-        if MainThreadMarker::new().is_some() {
+        return run_on_gtk_thread(move || destroy_webview_inner(id));
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    run_on_main_thread(move || destroy_webview_inner(id))
+}
+
+// ============================================================================
+// WebView Pool
+// ============================================================================
+
+/// Keeps released WebViews alive, hidden on `about:blank`, so they can be reused
+/// instead of paying for the creation of a new WebView.
+///
+/// A reused WebView keeps its handlers and settings; callers that change them
+/// should reset them before releasing it. Dropping the pool destroys its idle
+/// WebViews; the acquired ones stay with their callers.
+#[derive(uniffi::Object)]
+pub struct WebViewPool {
+    capacity: usize,
+    idle: Mutex<Vec<u64>>,
+    /// WebViews handed out by `acquire` and not released since.
+    acquired: Mutex<HashSet<u64>>,
+}
+
+impl WebViewPool {
+    fn idle(&self) -> Result<MutexGuard<'_, Vec<u64>>, WebViewError> {
+        self.idle
+            .lock()
+            .map_err(|_| WebViewError::Internal("pool lock poisoned".to_string()))
+    }
+
+    fn acquired(&self) -> Result<MutexGuard<'_, HashSet<u64>>, WebViewError> {
+        self.acquired
+            .lock()
+            .map_err(|_| WebViewError::Internal("pool lock poisoned".to_string()))
+    }
+
+    fn park(&self, id: u64) -> Result<(), WebViewError> {
+        with_webview(id, |webview| webview.set_visible(false).map_err(WebViewError::from))?;
+        load_url_inner(id, "about:blank".to_string())?;
+        self.idle()?.push(id);
+        Ok(())
+    }
+
+    fn prewarm_inner(&self, parent_handle: u64) -> Result<(), WebViewError> {
+        while self.idle()?.len() < self.capacity {
+            let id = create_webview_inner(
+                parent_handle,
+                0,
+                0,
+                1,
+                1,
+                WebViewConfig::from_url("about:blank".to_string()),
+            )?;
+            if let Err(e) = self.park(id) {
+                if let Err(destroy_error) = destroy_webview_inner(id) {
+                    eprintln!("[wrywebview] pool prewarm cleanup failed: {}", destroy_error);
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    fn acquire_inner(
+        &self,
+        parent_handle: u64,
+        width: i32,
+        height: i32,
+        url: String,
+    ) -> Result<u64, WebViewError> {
+        check_creation(&url, width, height)?;
+        let id = match self.idle()?.pop() {
+            Some(id) => {
+                eprintln!("[wrywebview] pool acquire reuses id={}", id);
+                move_to_parent_inner(id, parent_handle)?;
+                apply_bounds(id, 0.0, 0.0, width.into(), height.into())?;
+                with_webview(id, |webview| {
+                    webview.set_visible(true).map_err(WebViewError::from)
+                })?;
+                load_url_inner(id, url)?;
+                id
+            }
+            None => {
+                let config = WebViewConfig::from_url(url);
+                create_webview_inner(parent_handle, 0, 0, width, height, config)?
+            }
+        };
+        self.acquired()?.insert(id);
+        Ok(id)
+    }
+
+    fn release_inner(&self, id: u64) -> Result<(), WebViewError> {
+        eprintln!("[wrywebview] pool release id={}", id);
+        if !self.acquired()?.remove(&id) {
+            return Err(WebViewError::InvalidArgument(format!(
+                "webview {} is not acquired from this pool",
+                id
+            )));
+        }
+        if self.idle()?.len() >= self.capacity {
             return destroy_webview_inner(id);
         }
-        DispatchQueue::main().exec_async(move || {
-            let _ = destroy_webview_inner(id);
-        });
-        return Ok(());
+        self.park(id)
+    }
+}
+
+#[uniffi::export]
+impl WebViewPool {
+    /// Creates an empty pool keeping at most `capacity` idle WebViews.
+    #[uniffi::constructor]
+    pub fn new(capacity: u32) -> Arc<Self> {
+        Arc::new(Self {
+            capacity: capacity as usize,
+            idle: Mutex::new(Vec::new()),
+            acquired: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Creates idle WebViews in `parent_handle` until the pool is full.
+    pub fn prewarm(self: Arc<Self>, parent_handle: u64) -> Result<(), WebViewError> {
+        #[cfg(target_os = "linux")]
+        {
+            return run_on_gtk_thread(move || self.prewarm_inner(parent_handle));
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        run_on_main_thread(move || self.prewarm_inner(parent_handle))
+    }
+
+    /// Returns an idle WebView moved into `parent_handle` and loading `url`, or a new
+    /// WebView when the pool is empty.
+    pub fn acquire(
+        self: Arc<Self>,
+        parent_handle: u64,
+        width: i32,
+        height: i32,
+        url: String,
+    ) -> Result<u64, WebViewError> {
+        #[cfg(target_os = "linux")]
+        {
+            return run_on_gtk_thread(move || self.acquire_inner(parent_handle, width, height, url));
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        run_on_main_thread(move || self.acquire_inner(parent_handle, width, height, url))
+    }
+
+    /// Hides the WebView and parks it on `about:blank`; it is destroyed instead
+    /// when the pool is full.
+    ///
+    /// Fails with `InvalidArgument` for a WebView this pool did not hand out, or
+    /// one already released.
+    pub fn release(self: Arc<Self>, id: u64) -> Result<(), WebViewError> {
+        #[cfg(target_os = "linux")]
+        {
+            return run_on_gtk_thread(move || self.release_inner(id));
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        run_on_main_thread(move || self.release_inner(id))
+    }
+
+    /// Returns the number of idle WebViews.
+    pub fn idle_count(&self) -> Result<u32, WebViewError> {
+        Ok(self.idle()?.len() as u32)
+    }
+}
+
+impl Drop for WebViewPool {
+    fn drop(&mut self) {
+        let idle = match self.idle.get_mut() {
+            Ok(idle) => std::mem::take(idle),
+            Err(poisoned) => std::mem::take(poisoned.into_inner()),
+        };
+        if idle.is_empty() {
+            return;
+        }
+        eprintln!("[wrywebview] pool dropped, destroying {} idle webviews", idle.len());
+        let destroy = move || {
+            for id in idle {
+                if let Err(e) = destroy_webview_inner(id) {
+                    eprintln!("[wrywebview] pool destroy id={} failed: {}", id, e);
+                }
+            }
+            Ok(())
+        };
+
+        #[cfg(target_os = "linux")]
+        let result = run_on_gtk_thread(destroy);
+        #[cfg(not(target_os = "linux"))]
+        let result = run_on_main_thread(destroy);
+
+        if let Err(e) = result {
+            eprintln!("[wrywebview] pool drop failed: {}", e);
+        }
     }
+}
+
+// ============================================================================
+// Threading
+// ============================================================================
+
+/// Sets how long calls wait for the UI thread (the main thread on macOS, the GTK
+/// thread on Linux, the thread that created the WebViews on Windows) before
+/// failing with `MainThreadTimeout`; 0 waits forever.
+///
+/// Defaults to 10 seconds. A call the UI thread has not started by then is
+/// cancelled and never runs. On Windows, calls made from the UI thread run
+/// inline; calls from other threads wait for its next `pump_windows_events`.
+///
+/// Calls returning a script result, like `get_page_source`, also wait this long
+/// for the page to produce it.
+#[uniffi::export]
+pub fn set_main_thread_timeout(timeout_ms: u64) {
+    eprintln!("[wrywebview] set_main_thread_timeout timeout_ms={}", timeout_ms);
+    platform::set_main_thread_timeout_ms(timeout_ms);
+}
 
+/// Returns whether the calling thread is the one WebViews must be used from:
+/// the GTK thread on Linux. Always `true` on the other platforms, whose calls
+/// are routed by `run_on_main_thread`.
+#[uniffi::export]
+pub fn is_on_gtk_thread() -> bool {
     #[cfg(target_os = "linux")]
     {
-        return run_on_gtk_thread(move || destroy_webview_inner(id));
+        return platform::linux::gtk_thread_id() == Some(std::thread::current().id());
     }
 
-    #[cfg(any(target_os = "windows", not(any(target_os = "linux", target_os = "macos"))))]
-    run_on_main_thread(move || destroy_webview_inner(id))
+    #[cfg(not(target_os = "linux"))]
+    true
 }
 
 // ============================================================================
 // Event Pumps
 // ============================================================================
 
+/// Waits until the GTK thread has handled every event and call queued so far,
+/// e.g. to synchronize a test with the WebView. Does nothing on the other
+/// platforms.
+///
+/// GTK events are pumped continuously on that thread, so this is never needed
+/// to keep the WebViews responsive.
 #[uniffi::export]
 pub fn pump_gtk_events() {
     #[cfg(target_os = "linux")]
     {
-        // Events are pumped continuously on the dedicated GTK thread.
+        if let Err(e) = platform::linux::flush_gtk_events() {
+            eprintln!("[wrywebview] pump_gtk_events failed: {}", e);
+        }
     }
 }
 
+/// Handles pending Windows messages, up to the limit set with `set_pump_message_limit`
+/// (64 by default).
 #[uniffi::export]
 pub fn pump_windows_events() {
     #[cfg(target_os = "windows")]
     {
-        platform::windows::pump_events();
+        platform::windows::pump_events(None);
+    }
+}
+
+/// Handles at most `max` pending Windows messages.
+#[uniffi::export]
+pub fn pump_windows_events_with_limit(max: u32) {
+    #[cfg(target_os = "windows")]
+    {
+        platform::windows::pump_events(Some(max));
     }
+    #[cfg(not(target_os = "windows"))]
+    let _ = max;
+}
+
+/// Sets the number of messages handled by each `pump_windows_events` call.
+#[uniffi::export]
+pub fn set_pump_message_limit(n: u32) {
+    #[cfg(target_os = "windows")]
+    {
+        platform::windows::set_pump_message_limit(n);
+    }
+    #[cfg(not(target_os = "windows"))]
+    let _ = n;
 }
 
 uniffi::setup_scaffolding!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_malformed_urls_before_creating() {
+        for url in ["not a url", ""] {
+            let result = create_webview(0, 0, 0, 100, 100, url.to_string());
+            assert!(matches!(result, Err(WebViewError::InvalidUrl(u)) if u == url));
+        }
+    }
+
+    #[test]
+    fn rejects_empty_and_negative_sizes() {
+        // No WebView has this ID, so bounds that pass validation fail to find it.
+        let id = u64::MAX;
+        assert!(matches!(set_bounds(id, 0, 0, 0, 0), Err(WebViewError::InvalidArgument(_))));
+        assert!(matches!(set_bounds(id, -1, -1, 100, 100), Err(WebViewError::WebViewNotFound(_))));
+        assert!(matches!(set_bounds(id, 0, 0, -1, 100), Err(WebViewError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn rejects_fractional_and_infinite_sizes() {
+        let id = u64::MAX;
+        for (width, height) in [(0.5, 100.0), (100.0, f64::INFINITY), (f64::NAN, 100.0)] {
+            let result = set_bounds_f64(id, 0.0, 0.0, width, height);
+            assert!(matches!(result, Err(WebViewError::InvalidArgument(_))));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn creation_times_out_while_the_ui_thread_is_busy() {
+        if let Err(e) = run_on_gtk_thread(|| Ok(())) {
+            eprintln!("skipped: {}", e);
+            return;
+        }
+
+        // Stands in for a slow engine: the GTK thread is busy for 50 ms.
+        let (started_tx, started_rx) = mpsc::channel();
+        let busy = std::thread::spawn(move || {
+            run_on_gtk_thread(move || {
+                let _ = started_tx.send(());
+                std::thread::sleep(Duration::from_millis(50));
+                Ok(())
+            })
+        });
+        started_rx.recv().unwrap();
+
+        let config = WebViewConfig {
+            initialization_timeout_ms: 10,
+            ..WebViewConfig::from_url("about:blank".to_string())
+        };
+        let result = create_webview_with_config(0, 0, 0, 100, 100, config);
+        assert!(matches!(result, Err(WebViewError::InitializationTimeout(10))));
+        busy.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn rejects_negative_insets() {
+        let result = set_auto_resize_insets(u64::MAX, 0, -1, 0, 0);
+        assert!(matches!(result, Err(WebViewError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn gives_groups_their_own_data_store() {
+        let group_id = create_webview_group().unwrap();
+        let other_group_id = create_webview_group().unwrap();
+        let config = WebViewConfig::from_url("about:blank".to_string());
+        let store = group_data_store(group_id, &config).unwrap();
+        assert_ne!(store, group_data_store(other_group_id, &config).unwrap());
+
+        let named = WebViewConfig {
+            data_store_identifier: Some(store.clone()),
+            ..config.clone()
+        };
+        assert_eq!(group_data_store(group_id, &named).unwrap(), store);
+        let conflicting = WebViewConfig {
+            data_store_identifier: Some("profile".to_string()),
+            ..config.clone()
+        };
+        let result = group_data_store(group_id, &conflicting);
+        assert!(matches!(result, Err(WebViewError::InvalidArgument(_))));
+        let result = group_data_store(u64::MAX, &config);
+        assert!(matches!(result, Err(WebViewError::GroupNotFound(u64::MAX))));
+    }
+
+    #[test]
+    fn rejects_invalid_zooms() {
+        for zoom in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let result = set_zoom(u64::MAX, zoom);
+            assert!(matches!(result, Err(WebViewError::InvalidArgument(_))), "zoom {}", zoom);
+        }
+    }
+
+    #[test]
+    fn validates_internal_creations() {
+        // The path taken by pooled WebViews and links opened in a new WebView.
+        let config = WebViewConfig::from_url("not a url".to_string());
+        let result = create_webview_inner(0, 0, 0, 100, 100, config);
+        assert!(matches!(result, Err(WebViewError::InvalidUrl(_))));
+
+        let config = WebViewConfig::from_url("about:blank".to_string());
+        let result = create_webview_inner(0, 0, 0, -1, 100, config);
+        assert!(matches!(result, Err(WebViewError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn rejects_a_missing_parent_window() {
+        let config = WebViewConfig::from_url("https://example.com".to_string());
+        let result = create_webview_inner(0, 0, 0, 100, 100, config);
+        assert!(matches!(result, Err(WebViewError::InvalidWindowHandle(0))));
+    }
+}
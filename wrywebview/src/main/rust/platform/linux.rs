@@ -1,15 +1,45 @@
 //! Linux-specific GTK thread management.
 
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::os::raw::{c_int, c_uint, c_ulong};
+use std::sync::atomic::Ordering;
 use std::sync::mpsc;
-use std::sync::OnceLock;
+use std::sync::{Arc, OnceLock};
+use std::thread::ThreadId;
 use std::time::Duration;
 
+use gdkx11::glib::translate::ToGlibPtr;
+use gdkx11::glib::Cast;
+use gdkx11::X11Display;
+use gio::prelude::{ActionExt, TlsCertificateExt};
+use gtk::prelude::{GtkWindowExt, WidgetExt};
+use gdkx11::glib::ObjectExt;
+use glib::object::ObjectType;
+use webkit2gtk::{
+    AuthenticationRequestExt, ContextMenuExt, ContextMenuItemExt, Credential, CredentialPersistence,
+    DownloadExt, FileChooserRequestExt, GeolocationPermissionRequest, HardwareAccelerationPolicy,
+    InsecureContentEvent, LoadEvent, NetworkError, NetworkProxyMode, NetworkProxySettings,
+    NotificationPermissionRequest, PermissionRequest, PermissionRequestExt, PolicyError,
+    ScriptDialogType, SettingsExt, URIRequest, URIRequestExt, URIResponseExt,
+    UserMediaPermissionRequest, WebContextExt,
+    WebResourceExt, WebViewExt, WebsiteDataManager, WebsiteDataManagerExt,
+};
+use wry::{WebView, WebViewExtUnix};
+
 use crate::error::WebViewError;
+use crate::platform::{CancellationToken, CertificateChainCompletion};
+use crate::state::WebViewState;
+use crate::{
+    AuthAnswer, AutoplayPolicy, ContextMenuDecision, ContextMenuItem, PermissionResource,
+    PermissionResponse, ProxyConfig, SslWarningType, TlsDecision,
+};
 
 type GtkTask = Box<dyn FnOnce() + Send + 'static>;
 
 struct GtkRunner {
     sender: mpsc::Sender<GtkTask>,
+    thread_id: ThreadId,
     init_error: Option<String>,
 }
 
@@ -20,8 +50,8 @@ fn gtk_runner() -> Result<&'static GtkRunner, WebViewError> {
         let (task_tx, task_rx) = mpsc::channel::<GtkTask>();
         let (init_tx, init_rx) = mpsc::sync_channel::<Result<(), String>>(1);
 
-        std::thread::spawn(move || {
-            let init_result = gtk::init().map_err(|err| err.to_string());
+        let thread = std::thread::spawn(move || {
+            let init_result = init_gtk();
             let _ = init_tx.send(init_result.clone());
 
             if init_result.is_err() {
@@ -45,40 +75,931 @@ fn gtk_runner() -> Result<&'static GtkRunner, WebViewError> {
 
         GtkRunner {
             sender: task_tx,
+            thread_id: thread.thread().id(),
             init_error: init_result.err(),
         }
     });
 
     if let Some(err) = runner.init_error.as_ref() {
-        return Err(WebViewError::GtkInit(err.clone()));
+        return Err(WebViewError::PlatformInitFailed(err.clone()));
     }
 
     Ok(runner)
 }
 
-/// Runs a closure on the dedicated GTK thread.
+/// Returns the ID of the GTK thread, or `None` if it has not been started or
+/// failed to initialize GTK.
+pub fn gtk_thread_id() -> Option<ThreadId> {
+    GTK_RUNNER
+        .get()
+        .filter(|runner| runner.init_error.is_none())
+        .map(|runner| runner.thread_id)
+}
+
+/// Waits until the GTK thread has handled the GLib events and tasks queued
+/// before the call. Does nothing if the GTK thread has not been started.
+pub fn flush_gtk_events() -> Result<(), WebViewError> {
+    if gtk_thread_id().is_none() {
+        return Ok(());
+    }
+    run_on_gtk_thread(|| {
+        while gtk::events_pending() {
+            gtk::main_iteration_do(false);
+        }
+        Ok(())
+    })
+}
+
+/// Makes WebKitGTK serve its inspector over HTTP on `port`.
+///
+/// The web process reads the variable when it starts, so this must run before
+/// the first WebView is created.
+pub fn enable_remote_inspector(port: u16) {
+    std::env::set_var("WEBKIT_INSPECTOR_HTTP_SERVER", format!("127.0.0.1:{}", port));
+}
+
+/// Runs a closure on the dedicated GTK thread, waiting at most the configured
+/// main-thread timeout (10 s by default).
 pub fn run_on_gtk_thread<F, R>(f: F) -> Result<R, WebViewError>
+where
+    F: FnOnce() -> Result<R, WebViewError> + Send + 'static,
+    R: Send + 'static,
+{
+    run_on_gtk_thread_timeout(f, crate::platform::main_thread_timeout_ms())
+}
+
+/// Runs a closure on the dedicated GTK thread, waiting at most `timeout_ms`
+/// (0 waits forever).
+///
+/// If the GTK thread has not picked the closure up by then, it is cancelled
+/// and never runs. A closure that already started is left to finish.
+pub fn run_on_gtk_thread_timeout<F, R>(f: F, timeout_ms: u64) -> Result<R, WebViewError>
 where
     F: FnOnce() -> Result<R, WebViewError> + Send + 'static,
     R: Send + 'static,
 {
     let runner = gtk_runner()?;
     let (result_tx, result_rx) = mpsc::sync_channel(1);
+    let token = CancellationToken::new();
+    let task_token = token.clone();
 
     runner
         .sender
         .send(Box::new(move || {
+            if !task_token.start() {
+                return;
+            }
             let result = f();
             let _ = result_tx.send(result);
         }))
         .map_err(|_| WebViewError::Internal("gtk runner stopped".to_string()))?;
 
-    result_rx
-        .recv()
-        .map_err(|_| WebViewError::Internal("gtk runner stopped".to_string()))?
+    crate::platform::wait_for_result(&result_rx, &token, timeout_ms, "gtk runner")
+}
+
+/// Initializes GTK on the current thread, explaining the usual causes of failure.
+fn init_gtk() -> Result<(), String> {
+    gtk::init().map_err(|err| {
+        let has_display = ["DISPLAY", "WAYLAND_DISPLAY"]
+            .iter()
+            .any(|name| std::env::var_os(name).is_some_and(|value| !value.is_empty()));
+        if has_display {
+            format!("gtk: {}", err)
+        } else {
+            format!("gtk: {} (neither DISPLAY nor WAYLAND_DISPLAY is set)", err)
+        }
+    })
 }
 
 /// Ensures GTK is initialized on the current thread.
+///
+/// GTK may already have been initialized by the host; it is then left as is, as
+/// long as it was initialized on this thread.
 pub fn ensure_gtk_initialized() -> Result<(), WebViewError> {
-    gtk::init().map_err(|err| WebViewError::GtkInit(err.to_string()))
+    if gtk::is_initialized_main_thread() {
+        return Ok(());
+    }
+    if gtk::is_initialized() {
+        return Err(WebViewError::PlatformInitFailed(
+            "gtk was initialized on another thread".to_string(),
+        ));
+    }
+    init_gtk().map_err(WebViewError::PlatformInitFailed)
+}
+
+/// Interval at which the parent X11 window is checked for size changes.
+///
+/// The parent window belongs to a foreign (AWT/Swing) toolkit, so GTK never
+/// emits `size-allocate` for it; its geometry has to be polled instead.
+const AUTO_RESIZE_POLL_MS: u64 = 32;
+
+thread_local! {
+    static AUTO_RESIZE_SOURCES: RefCell<HashMap<u64, glib::SourceId>> = RefCell::new(HashMap::new());
+}
+
+/// Returns the Xlib display used by GDK.
+fn x_display() -> Option<*mut x11::xlib::Display> {
+    let display = gdk::Display::default()?;
+    let x11_display = display.downcast::<X11Display>().ok()?;
+    unsafe {
+        let x11_display_ptr: *mut gdkx11::ffi::GdkX11Display = x11_display.to_glib_none().0;
+        let x_display = gdkx11::ffi::gdk_x11_display_get_xdisplay(x11_display_ptr);
+        if x_display.is_null() {
+            return None;
+        }
+        Some(x_display as *mut x11::xlib::Display)
+    }
+}
+
+/// Returns the size of an X11 window in physical pixels.
+fn x11_window_size(xid: u64) -> Option<(i32, i32)> {
+    let x_display = x_display()?;
+    unsafe {
+        let mut root: x11::xlib::Window = 0;
+        let (mut x, mut y): (c_int, c_int) = (0, 0);
+        let (mut width, mut height, mut border, mut depth): (c_uint, c_uint, c_uint, c_uint) = (0, 0, 0, 0);
+        let status = x11::xlib::XGetGeometry(
+            x_display,
+            xid as c_ulong,
+            &mut root,
+            &mut x,
+            &mut y,
+            &mut width,
+            &mut height,
+            &mut border,
+            &mut depth,
+        );
+        if status == 0 {
+            return None;
+        }
+        Some((width as i32, height as i32))
+    }
+}
+
+/// Returns the logical size of the parent window.
+pub fn parent_size(webview: &WebView, parent_handle: u64) -> Option<(i32, i32)> {
+    let scale = webview.webview().scale_factor().max(1);
+    x11_window_size(parent_handle).map(|(width, height)| (width / scale, height / scale))
+}
+
+/// Returns the parent of an X11 window, or `None` at the root window.
+fn x11_parent(x_display: *mut x11::xlib::Display, xid: c_ulong) -> Option<c_ulong> {
+    let mut root: x11::xlib::Window = 0;
+    let mut parent: x11::xlib::Window = 0;
+    let mut children: *mut x11::xlib::Window = std::ptr::null_mut();
+    let mut count: c_uint = 0;
+    unsafe {
+        let status = x11::xlib::XQueryTree(
+            x_display,
+            xid,
+            &mut root,
+            &mut parent,
+            &mut children,
+            &mut count,
+        );
+        if !children.is_null() {
+            x11::xlib::XFree(children.cast());
+        }
+        if status == 0 || parent == 0 || parent == root {
+            return None;
+        }
+    }
+    Some(parent)
+}
+
+/// Returns the X11 window embedding the WebView directly below `parent_handle`.
+///
+/// wry embeds the GTK window of the WebView in an X11 child window of the parent,
+/// so the window to move or restack is the topmost one below the parent.
+fn embedded_window(
+    webview: &WebView,
+    parent_handle: u64,
+) -> Result<(*mut x11::xlib::Display, c_ulong), WebViewError> {
+    let x_display = x_display()
+        .ok_or_else(|| WebViewError::Internal("X11 display unavailable".to_string()))?;
+    let gdk_window = webview
+        .webview()
+        .toplevel()
+        .and_then(|toplevel| toplevel.window())
+        .and_then(|window| window.downcast::<gdkx11::X11Window>().ok())
+        .ok_or_else(|| WebViewError::Internal("webview has no X11 window".to_string()))?;
+
+    let mut child = gdk_window.xid();
+    loop {
+        match x11_parent(x_display, child) {
+            Some(parent) if parent == parent_handle as c_ulong => return Ok((x_display, child)),
+            Some(parent) => child = parent,
+            None => {
+                return Err(WebViewError::Internal(
+                    "webview is not a child of its parent window".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+/// Moves the WebView into another parent X11 window, keeping its position.
+pub fn move_to_parent(
+    webview: &WebView,
+    old_parent_handle: u64,
+    new_parent_handle: u64,
+) -> Result<(), WebViewError> {
+    let (x_display, window) = embedded_window(webview, old_parent_handle)?;
+    unsafe {
+        let mut attributes: x11::xlib::XWindowAttributes = std::mem::zeroed();
+        x11::xlib::XGetWindowAttributes(x_display, window, &mut attributes);
+        x11::xlib::XReparentWindow(
+            x_display,
+            window,
+            new_parent_handle as c_ulong,
+            attributes.x,
+            attributes.y,
+        );
+        x11::xlib::XFlush(x_display);
+    }
+    Ok(())
+}
+
+/// Raises the WebView above, or lowers it below, the other children of its parent.
+///
+/// The embedding X11 window is restacked rather than the GDK window of the
+/// WebView, which only has siblings inside wry's own container.
+pub fn restack(webview: &WebView, parent_handle: u64, to_front: bool) -> Result<(), WebViewError> {
+    let (x_display, window) = embedded_window(webview, parent_handle)?;
+    unsafe {
+        if to_front {
+            x11::xlib::XRaiseWindow(x_display, window);
+        } else {
+            x11::xlib::XLowerWindow(x_display, window);
+        }
+        x11::xlib::XFlush(x_display);
+    }
+    Ok(())
+}
+
+/// Starts or stops following the size of the parent window.
+pub fn set_auto_resize(
+    webview: &WebView,
+    id: u64,
+    parent_handle: u64,
+    enabled: bool,
+) -> Result<(), WebViewError> {
+    AUTO_RESIZE_SOURCES.with(|sources| {
+        if let Some(source) = sources.borrow_mut().remove(&id) {
+            source.remove();
+        }
+    });
+    if !enabled {
+        return Ok(());
+    }
+
+    let scale = webview.webview().scale_factor().max(1);
+    let mut last_size = x11_window_size(parent_handle);
+    let source = glib::timeout_add_local(Duration::from_millis(AUTO_RESIZE_POLL_MS), move || {
+        let size = x11_window_size(parent_handle);
+        if size == last_size {
+            return glib::ControlFlow::Continue;
+        }
+        last_size = size;
+        let Some((width, height)) = size else {
+            return glib::ControlFlow::Continue;
+        };
+        if let Err(e) = crate::resize_to_parent(id, width / scale, height / scale) {
+            eprintln!("[wrywebview] auto_resize id={} stopped: {}", id, e);
+            // Returning `Break` destroys the source, so only forget its ID here.
+            AUTO_RESIZE_SOURCES.with(|sources| sources.borrow_mut().remove(&id));
+            return glib::ControlFlow::Break;
+        }
+        glib::ControlFlow::Continue
+    });
+    AUTO_RESIZE_SOURCES.with(|sources| sources.borrow_mut().insert(id, source));
+    Ok(())
+}
+
+/// Connects the WebKitGTK signals backing the handlers stored in `WebViewState`.
+pub fn install_handlers(webview: &WebView, state: &Arc<WebViewState>) -> Result<(), WebViewError> {
+    let webkit_webview = webview.webview();
+    install_permission_handler(&webkit_webview, Arc::clone(state));
+    install_download_progress(&webkit_webview, state);
+    install_js_dialog_handler(&webkit_webview, Arc::clone(state));
+    install_file_chooser_handler(&webkit_webview, Arc::clone(state));
+    install_auth_challenge_handler(&webkit_webview, Arc::clone(state));
+    install_insecure_content_observer(&webkit_webview, Arc::clone(state));
+    install_main_response_observer(&webkit_webview, Arc::clone(state));
+    install_tls_error_handler(&webkit_webview, Arc::clone(state));
+    install_error_page_observer(&webkit_webview, Arc::clone(state));
+    install_fullscreen_handler(&webkit_webview, Arc::clone(state));
+    install_context_menu_handler(&webkit_webview, Arc::clone(state));
+    install_load_progress_observer(&webkit_webview, Arc::clone(state));
+    install_focus_observer(&webkit_webview, Arc::clone(state));
+    Ok(())
+}
+
+fn permission_resources(request: &PermissionRequest) -> Vec<PermissionResource> {
+    if let Some(media) = request.downcast_ref::<UserMediaPermissionRequest>() {
+        let mut resources = Vec::new();
+        if media.is_for_video_device() {
+            resources.push(PermissionResource::Camera);
+        }
+        if media.is_for_audio_device() {
+            resources.push(PermissionResource::Microphone);
+        }
+        return resources;
+    }
+    if request.is::<GeolocationPermissionRequest>() {
+        return vec![PermissionResource::Geolocation];
+    }
+    if request.is::<NotificationPermissionRequest>() {
+        return vec![PermissionResource::Notifications];
+    }
+    Vec::new()
+}
+
+/// Forwards `permission-request` signals to the registered `PermissionHandler`.
+///
+/// A request covering several resources (camera and microphone) is allowed only
+/// if every resource is allowed.
+fn install_permission_handler(webkit_webview: &webkit2gtk::WebView, state: Arc<WebViewState>) {
+    webkit_webview.connect_permission_request(move |_, request| {
+        let Some(handler) = state.permission_handler.get() else {
+            return false;
+        };
+        let resources = permission_resources(request);
+        if resources.is_empty() {
+            return false;
+        }
+
+        let mut response = PermissionResponse::Allow;
+        for resource in resources {
+            match handler.on_request(state.id(), resource) {
+                PermissionResponse::Allow => {}
+                PermissionResponse::Deny => {
+                    response = PermissionResponse::Deny;
+                    break;
+                }
+                PermissionResponse::AskUser => response = PermissionResponse::AskUser,
+            }
+        }
+
+        match response {
+            PermissionResponse::Allow => request.allow(),
+            PermissionResponse::Deny => request.deny(),
+            // Fall back to WebKit's default handling.
+            PermissionResponse::AskUser => return false,
+        }
+        true
+    });
+}
+
+/// Reports the progress of downloads started by this WebView to the `DownloadHandler`.
+///
+/// The web context can be shared between WebViews, so downloads are filtered by
+/// their originating WebView. Only weak references are captured so the
+/// connection does not keep a destroyed WebView alive.
+fn install_download_progress(webkit_webview: &webkit2gtk::WebView, state: &Arc<WebViewState>) {
+    let Some(context) = webkit_webview.context() else {
+        return;
+    };
+    let own_webview = webkit_webview.downgrade();
+    let state = Arc::downgrade(state);
+    context.connect_download_started(move |_, download| {
+        let Some(own_webview) = own_webview.upgrade() else {
+            return;
+        };
+        if download.web_view().as_ref() != Some(&own_webview) {
+            return;
+        }
+        let state = state.clone();
+        download.connect_received_data(move |download, _| {
+            let Some(state) = state.upgrade() else {
+                return;
+            };
+            let Some(handler) = state.download_handler.get() else {
+                return;
+            };
+            let total = download
+                .response()
+                .map(|response| response.content_length())
+                .filter(|length| *length > 0);
+            handler.on_progress(state.id(), download.received_data_length(), total);
+        });
+    });
+}
+
+/// Forwards `script-dialog` signals to the registered `JsDialogHandler`.
+fn install_js_dialog_handler(webkit_webview: &webkit2gtk::WebView, state: Arc<WebViewState>) {
+    webkit_webview.connect_script_dialog(move |_, dialog| {
+        let Some(handler) = state.js_dialog_handler.get() else {
+            return false;
+        };
+        let mut dialog = dialog.clone();
+        let message = dialog.message().map(|m| m.to_string()).unwrap_or_default();
+        match dialog.dialog_type() {
+            ScriptDialogType::Alert => handler.on_alert(state.id(), message),
+            ScriptDialogType::Confirm | ScriptDialogType::BeforeUnloadConfirm => {
+                let confirmed = handler.on_confirm(state.id(), message);
+                dialog.confirm_set_confirmed(confirmed);
+            }
+            ScriptDialogType::Prompt => {
+                let default_value = dialog
+                    .prompt_get_default_text()
+                    .map(|text| text.to_string())
+                    .unwrap_or_default();
+                // Leaving the text unset makes `prompt()` return null.
+                if let Some(text) = handler.on_prompt(state.id(), message, default_value) {
+                    dialog.prompt_set_text(&text);
+                }
+            }
+            _ => return false,
+        }
+        true
+    });
+}
+
+/// Forwards `run-file-chooser` signals to the registered `FileChooserHandler`.
+fn install_file_chooser_handler(webkit_webview: &webkit2gtk::WebView, state: Arc<WebViewState>) {
+    webkit_webview.connect_run_file_chooser(move |_, request| {
+        let accept = request
+            .mime_types()
+            .iter()
+            .map(|mime_type| mime_type.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let Some(paths) = crate::choose_files(&state, accept, request.selects_multiple()) else {
+            return false;
+        };
+        if paths.is_empty() {
+            request.cancel();
+        } else {
+            let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+            request.select_files(&paths);
+        }
+        true
+    });
+}
+
+/// Forwards `authenticate` signals to the registered `AuthChallengeHandler`.
+fn install_auth_challenge_handler(webkit_webview: &webkit2gtk::WebView, state: Arc<WebViewState>) {
+    webkit_webview.connect_authenticate(move |_, request| {
+        let host = request.host().map(|host| host.to_string()).unwrap_or_default();
+        let realm = request.realm().map(|realm| realm.to_string()).unwrap_or_default();
+        match crate::answer_auth_challenge(&state, host, realm, request.is_for_proxy()) {
+            AuthAnswer::Default => return false,
+            AuthAnswer::Cancel => request.cancel(),
+            AuthAnswer::Respond(credentials) => {
+                let credential = Credential::new(
+                    &credentials.username,
+                    &credentials.password,
+                    CredentialPersistence::ForSession,
+                );
+                request.authenticate(Some(&credential));
+            }
+        }
+        true
+    });
+}
+
+/// Reports mixed content as SSL warnings.
+fn install_insecure_content_observer(
+    webkit_webview: &webkit2gtk::WebView,
+    state: Arc<WebViewState>,
+) {
+    webkit_webview.connect_insecure_content_detected(move |webkit_webview, event| {
+        let warning_type = match event {
+            InsecureContentEvent::RunInsecureContent => SslWarningType::MixedContentRan,
+            InsecureContentEvent::DisplayedInsecureContent => SslWarningType::MixedContentDisplayed,
+            _ => return,
+        };
+        let url = webkit_webview.uri().map(|uri| uri.to_string()).unwrap_or_default();
+        crate::report_ssl_warning(&state, url, warning_type);
+    });
+}
+
+/// Caches the estimated load progress reported by WebKitGTK.
+fn install_load_progress_observer(webkit_webview: &webkit2gtk::WebView, state: Arc<WebViewState>) {
+    webkit_webview.connect_estimated_load_progress_notify(move |webkit_webview| {
+        state.set_load_progress(webkit_webview.estimated_load_progress());
+    });
+}
+
+/// Keeps `WebViewState::has_focus` in sync with the GTK focus of the WebView.
+fn install_focus_observer(webkit_webview: &webkit2gtk::WebView, state: Arc<WebViewState>) {
+    let state_for_out = Arc::clone(&state);
+    webkit_webview.connect_focus_in_event(move |_, _| {
+        state.has_focus.store(true, Ordering::Release);
+        glib::Propagation::Proceed
+    });
+    webkit_webview.connect_focus_out_event(move |_, _| {
+        state_for_out.has_focus.store(false, Ordering::Release);
+        glib::Propagation::Proceed
+    });
+}
+
+/// Caches the response of the main resource once the load is committed.
+fn install_main_response_observer(webkit_webview: &webkit2gtk::WebView, state: Arc<WebViewState>) {
+    webkit_webview.connect_load_changed(move |webkit_webview, event| {
+        if event != LoadEvent::Committed {
+            return;
+        }
+        let Some(response) = webkit_webview.main_resource().and_then(|resource| resource.response())
+        else {
+            return;
+        };
+        let mut headers = Vec::new();
+        if let Some(http_headers) = response.http_headers() {
+            http_headers.foreach(|name, value| headers.push((name.to_string(), value.to_string())));
+        }
+        crate::record_main_response(&state, response.status_code() as u16, headers);
+    });
+}
+
+/// Forwards `load-failed-with-tls-errors` signals to the registered `TlsErrorHandler`.
+///
+/// An allowed certificate is trusted for its host in the web context, then the
+/// failed URI is loaded again.
+fn install_tls_error_handler(webkit_webview: &webkit2gtk::WebView, state: Arc<WebViewState>) {
+    webkit_webview.connect_load_failed_with_tls_errors(
+        move |webkit_webview, uri, certificate, errors| {
+            let description = format!("{:?}", errors);
+            if crate::decide_tls_error(&state, uri.to_string(), description) == TlsDecision::Deny {
+                return false;
+            }
+            let host = uri
+                .parse::<wry::http::Uri>()
+                .ok()
+                .and_then(|uri| uri.host().map(str::to_string));
+            let (Some(context), Some(host)) = (webkit_webview.context(), host) else {
+                return false;
+            };
+            context.allow_tls_certificate_for_host(certificate, &host);
+            webkit_webview.load_uri(uri);
+            true
+        },
+    );
+}
+
+/// Reports the loads that WebKitGTK replaces with its error page.
+fn install_error_page_observer(webkit_webview: &webkit2gtk::WebView, state: Arc<WebViewState>) {
+    webkit_webview.connect_load_failed(move |_, _, uri, error| {
+        // Neither shows an error page: the load was stopped or replaced by another one.
+        if error.matches(NetworkError::Cancelled)
+            || error.matches(PolicyError::FrameLoadInterruptedByPolicyChange)
+        {
+            return false;
+        }
+        let raw: *const glib::ffi::GError = error.to_glib_none().0;
+        let code = unsafe { (*raw).code };
+        crate::report_error_page(&state, uri.to_string(), code);
+        false
+    });
+}
+
+/// Forwards `enter-fullscreen` and `leave-fullscreen` to the registered `FullscreenHandler`.
+///
+/// The signals are stopped while a handler is set: by default GTK makes the
+/// toplevel of the WebView fullscreen, which is not the host window.
+fn install_fullscreen_handler(webkit_webview: &webkit2gtk::WebView, state: Arc<WebViewState>) {
+    let enter_state = Arc::clone(&state);
+    webkit_webview.connect_enter_fullscreen(move |_| {
+        let Some(handler) = enter_state.fullscreen_handler.get() else {
+            return false;
+        };
+        handler.on_enter_fullscreen(enter_state.id());
+        true
+    });
+    webkit_webview.connect_leave_fullscreen(move |_| {
+        let Some(handler) = state.fullscreen_handler.get() else {
+            return false;
+        };
+        handler.on_exit_fullscreen(state.id());
+        true
+    });
+}
+
+/// Forwards `context-menu` signals to the registered `ContextMenuHandler`.
+///
+/// WebKitGTK does not expose the labels of its items, so default items are
+/// described by their stock action (e.g. `CopyLinkToClipboard`).
+fn install_context_menu_handler(webkit_webview: &webkit2gtk::WebView, state: Arc<WebViewState>) {
+    webkit_webview.connect_context_menu(move |_, menu, event, _| {
+        if !state.context_menu_handler.is_set() {
+            return false;
+        }
+        let (x, y) = event.coords().unwrap_or_default();
+        let items = menu
+            .items()
+            .iter()
+            .filter(|item| !item.is_separator())
+            .map(|item| {
+                let action = format!("{:?}", item.stock_action());
+                ContextMenuItem {
+                    title: action.clone(),
+                    action_id: action,
+                    enabled: item.gaction().map(|action| action.is_enabled()).unwrap_or(true),
+                }
+            })
+            .collect();
+        match crate::decide_context_menu(&state, x as i32, y as i32, items) {
+            ContextMenuDecision::ShowDefault => false,
+            ContextMenuDecision::Suppress => true,
+            ContextMenuDecision::ShowCustom(items) => {
+                menu.remove_all();
+                for (index, item) in items.into_iter().enumerate() {
+                    // Action names are restricted, so the action ID is kept by the closure.
+                    let name = format!("wrywebview-item-{}", index);
+                    let action = gio::SimpleAction::new(&name, None);
+                    action.set_enabled(item.enabled);
+                    let state = Arc::clone(&state);
+                    let action_id = item.action_id;
+                    action.connect_activate(move |_, _| {
+                        crate::context_menu_item_selected(&state, action_id.clone());
+                    });
+                    menu.append(&webkit2gtk::ContextMenuItem::from_gaction(
+                        &action,
+                        &item.title,
+                        None,
+                    ));
+                }
+                false
+            }
+        }
+    });
+}
+
+/// Returns the warnings of the loaded page that WebKitGTK does not signal.
+///
+/// Mixed content is reported as it happens by `insecure-content-detected`.
+pub fn page_security_warnings(webview: &WebView) -> Vec<SslWarningType> {
+    match webview.webview().tls_info() {
+        Some((_, errors)) if !errors.is_empty() => vec![SslWarningType::InvalidCertificate],
+        _ => Vec::new(),
+    }
+}
+
+/// Reads the TLS certificate chain of the page currently shown.
+pub fn certificate_chain(
+    webview: &WebView,
+    _url: &str,
+    on_chain: CertificateChainCompletion,
+) -> Result<(), WebViewError> {
+    let Some((certificate, _errors)) = webview.webview().tls_info() else {
+        return Err(WebViewError::NotSecure);
+    };
+
+    let mut chain = Vec::new();
+    let mut next = Some(certificate);
+    while let Some(certificate) = next {
+        if let Some(der) = certificate.certificate() {
+            chain.push(der.to_vec());
+        }
+        next = certificate.issuer();
+    }
+    on_chain(chain);
+    Ok(())
+}
+
+/// Toggles the HTML5 (Web SQL) database setting of WebKitGTK.
+pub fn set_web_sql_enabled(webview: &WebView, enabled: bool) -> Result<(), WebViewError> {
+    let settings = WebViewExt::settings(&webview.webview())
+        .ok_or_else(|| WebViewError::Internal("webkit settings unavailable".to_string()))?;
+    #[allow(deprecated)]
+    settings.set_enable_html5_database(enabled);
+    Ok(())
+}
+
+pub fn set_javascript_enabled(webview: &WebView, enabled: bool) -> Result<(), WebViewError> {
+    let settings = WebViewExt::settings(&webview.webview())
+        .ok_or_else(|| WebViewError::Internal("webkit settings unavailable".to_string()))?;
+    settings.set_enable_javascript(enabled);
+    Ok(())
+}
+
+/// Makes WebKitGTK render the WebView without the GPU.
+pub fn disable_hardware_acceleration(webview: &WebView) -> Result<(), WebViewError> {
+    let settings = WebViewExt::settings(&webview.webview())
+        .ok_or_else(|| WebViewError::Internal("webkit settings unavailable".to_string()))?;
+    settings.set_hardware_acceleration_policy(HardwareAccelerationPolicy::Never);
+    Ok(())
+}
+
+/// Gives the GTK window hosting a transparent WebView an RGBA visual, so the page
+/// background wry cleared lets what lies below show through.
+///
+/// This needs a compositing window manager. Without an RGBA visual the WebView
+/// is left opaque.
+pub fn set_transparent(webview: &WebView) -> Result<(), WebViewError> {
+    let toplevel = webview
+        .webview()
+        .toplevel()
+        .ok_or_else(|| WebViewError::Internal("webview has no toplevel window".to_string()))?;
+    let Some(visual) = WidgetExt::screen(&toplevel).and_then(|screen| screen.rgba_visual()) else {
+        eprintln!("[wrywebview] set_transparent: no RGBA visual, the webview stays opaque");
+        return Ok(());
+    };
+    toplevel.set_visual(Some(&visual));
+    toplevel.set_app_paintable(true);
+    Ok(())
+}
+
+pub fn set_allow_file_access(webview: &WebView, enabled: bool) -> Result<(), WebViewError> {
+    let settings = WebViewExt::settings(&webview.webview())
+        .ok_or_else(|| WebViewError::Internal("webkit settings unavailable".to_string()))?;
+    settings.set_allow_file_access_from_file_urls(enabled);
+    settings.set_allow_universal_access_from_file_urls(enabled);
+    Ok(())
+}
+
+/// WebKitGTK always lets pinches zoom the page; `zoom-text-only` only changes
+/// what the page zoom scales, so pinch zoom cannot be turned off.
+pub fn set_pinch_zoom_enabled(_webview: &WebView, enabled: bool) -> Result<(), WebViewError> {
+    if enabled {
+        Ok(())
+    } else {
+        Err(WebViewError::UnsupportedPlatform)
+    }
+}
+
+/// WebKitGTK only distinguishes whether a user gesture is required; muted media
+/// may still play without one.
+pub fn set_autoplay_policy(webview: &WebView, policy: AutoplayPolicy) -> Result<(), WebViewError> {
+    let settings = WebViewExt::settings(&webview.webview())
+        .ok_or_else(|| WebViewError::Internal("webkit settings unavailable".to_string()))?;
+    settings.set_media_playback_requires_user_gesture(policy != AutoplayPolicy::Allow);
+    Ok(())
+}
+
+/// Lets clicks through the WebView with an empty input shape on its GTK window.
+///
+/// X11 then delivers pointer events to the window below, and as wry's container
+/// does not select them they propagate to the parent window.
+pub fn set_pointer_passthrough(webview: &WebView, passthrough: bool) -> Result<(), WebViewError> {
+    let toplevel = webview
+        .webview()
+        .toplevel()
+        .ok_or_else(|| WebViewError::Internal("webview has no GTK window".to_string()))?;
+    if passthrough {
+        toplevel.input_shape_combine_region(Some(&gtk::cairo::Region::create()));
+    } else {
+        toplevel.input_shape_combine_region(None);
+    }
+    Ok(())
+}
+
+/// Gives the keyboard focus to the WebView.
+///
+/// The GTK widget is embedded in a foreign (AWT/Swing) window hierarchy, so the
+/// X11 input focus is set directly before the GTK grab.
+pub fn focus(webview: &WebView) -> Result<(), WebViewError> {
+    let widget = webview.webview();
+    widget.set_can_focus(true);
+    if !widget.is_realized() {
+        widget.realize();
+    }
+    let xid = widget
+        .window()
+        .and_then(|window| window.downcast::<gdkx11::X11Window>().ok())
+        .map(|window| window.xid());
+    if let (Some(xid), Some(x_display)) = (xid, x_display()) {
+        unsafe {
+            x11::xlib::XSetInputFocus(
+                x_display,
+                xid,
+                x11::xlib::RevertToParent,
+                x11::xlib::CurrentTime,
+            );
+        }
+        eprintln!("[wrywebview] XSetInputFocus xid=0x{:x}", xid);
+    }
+    widget.grab_focus();
+    Ok(())
+}
+
+/// Gives the keyboard focus back to the parent window.
+pub fn blur(webview: &WebView, parent_handle: u64) -> Result<(), WebViewError> {
+    if let Some(window) = webview
+        .webview()
+        .toplevel()
+        .and_then(|toplevel| toplevel.downcast::<gtk::Window>().ok())
+    {
+        window.set_focus(None::<&gtk::Widget>);
+    }
+    if let Some(x_display) = x_display() {
+        unsafe {
+            x11::xlib::XSetInputFocus(
+                x_display,
+                parent_handle as c_ulong,
+                x11::xlib::RevertToParent,
+                x11::xlib::CurrentTime,
+            );
+            x11::xlib::XFlush(x_display);
+        }
+    }
+    Ok(())
+}
+
+/// Goes back in the native session history; `false` if there is no previous page.
+pub fn go_back(webview: &WebView) -> Result<bool, WebViewError> {
+    let view = webview.webview();
+    if !view.can_go_back() {
+        return Ok(false);
+    }
+    view.go_back();
+    Ok(true)
+}
+
+/// Goes forward in the native session history; `false` if there is no next page.
+pub fn go_forward(webview: &WebView) -> Result<bool, WebViewError> {
+    let view = webview.webview();
+    if !view.can_go_forward() {
+        return Ok(false);
+    }
+    view.go_forward();
+    Ok(true)
+}
+
+/// Loads `url` with a custom method and headers.
+///
+/// A `WebKitURIRequest` cannot carry a body, so a request with one returns
+/// `UnsupportedPlatform`.
+pub fn load_request(
+    webview: &WebView,
+    method: &str,
+    url: &str,
+    headers: &[(String, String)],
+    body: Option<&[u8]>,
+) -> Result<(), WebViewError> {
+    if body.is_some() {
+        return Err(WebViewError::UnsupportedPlatform);
+    }
+    let request = URIRequest::new(url);
+    request.set_http_method(Some(method));
+    if let Some(request_headers) = request.http_headers() {
+        for (name, value) in headers {
+            request_headers.append(name, value);
+        }
+    }
+    webview.webview().load_request(&request);
+    Ok(())
+}
+
+pub fn reload_bypass_cache(webview: &WebView) -> Result<(), WebViewError> {
+    webview.webview().reload_bypass_cache();
+    Ok(())
+}
+
+pub fn set_muted(webview: &WebView, muted: bool) -> Result<(), WebViewError> {
+    webview.webview().set_is_muted(muted);
+    Ok(())
+}
+
+fn website_data_manager(webview: &WebView) -> Result<WebsiteDataManager, WebViewError> {
+    webview
+        .webview()
+        .context()
+        .and_then(|context| context.website_data_manager())
+        .ok_or_else(|| WebViewError::Internal("webview has no website data manager".to_string()))
+}
+
+/// Sets the proxy on the web context of the WebView, which applies to every
+/// WebView sharing that context.
+pub fn set_proxy(webview: &WebView, proxy: &ProxyConfig) -> Result<(), WebViewError> {
+    let ignore_hosts: Vec<&str> = proxy.bypass_list.iter().map(String::as_str).collect();
+    let mut settings = NetworkProxySettings::new(Some(&proxy.url()), &ignore_hosts);
+    website_data_manager(webview)?
+        .set_network_proxy_settings(NetworkProxyMode::Custom, Some(&mut settings));
+    Ok(())
+}
+
+/// Restores the system proxy settings on the web context of the WebView.
+pub fn clear_proxy(webview: &WebView) -> Result<(), WebViewError> {
+    website_data_manager(webview)?.set_network_proxy_settings(NetworkProxyMode::Default, None);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn gtk_thread_timeout_fires_near_the_limit() {
+        if let Err(e) = gtk_runner() {
+            eprintln!("skipped: {}", e);
+            return;
+        }
+
+        let start = Instant::now();
+        let result = run_on_gtk_thread_timeout(
+            || {
+                std::thread::sleep(Duration::from_millis(500));
+                Ok(())
+            },
+            100,
+        );
+        let elapsed = start.elapsed();
+
+        assert!(matches!(result, Err(WebViewError::MainThreadTimeout(100))));
+        assert!(elapsed >= Duration::from_millis(100), "timed out after {:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(300), "timed out after {:?}", elapsed);
+    }
 }
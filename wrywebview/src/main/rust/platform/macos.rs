@@ -1,24 +1,89 @@
 //! macOS-specific AppKit handling.
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi::c_void;
 use std::ffi::CStr;
 use std::ptr::NonNull;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::{Arc, OnceLock, Weak};
 
-use dispatch2::run_on_main;
+use block2::Block;
+use objc2::encode::{Encoding, RefEncode};
 use objc2::msg_send;
-use objc2::runtime::{AnyClass, AnyObject};
+use objc2::rc::Retained;
+use objc2::runtime::{AnyClass, AnyObject, Bool, Imp, Sel};
+use objc2::sel;
 pub use objc2::MainThreadMarker;
 pub use dispatch2::DispatchQueue;
+use objc2_foundation::{NSArray, NSPoint, NSRect, NSString, NSURL};
+use wry::{ProxyEndpoint, WebView, WebViewBuilder, WebViewBuilderExtDarwin, WebViewExtMacOS};
 
 use crate::error::WebViewError;
+use crate::platform::{CancellationToken, CertificateChainCompletion};
+use crate::state::WebViewState;
+use crate::{
+    AuthAnswer, AutoplayPolicy, ColorScheme, ProxyConfig, ProxyType, SslWarningType, TlsDecision,
+};
 
 /// Runs a closure on the main thread using GCD.
+///
+/// Called on the main thread, the closure runs inline: waiting on the main
+/// queue from the main thread would wait on itself. Call sites therefore never
+/// check `MainThreadMarker` themselves.
+///
+/// Called from another thread, it fails with `MainThreadTimeout` if the main
+/// thread does not start the closure in time; the closure is then skipped.
 pub fn run_on_main_thread<F, R>(f: F) -> Result<R, WebViewError>
 where
     F: FnOnce() -> Result<R, WebViewError> + Send + 'static,
     R: Send + 'static,
 {
-    run_on_main(|_| f())
+    run_on_main_thread_timeout(f, crate::platform::main_thread_timeout_ms())
+}
+
+/// Runs a closure on the main thread using GCD, waiting at most `timeout_ms`
+/// (0 waits forever).
+pub fn run_on_main_thread_timeout<F, R>(f: F, timeout_ms: u64) -> Result<R, WebViewError>
+where
+    F: FnOnce() -> Result<R, WebViewError> + Send + 'static,
+    R: Send + 'static,
+{
+    if MainThreadMarker::new().is_some() {
+        return f();
+    }
+    DispatchQueue::main().exec_sync_with_result(f, timeout_ms)
+}
+
+/// Dispatch that blocks the caller and hands back the closure's result.
+pub trait DispatchQueueExt {
+    /// Runs `f` on this queue and waits at most `timeout_ms` (0 waits forever)
+    /// for its result.
+    ///
+    /// Must not be called on the queue's own thread, which would wait on itself.
+    fn exec_sync_with_result<F, R>(&self, f: F, timeout_ms: u64) -> Result<R, WebViewError>
+    where
+        F: FnOnce() -> Result<R, WebViewError> + Send + 'static,
+        R: Send + 'static;
+}
+
+impl DispatchQueueExt for DispatchQueue {
+    fn exec_sync_with_result<F, R>(&self, f: F, timeout_ms: u64) -> Result<R, WebViewError>
+    where
+        F: FnOnce() -> Result<R, WebViewError> + Send + 'static,
+        R: Send + 'static,
+    {
+        let (result_tx, result_rx) = mpsc::sync_channel(1);
+        let token = CancellationToken::new();
+        let task_token = token.clone();
+        self.exec_async(move || {
+            if task_token.start() {
+                let _ = result_tx.send(f());
+            }
+        });
+        crate::platform::wait_for_result(&result_rx, &token, timeout_ms, "dispatch queue")
+    }
 }
 
 /// Converts a raw handle to an NSView pointer.
@@ -26,20 +91,21 @@ where
 /// Handles both NSWindow (extracts contentView) and NSView objects.
 pub fn appkit_ns_view_from_handle(parent_handle: u64) -> Result<NonNull<c_void>, WebViewError> {
     let ptr = NonNull::new(parent_handle as *mut c_void)
-        .ok_or(WebViewError::InvalidWindowHandle)?;
+        .ok_or(WebViewError::InvalidWindowHandle(parent_handle))?;
     let obj = unsafe { &*(ptr.as_ptr() as *mut AnyObject) };
     let class_name = obj.class().name().to_string_lossy();
     eprintln!("[wrywebview] appkit handle class={}", class_name);
 
     let nswindow_name = unsafe { CStr::from_bytes_with_nul_unchecked(b"NSWindow\0") };
     let nsview_name = unsafe { CStr::from_bytes_with_nul_unchecked(b"NSView\0") };
-    let nswindow_cls = AnyClass::get(nswindow_name).ok_or(WebViewError::InvalidWindowHandle)?;
-    let nsview_cls = AnyClass::get(nsview_name).ok_or(WebViewError::InvalidWindowHandle)?;
+    let invalid = || WebViewError::InvalidWindowHandle(parent_handle);
+    let nswindow_cls = AnyClass::get(nswindow_name).ok_or_else(invalid)?;
+    let nsview_cls = AnyClass::get(nsview_name).ok_or_else(invalid)?;
 
     unsafe {
         if msg_send![obj, isKindOfClass: nswindow_cls] {
             let view: *mut AnyObject = msg_send![obj, contentView];
-            let view = NonNull::new(view).ok_or(WebViewError::InvalidWindowHandle)?;
+            let view = NonNull::new(view).ok_or(WebViewError::InvalidWindowHandle(parent_handle))?;
             eprintln!(
                 "[wrywebview] appkit handle is NSWindow, contentView=0x{:x}",
                 view.as_ptr() as usize
@@ -51,5 +117,1253 @@ pub fn appkit_ns_view_from_handle(parent_handle: u64) -> Result<NonNull<c_void>,
         }
     }
 
-    Err(WebViewError::InvalidWindowHandle)
+    Err(WebViewError::InvalidWindowHandle(parent_handle))
+}
+
+/// Installs native hooks for the handlers stored in `WebViewState`.
+///
+/// wry owns the `WKUIDelegate` and `WKNavigationDelegate` of the WebView, so the
+/// delegate-based handlers (permissions, ...) keep the WebKit defaults on macOS,
+/// except for the UI delegate methods patched below.
+pub fn install_handlers(webview: &WebView, state: &Arc<WebViewState>) -> Result<(), WebViewError> {
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    DELEGATE_STATES.with(|states| {
+        states
+            .borrow_mut()
+            .insert(view as *const AnyObject as usize, Arc::downgrade(state))
+    });
+
+    let (ui_delegate, navigation_delegate): (*mut AnyObject, *mut AnyObject) = unsafe {
+        (msg_send![view, UIDelegate], msg_send![view, navigationDelegate])
+    };
+    install_context_menu_methods(view.class() as *const AnyClass as *mut AnyClass);
+    install_hit_test_method(view.class() as *const AnyClass as *mut AnyClass);
+    install_first_responder_methods(view.class() as *const AnyClass as *mut AnyClass);
+    let ui_class = delegate_class(ui_delegate, "UI")?;
+    install_js_dialog_methods(ui_class);
+    install_open_panel_method(ui_class);
+    install_fullscreen_methods(ui_class);
+    let navigation_class = delegate_class(navigation_delegate, "navigation")?;
+    install_auth_challenge_method(navigation_class);
+    install_navigation_response_method(navigation_class);
+    install_provisional_failure_method(navigation_class);
+    Ok(())
+}
+
+fn delegate_class(delegate: *mut AnyObject, kind: &str) -> Result<*mut AnyClass, WebViewError> {
+    let delegate = unsafe { delegate.as_ref() }
+        .ok_or_else(|| WebViewError::Internal(format!("WKWebView has no {} delegate", kind)))?;
+    Ok(delegate.class() as *const AnyClass as *mut AnyClass)
+}
+
+thread_local! {
+    /// WebView states by `WKWebView` pointer, for the delegate methods added below.
+    static DELEGATE_STATES: RefCell<HashMap<usize, Weak<WebViewState>>> = RefCell::new(HashMap::new());
+}
+
+fn delegate_state(wk_webview: *mut AnyObject) -> Option<Arc<WebViewState>> {
+    DELEGATE_STATES.with(|states| {
+        let mut states = states.borrow_mut();
+        let state = states.get(&(wk_webview as usize))?.upgrade();
+        if state.is_none() {
+            states.remove(&(wk_webview as usize));
+        }
+        state
+    })
+}
+
+fn ns_string_to_string(string: *mut NSString) -> String {
+    unsafe { string.as_ref() }
+        .map(|string| string.to_string())
+        .unwrap_or_default()
+}
+
+extern "C-unwind" fn run_alert_panel(
+    _this: *mut AnyObject,
+    _cmd: Sel,
+    wk_webview: *mut AnyObject,
+    message: *mut NSString,
+    _frame: *mut AnyObject,
+    completion: &Block<dyn Fn()>,
+) {
+    if let Some(state) = delegate_state(wk_webview) {
+        if let Some(handler) = state.js_dialog_handler.get() {
+            handler.on_alert(state.id(), ns_string_to_string(message));
+        }
+    }
+    completion.call(());
+}
+
+extern "C-unwind" fn run_confirm_panel(
+    _this: *mut AnyObject,
+    _cmd: Sel,
+    wk_webview: *mut AnyObject,
+    message: *mut NSString,
+    _frame: *mut AnyObject,
+    completion: &Block<dyn Fn(Bool)>,
+) {
+    let confirmed = delegate_state(wk_webview)
+        .and_then(|state| {
+            let handler = state.js_dialog_handler.get()?;
+            Some(handler.on_confirm(state.id(), ns_string_to_string(message)))
+        })
+        .unwrap_or(false);
+    completion.call((Bool::new(confirmed),));
+}
+
+extern "C-unwind" fn run_text_input_panel(
+    _this: *mut AnyObject,
+    _cmd: Sel,
+    wk_webview: *mut AnyObject,
+    prompt: *mut NSString,
+    default_text: *mut NSString,
+    _frame: *mut AnyObject,
+    completion: &Block<dyn Fn(*mut NSString)>,
+) {
+    let text = delegate_state(wk_webview).and_then(|state| {
+        let handler = state.js_dialog_handler.get()?;
+        handler.on_prompt(
+            state.id(),
+            ns_string_to_string(prompt),
+            ns_string_to_string(default_text),
+        )
+    });
+    match text {
+        Some(text) => {
+            let text = NSString::from_str(&text);
+            completion.call((Retained::as_ptr(&text) as *mut NSString,));
+        }
+        None => completion.call((std::ptr::null_mut(),)),
+    }
+}
+
+extern "C-unwind" fn did_enter_fullscreen(
+    _this: *mut AnyObject,
+    _cmd: Sel,
+    wk_webview: *mut AnyObject,
+) {
+    if let Some(state) = delegate_state(wk_webview) {
+        if let Some(handler) = state.fullscreen_handler.get() {
+            handler.on_enter_fullscreen(state.id());
+        }
+    }
+}
+
+extern "C-unwind" fn did_exit_fullscreen(
+    _this: *mut AnyObject,
+    _cmd: Sel,
+    wk_webview: *mut AnyObject,
+) {
+    if let Some(state) = delegate_state(wk_webview) {
+        if let Some(handler) = state.fullscreen_handler.get() {
+            handler.on_exit_fullscreen(state.id());
+        }
+    }
+}
+
+/// Adds the private fullscreen notifications of `WKUIDelegatePrivate` to the
+/// class of wry's `WKUIDelegate`.
+fn install_fullscreen_methods(class: *mut AnyClass) {
+    unsafe {
+        let methods: [(Sel, Imp); 2] = [
+            (
+                sel!(_webViewDidEnterFullscreen:),
+                std::mem::transmute::<*const (), Imp>(did_enter_fullscreen as *const ()),
+            ),
+            (
+                sel!(_webViewDidExitFullscreen:),
+                std::mem::transmute::<*const (), Imp>(did_exit_fullscreen as *const ()),
+            ),
+        ];
+        for (sel, imp) in methods {
+            objc2::ffi::class_addMethod(class, sel, imp, c"v@:@".as_ptr());
+        }
+    }
+}
+
+/// Adds the JavaScript dialog methods to the class of wry's `WKUIDelegate`.
+///
+/// Without them WebKit silently dismisses the dialogs, so the methods complete
+/// with the same results when no `JsDialogHandler` is set. `class_addMethod`
+/// keeps any existing implementation, so this is a no-op for the second WebView.
+fn install_js_dialog_methods(class: *mut AnyClass) {
+    unsafe {
+        let methods: [(Sel, Imp, &CStr); 3] = [
+            (
+                sel!(webView:runJavaScriptAlertPanelWithMessage:initiatedByFrame:completionHandler:),
+                std::mem::transmute::<*const (), Imp>(run_alert_panel as *const ()),
+                c"v@:@@@@?",
+            ),
+            (
+                sel!(webView:runJavaScriptConfirmPanelWithMessage:initiatedByFrame:completionHandler:),
+                std::mem::transmute::<*const (), Imp>(run_confirm_panel as *const ()),
+                c"v@:@@@@?",
+            ),
+            (
+                sel!(webView:runJavaScriptTextInputPanelWithPrompt:defaultText:initiatedByFrame:completionHandler:),
+                std::mem::transmute::<*const (), Imp>(run_text_input_panel as *const ()),
+                c"v@:@@@@@?",
+            ),
+        ];
+        for (selector, imp, types) in methods {
+            objc2::ffi::class_addMethod(class, selector, imp, types.as_ptr());
+        }
+    }
+}
+
+type OpenPanelFn = unsafe extern "C-unwind" fn(
+    *mut AnyObject,
+    Sel,
+    *mut AnyObject,
+    *mut AnyObject,
+    *mut AnyObject,
+    &Block<dyn Fn(*mut NSArray<NSURL>)>,
+);
+
+/// wry's `runOpenPanelWithParameters` implementation, used when no handler is set.
+static WRY_OPEN_PANEL: OnceLock<Option<Imp>> = OnceLock::new();
+
+extern "C-unwind" fn run_open_panel(
+    this: *mut AnyObject,
+    cmd: Sel,
+    wk_webview: *mut AnyObject,
+    parameters: *mut AnyObject,
+    frame: *mut AnyObject,
+    completion: &Block<dyn Fn(*mut NSArray<NSURL>)>,
+) {
+    let multiple = unsafe { parameters.as_ref() }
+        .map(|parameters| {
+            let multiple: Bool = unsafe { msg_send![parameters, allowsMultipleSelection] };
+            multiple.as_bool()
+        })
+        .unwrap_or(false);
+    // `WKOpenPanelParameters` does not expose the `accept` attribute.
+    let paths = delegate_state(wk_webview)
+        .and_then(|state| crate::choose_files(&state, String::new(), multiple));
+
+    match paths {
+        Some(paths) if !paths.is_empty() => {
+            let urls: Vec<Retained<NSURL>> = paths
+                .iter()
+                .map(|path| NSURL::fileURLWithPath(&NSString::from_str(path)))
+                .collect();
+            let urls = NSArray::from_retained_slice(&urls);
+            completion.call((Retained::as_ptr(&urls) as *mut NSArray<NSURL>,));
+        }
+        Some(_) => completion.call((std::ptr::null_mut(),)),
+        None => match WRY_OPEN_PANEL.get().copied().flatten() {
+            Some(imp) => unsafe {
+                let original = std::mem::transmute::<Imp, OpenPanelFn>(imp);
+                original(this, cmd, wk_webview, parameters, frame, completion);
+            },
+            None => completion.call((std::ptr::null_mut(),)),
+        },
+    }
+}
+
+/// Replaces the open panel method of wry's `WKUIDelegate`, keeping wry's
+/// implementation as the fallback. The class is shared by all WebViews, so it
+/// is only patched once.
+fn install_open_panel_method(class: *mut AnyClass) {
+    WRY_OPEN_PANEL.get_or_init(|| unsafe {
+        objc2::ffi::class_replaceMethod(
+            class,
+            sel!(webView:runOpenPanelWithParameters:initiatedByFrame:completionHandler:),
+            std::mem::transmute::<*const (), Imp>(run_open_panel as *const ()),
+            c"v@:@@@@?".as_ptr(),
+        )
+    });
+}
+
+const NS_URL_SESSION_AUTH_CHALLENGE_USE_CREDENTIAL: isize = 0;
+const NS_URL_SESSION_AUTH_CHALLENGE_PERFORM_DEFAULT_HANDLING: isize = 1;
+const NS_URL_SESSION_AUTH_CHALLENGE_CANCEL: isize = 2;
+const NS_URL_CREDENTIAL_PERSISTENCE_FOR_SESSION: usize = 1;
+
+type AuthChallengeFn = unsafe extern "C-unwind" fn(
+    *mut AnyObject,
+    Sel,
+    *mut AnyObject,
+    *mut AnyObject,
+    &Block<dyn Fn(isize, *mut AnyObject)>,
+);
+
+/// wry's `didReceiveAuthenticationChallenge` implementation, if it has one.
+static WRY_AUTH_CHALLENGE: OnceLock<Option<Imp>> = OnceLock::new();
+
+/// Returns the trust of a server trust challenge that fails evaluation, with the
+/// URL of the server and the reason of the failure.
+///
+/// Valid certificates return `None`, so they keep the default handling.
+unsafe fn failed_server_trust(
+    challenge: *mut AnyObject,
+) -> Option<(*const SecTrust, String, String)> {
+    let challenge = challenge.as_ref()?;
+    let space: *mut AnyObject = msg_send![challenge, protectionSpace];
+    let space = space.as_ref()?;
+    let method: *mut NSString = msg_send![space, authenticationMethod];
+    if ns_string_to_string(method) != "NSURLAuthenticationMethodServerTrust" {
+        return None;
+    }
+    let trust: *const SecTrust = msg_send![space, serverTrust];
+    if trust.is_null() {
+        return None;
+    }
+    let mut error: *const c_void = std::ptr::null();
+    if SecTrustEvaluateWithError(trust, &mut error) {
+        return None;
+    }
+
+    let description = if error.is_null() {
+        "certificate is not trusted".to_string()
+    } else {
+        // CFString is toll-free bridged with NSString.
+        let description = CFErrorCopyDescription(error);
+        let text = ns_string_to_string(description as *mut NSString);
+        CFRelease(description);
+        CFRelease(error);
+        text
+    };
+    let host: *mut NSString = msg_send![space, host];
+    let port: isize = msg_send![space, port];
+    let url = match port {
+        0 | 443 => format!("https://{}", ns_string_to_string(host)),
+        port => format!("https://{}:{}", ns_string_to_string(host), port),
+    };
+    Some((trust, url, description))
+}
+
+/// Returns the host and realm of an HTTP Basic or Digest challenge, and whether
+/// it comes from a proxy.
+fn http_auth_protection_space(challenge: *mut AnyObject) -> Option<(String, String, bool)> {
+    unsafe {
+        let challenge = challenge.as_ref()?;
+        let space: *mut AnyObject = msg_send![challenge, protectionSpace];
+        let space = space.as_ref()?;
+        let method: *mut NSString = msg_send![space, authenticationMethod];
+        let method = ns_string_to_string(method);
+        if method != "NSURLAuthenticationMethodHTTPBasic"
+            && method != "NSURLAuthenticationMethodHTTPDigest"
+        {
+            return None;
+        }
+        let host: *mut NSString = msg_send![space, host];
+        let realm: *mut NSString = msg_send![space, realm];
+        let is_proxy: bool = msg_send![space, isProxy];
+        Some((ns_string_to_string(host), ns_string_to_string(realm), is_proxy))
+    }
+}
+
+extern "C-unwind" fn did_receive_auth_challenge(
+    this: *mut AnyObject,
+    cmd: Sel,
+    wk_webview: *mut AnyObject,
+    challenge: *mut AnyObject,
+    completion: &Block<dyn Fn(isize, *mut AnyObject)>,
+) {
+    let state = delegate_state(wk_webview);
+    if let (Some(state), Some((trust, url, description))) =
+        (state.as_ref(), unsafe { failed_server_trust(challenge) })
+    {
+        match crate::decide_tls_error(state, url, description) {
+            TlsDecision::Allow => unsafe {
+                let credential: *mut AnyObject = match AnyClass::get(c"NSURLCredential") {
+                    Some(class) => msg_send![class, credentialForTrust: trust],
+                    None => std::ptr::null_mut(),
+                };
+                completion.call((NS_URL_SESSION_AUTH_CHALLENGE_USE_CREDENTIAL, credential));
+            },
+            TlsDecision::Deny => {
+                completion.call((NS_URL_SESSION_AUTH_CHALLENGE_CANCEL, std::ptr::null_mut()));
+            }
+        }
+        return;
+    }
+
+    let answer = match (state, http_auth_protection_space(challenge)) {
+        (Some(state), Some((host, realm, is_proxy))) => {
+            crate::answer_auth_challenge(&state, host, realm, is_proxy)
+        }
+        _ => AuthAnswer::Default,
+    };
+
+    match answer {
+        AuthAnswer::Respond(credentials) => unsafe {
+            let Some(class) = AnyClass::get(c"NSURLCredential") else {
+                completion.call((NS_URL_SESSION_AUTH_CHALLENGE_CANCEL, std::ptr::null_mut()));
+                return;
+            };
+            let username = NSString::from_str(&credentials.username);
+            let password = NSString::from_str(&credentials.password);
+            let credential: *mut AnyObject = msg_send![
+                class,
+                credentialWithUser: &*username,
+                password: &*password,
+                persistence: NS_URL_CREDENTIAL_PERSISTENCE_FOR_SESSION
+            ];
+            completion.call((NS_URL_SESSION_AUTH_CHALLENGE_USE_CREDENTIAL, credential));
+        },
+        AuthAnswer::Cancel => {
+            completion.call((NS_URL_SESSION_AUTH_CHALLENGE_CANCEL, std::ptr::null_mut()));
+        }
+        AuthAnswer::Default => match WRY_AUTH_CHALLENGE.get().copied().flatten() {
+            Some(imp) => unsafe {
+                let original = std::mem::transmute::<Imp, AuthChallengeFn>(imp);
+                original(this, cmd, wk_webview, challenge, completion);
+            },
+            None => completion.call((
+                NS_URL_SESSION_AUTH_CHALLENGE_PERFORM_DEFAULT_HANDLING,
+                std::ptr::null_mut(),
+            )),
+        },
+    }
+}
+
+/// Replaces the authentication challenge method of wry's `WKNavigationDelegate`
+/// to answer HTTP authentication and invalid server certificates, falling back
+/// to wry's implementation (or WebKit's default handling).
+fn install_auth_challenge_method(class: *mut AnyClass) {
+    WRY_AUTH_CHALLENGE.get_or_init(|| unsafe {
+        objc2::ffi::class_replaceMethod(
+            class,
+            sel!(webView:didReceiveAuthenticationChallenge:completionHandler:),
+            std::mem::transmute::<*const (), Imp>(did_receive_auth_challenge as *const ()),
+            c"v@:@@@?".as_ptr(),
+        )
+    });
+}
+
+const WK_NAVIGATION_RESPONSE_POLICY_ALLOW: isize = 1;
+
+type NavigationResponseFn = unsafe extern "C-unwind" fn(
+    *mut AnyObject,
+    Sel,
+    *mut AnyObject,
+    *mut AnyObject,
+    &Block<dyn Fn(isize)>,
+);
+
+/// wry's `decidePolicyForNavigationResponse` implementation, if it has one.
+static WRY_NAVIGATION_RESPONSE: OnceLock<Option<Imp>> = OnceLock::new();
+
+/// Returns the status and headers of an `NSHTTPURLResponse`.
+unsafe fn http_response_headers(response: &AnyObject) -> Option<(u16, Vec<(String, String)>)> {
+    let http_response_class = AnyClass::get(c"NSHTTPURLResponse")?;
+    if !msg_send![response, isKindOfClass: http_response_class] {
+        return None;
+    }
+    let status: isize = msg_send![response, statusCode];
+    let fields: *mut AnyObject = msg_send![response, allHeaderFields];
+    let fields = fields.as_ref()?;
+    let keys: *mut AnyObject = msg_send![fields, allKeys];
+    let keys = keys.as_ref()?;
+    let count: usize = msg_send![keys, count];
+
+    let mut headers = Vec::with_capacity(count);
+    for index in 0..count {
+        let key: *mut NSString = msg_send![keys, objectAtIndex: index];
+        let value: *mut NSString = msg_send![fields, objectForKey: key];
+        headers.push((ns_string_to_string(key), ns_string_to_string(value)));
+    }
+    Some((status as u16, headers))
+}
+
+extern "C-unwind" fn decide_policy_for_navigation_response(
+    this: *mut AnyObject,
+    cmd: Sel,
+    wk_webview: *mut AnyObject,
+    navigation_response: *mut AnyObject,
+    decision: &Block<dyn Fn(isize)>,
+) {
+    unsafe {
+        if let (Some(state), Some(navigation_response)) =
+            (delegate_state(wk_webview), navigation_response.as_ref())
+        {
+            let for_main_frame: Bool = msg_send![navigation_response, isForMainFrame];
+            let response: *mut AnyObject = msg_send![navigation_response, response];
+            if for_main_frame.as_bool() {
+                let headers = response.as_ref().and_then(|response| http_response_headers(response));
+                if let Some((status, headers)) = headers {
+                    crate::record_main_response(&state, status, headers);
+                }
+            }
+        }
+
+        match WRY_NAVIGATION_RESPONSE.get().copied().flatten() {
+            Some(imp) => {
+                let original = std::mem::transmute::<Imp, NavigationResponseFn>(imp);
+                original(this, cmd, wk_webview, navigation_response, decision);
+            }
+            None => decision.call((WK_NAVIGATION_RESPONSE_POLICY_ALLOW,)),
+        }
+    }
+}
+
+/// Wraps the navigation response method of wry's `WKNavigationDelegate` to cache
+/// the main document response; the policy decision is left to wry.
+fn install_navigation_response_method(class: *mut AnyClass) {
+    WRY_NAVIGATION_RESPONSE.get_or_init(|| unsafe {
+        let imp = decide_policy_for_navigation_response as *const ();
+        objc2::ffi::class_replaceMethod(
+            class,
+            sel!(webView:decidePolicyForNavigationResponse:decisionHandler:),
+            std::mem::transmute::<*const (), Imp>(imp),
+            c"v@:@@@?".as_ptr(),
+        )
+    });
+}
+
+/// `NSURLErrorCancelled`: the load was stopped or replaced by another one.
+const NS_URL_ERROR_CANCELLED: isize = -999;
+/// `WebKitErrorFrameLoadInterruptedByPolicyChange`: the navigation became a download
+/// or was denied by a policy decision.
+const WEBKIT_ERROR_FRAME_LOAD_INTERRUPTED: isize = 102;
+
+type ProvisionalFailureFn = unsafe extern "C-unwind" fn(
+    *mut AnyObject,
+    Sel,
+    *mut AnyObject,
+    *mut AnyObject,
+    *mut AnyObject,
+);
+
+/// wry's `didFailProvisionalNavigation` implementation, if it has one.
+static WRY_PROVISIONAL_FAILURE: OnceLock<Option<Imp>> = OnceLock::new();
+
+extern "C-unwind" fn did_fail_provisional_navigation(
+    this: *mut AnyObject,
+    cmd: Sel,
+    wk_webview: *mut AnyObject,
+    navigation: *mut AnyObject,
+    error: *mut AnyObject,
+) {
+    unsafe {
+        if let (Some(state), Some(ns_error)) = (delegate_state(wk_webview), error.as_ref()) {
+            let code: isize = msg_send![ns_error, code];
+            if code != NS_URL_ERROR_CANCELLED && code != WEBKIT_ERROR_FRAME_LOAD_INTERRUPTED {
+                let user_info: *mut AnyObject = msg_send![ns_error, userInfo];
+                let key = NSString::from_str("NSErrorFailingURLStringKey");
+                let url: *mut NSString = match user_info.as_ref() {
+                    Some(user_info) => msg_send![user_info, objectForKey: &*key],
+                    None => std::ptr::null_mut(),
+                };
+                crate::report_error_page(&state, ns_string_to_string(url), code as i32);
+            }
+        }
+
+        if let Some(imp) = WRY_PROVISIONAL_FAILURE.get().copied().flatten() {
+            let original = std::mem::transmute::<Imp, ProvisionalFailureFn>(imp);
+            original(this, cmd, wk_webview, navigation, error);
+        }
+    }
+}
+
+/// Wraps the provisional navigation failure method of wry's `WKNavigationDelegate`
+/// to report failed page loads.
+fn install_provisional_failure_method(class: *mut AnyClass) {
+    WRY_PROVISIONAL_FAILURE.get_or_init(|| unsafe {
+        let imp = did_fail_provisional_navigation as *const ();
+        objc2::ffi::class_replaceMethod(
+            class,
+            sel!(webView:didFailProvisionalNavigation:withError:),
+            std::mem::transmute::<*const (), Imp>(imp),
+            c"v@:@@@".as_ptr(),
+        )
+    });
+}
+
+type WillOpenMenuFn =
+    unsafe extern "C-unwind" fn(*mut AnyObject, Sel, *mut AnyObject, *mut AnyObject);
+
+/// The `willOpenMenu:withEvent:` implementation of the WebView class, inherited
+/// from `WKWebView` unless wry overrides it.
+static WEBKIT_WILL_OPEN_MENU: OnceLock<Option<Imp>> = OnceLock::new();
+
+/// Returns the items of a context menu, without separators.
+unsafe fn context_menu_items(menu: &AnyObject) -> Vec<ContextMenuItem> {
+    let items: *mut AnyObject = msg_send![menu, itemArray];
+    let Some(items) = items.as_ref() else {
+        return Vec::new();
+    };
+    let count: usize = msg_send![items, count];
+    let mut result = Vec::with_capacity(count);
+    for index in 0..count {
+        let item: *mut AnyObject = msg_send![items, objectAtIndex: index];
+        let Some(item) = item.as_ref() else {
+            continue;
+        };
+        let separator: Bool = msg_send![item, isSeparatorItem];
+        if separator.as_bool() {
+            continue;
+        }
+        let title: *mut NSString = msg_send![item, title];
+        let identifier: *mut NSString = msg_send![item, identifier];
+        let enabled: Bool = msg_send![item, isEnabled];
+        result.push(ContextMenuItem {
+            title: ns_string_to_string(title),
+            action_id: ns_string_to_string(identifier),
+            enabled: enabled.as_bool(),
+        });
+    }
+    result
+}
+
+/// Replaces the items of `menu` with custom items targeting the WebView.
+unsafe fn set_custom_context_menu_items(
+    menu: &AnyObject,
+    wk_webview: *mut AnyObject,
+    items: Vec<ContextMenuItem>,
+) {
+    let _: () = msg_send![menu, removeAllItems];
+    let _: () = msg_send![menu, setAutoenablesItems: Bool::NO];
+    let Some(class) = AnyClass::get(c"NSMenuItem") else {
+        return;
+    };
+    let key_equivalent = NSString::from_str("");
+    for item in items {
+        let title = NSString::from_str(&item.title);
+        let action_id = NSString::from_str(&item.action_id);
+        let menu_item: *mut AnyObject = msg_send![class, alloc];
+        let menu_item: *mut AnyObject = msg_send![
+            menu_item,
+            initWithTitle: &*title,
+            action: sel!(wrywebviewContextMenuItemSelected:),
+            keyEquivalent: &*key_equivalent
+        ];
+        if menu_item.is_null() {
+            continue;
+        }
+        let _: () = msg_send![menu_item, setTarget: wk_webview];
+        let _: () = msg_send![menu_item, setRepresentedObject: &*action_id];
+        let _: () = msg_send![menu_item, setEnabled: Bool::new(item.enabled)];
+        let _: () = msg_send![menu, addItem: menu_item];
+        // The menu retains the item.
+        objc2::ffi::objc_release(menu_item);
+    }
+}
+
+extern "C-unwind" fn will_open_menu(
+    this: *mut AnyObject,
+    cmd: Sel,
+    menu: *mut AnyObject,
+    event: *mut AnyObject,
+) {
+    unsafe {
+        if let (Some(state), Some(menu_ref)) = (delegate_state(this), menu.as_ref()) {
+            if state.context_menu_handler.is_set() {
+                let (x, y) = match event.as_ref() {
+                    Some(event) => {
+                        let location: NSPoint = msg_send![event, locationInWindow];
+                        let point: NSPoint = msg_send![
+                            this,
+                            convertPoint: location,
+                            fromView: std::ptr::null_mut::<AnyObject>()
+                        ];
+                        (point.x as i32, point.y as i32)
+                    }
+                    None => (0, 0),
+                };
+                let items = context_menu_items(menu_ref);
+                match crate::decide_context_menu(&state, x, y, items) {
+                    ContextMenuDecision::ShowDefault => {}
+                    ContextMenuDecision::Suppress => {
+                        // AppKit does not show an empty menu.
+                        let _: () = msg_send![menu_ref, removeAllItems];
+                    }
+                    ContextMenuDecision::ShowCustom(items) => {
+                        set_custom_context_menu_items(menu_ref, this, items);
+                    }
+                }
+            }
+        }
+
+        if let Some(imp) = WEBKIT_WILL_OPEN_MENU.get().copied().flatten() {
+            let original = std::mem::transmute::<Imp, WillOpenMenuFn>(imp);
+            original(this, cmd, menu, event);
+        }
+    }
+}
+
+extern "C-unwind" fn context_menu_item_selected(
+    this: *mut AnyObject,
+    _cmd: Sel,
+    sender: *mut AnyObject,
+) {
+    let Some(sender) = (unsafe { sender.as_ref() }) else {
+        return;
+    };
+    let action_id: *mut NSString = unsafe { msg_send![sender, representedObject] };
+    if let Some(state) = delegate_state(this) {
+        crate::context_menu_item_selected(&state, ns_string_to_string(action_id));
+    }
+}
+
+/// Wraps `willOpenMenu:withEvent:` of the WebView class to let the
+/// `ContextMenuHandler` edit the context menu, and adds the action of custom items.
+fn install_context_menu_methods(class: *mut AnyClass) {
+    WEBKIT_WILL_OPEN_MENU.get_or_init(|| unsafe {
+        let selector = sel!(willOpenMenu:withEvent:);
+        let inherited = objc2::ffi::class_getMethodImplementation(class, selector);
+        objc2::ffi::class_replaceMethod(
+            class,
+            selector,
+            std::mem::transmute::<*const (), Imp>(will_open_menu as *const ()),
+            c"v@:@@".as_ptr(),
+        );
+        objc2::ffi::class_addMethod(
+            class,
+            sel!(wrywebviewContextMenuItemSelected:),
+            std::mem::transmute::<*const (), Imp>(context_menu_item_selected as *const ()),
+            c"v@:@".as_ptr(),
+        );
+        inherited
+    });
+}
+
+type HitTestFn = unsafe extern "C-unwind" fn(*mut AnyObject, Sel, NSPoint) -> *mut AnyObject;
+
+/// The `hitTest:` implementation of the WebView class.
+static WEBKIT_HIT_TEST: OnceLock<Option<Imp>> = OnceLock::new();
+
+/// Hides the WebView from hit-testing while its pointer events pass through, so
+/// AppKit delivers them to the views below.
+extern "C-unwind" fn hit_test(this: *mut AnyObject, cmd: Sel, point: NSPoint) -> *mut AnyObject {
+    if let Some(state) = delegate_state(this) {
+        if !state.pointer_events_enabled.load(Ordering::Acquire) {
+            return std::ptr::null_mut();
+        }
+    }
+    match WEBKIT_HIT_TEST.get().copied().flatten() {
+        Some(imp) => unsafe {
+            let original = std::mem::transmute::<Imp, HitTestFn>(imp);
+            original(this, cmd, point)
+        },
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Wraps `hitTest:` of the WebView class for `set_pointer_passthrough`.
+fn install_hit_test_method(class: *mut AnyClass) {
+    WEBKIT_HIT_TEST.get_or_init(|| unsafe {
+        let selector = sel!(hitTest:);
+        let inherited = objc2::ffi::class_getMethodImplementation(class, selector);
+        objc2::ffi::class_replaceMethod(
+            class,
+            selector,
+            std::mem::transmute::<*const (), Imp>(hit_test as *const ()),
+            c"@@:{CGPoint=dd}".as_ptr(),
+        );
+        inherited
+    });
+}
+
+type FirstResponderFn = unsafe extern "C-unwind" fn(*mut AnyObject, Sel) -> Bool;
+
+/// The `becomeFirstResponder` and `resignFirstResponder` implementations of the
+/// WebView class.
+static WEBKIT_BECOME_FIRST_RESPONDER: OnceLock<Option<Imp>> = OnceLock::new();
+static WEBKIT_RESIGN_FIRST_RESPONDER: OnceLock<Option<Imp>> = OnceLock::new();
+
+/// Calls the WebKit implementation and records the focus change it accepted.
+fn track_first_responder(
+    this: *mut AnyObject,
+    cmd: Sel,
+    inherited: &OnceLock<Option<Imp>>,
+    focused: bool,
+) -> Bool {
+    let accepted = match inherited.get().copied().flatten() {
+        Some(imp) => unsafe {
+            let original = std::mem::transmute::<Imp, FirstResponderFn>(imp);
+            original(this, cmd)
+        },
+        None => Bool::YES,
+    };
+    if accepted.as_bool() {
+        if let Some(state) = delegate_state(this) {
+            state.has_focus.store(focused, Ordering::Release);
+        }
+    }
+    accepted
+}
+
+extern "C-unwind" fn become_first_responder(this: *mut AnyObject, cmd: Sel) -> Bool {
+    track_first_responder(this, cmd, &WEBKIT_BECOME_FIRST_RESPONDER, true)
+}
+
+extern "C-unwind" fn resign_first_responder(this: *mut AnyObject, cmd: Sel) -> Bool {
+    track_first_responder(this, cmd, &WEBKIT_RESIGN_FIRST_RESPONDER, false)
+}
+
+/// Wraps the first responder methods of the WebView class to keep
+/// `WebViewState::has_focus` up to date.
+fn install_first_responder_methods(class: *mut AnyClass) {
+    let methods: [(&OnceLock<Option<Imp>>, Sel, FirstResponderFn); 2] = [
+        (
+            &WEBKIT_BECOME_FIRST_RESPONDER,
+            sel!(becomeFirstResponder),
+            become_first_responder,
+        ),
+        (
+            &WEBKIT_RESIGN_FIRST_RESPONDER,
+            sel!(resignFirstResponder),
+            resign_first_responder,
+        ),
+    ];
+    for (inherited, selector, implementation) in methods {
+        inherited.get_or_init(|| unsafe {
+            let original = objc2::ffi::class_getMethodImplementation(class, selector);
+            objc2::ffi::class_replaceMethod(
+                class,
+                selector,
+                std::mem::transmute::<*const (), Imp>(implementation as *const ()),
+                c"c@:".as_ptr(),
+            );
+            original
+        });
+    }
+}
+
+/// Gives up keyboard focus when pointer events start passing through; `hitTest:`
+/// reads the setting from the state.
+pub fn set_pointer_passthrough(webview: &WebView, passthrough: bool) -> Result<(), WebViewError> {
+    if !passthrough {
+        return Ok(());
+    }
+    resign_focus(webview)
+}
+
+/// Clears the first responder of the window if it is the WebView or one of its subviews.
+fn resign_focus(webview: &WebView) -> Result<(), WebViewError> {
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    unsafe {
+        let window: *mut AnyObject = msg_send![view, window];
+        let Some(window) = window.as_ref() else {
+            return Ok(());
+        };
+        let nsview_cls = AnyClass::get(c"NSView").ok_or(WebViewError::UnsupportedPlatform)?;
+        let responder: *mut AnyObject = msg_send![window, firstResponder];
+        let has_focus = match responder.as_ref() {
+            Some(responder) if msg_send![responder, isKindOfClass: nsview_cls] => {
+                msg_send![responder, isDescendantOf: view]
+            }
+            _ => false,
+        };
+        if has_focus {
+            let _: Bool = msg_send![window, makeFirstResponder: std::ptr::null_mut::<AnyObject>()];
+        }
+    }
+    Ok(())
+}
+
+const NS_VIEW_NOT_SIZABLE: usize = 0;
+const NS_VIEW_WIDTH_SIZABLE: usize = 2;
+const NS_VIEW_HEIGHT_SIZABLE: usize = 16;
+
+/// Returns the size of the WebView's superview in points.
+pub fn parent_size(webview: &WebView, _parent_handle: u64) -> Option<(i32, i32)> {
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    unsafe {
+        let superview: *mut AnyObject = msg_send![view, superview];
+        let superview = superview.as_ref()?;
+        let bounds: NSRect = msg_send![superview, bounds];
+        Some((bounds.size.width.round() as i32, bounds.size.height.round() as i32))
+    }
+}
+
+/// Moves the WebView into another NSView (or the content view of an NSWindow).
+///
+/// The frame is kept, so it stays at the same position relative to the new parent.
+pub fn move_to_parent(
+    webview: &WebView,
+    _old_parent_handle: u64,
+    new_parent_handle: u64,
+) -> Result<(), WebViewError> {
+    let parent = appkit_ns_view_from_handle(new_parent_handle)?;
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    unsafe {
+        let parent = &*(parent.as_ptr() as *mut AnyObject);
+        // wry keeps its own reference, so the view survives leaving its superview.
+        let _: () = msg_send![view, removeFromSuperview];
+        let _: () = msg_send![parent, addSubview: view];
+    }
+    Ok(())
+}
+
+/// `NSWindowOrderingMode` values used to restack subviews.
+const NS_WINDOW_ABOVE: isize = 1;
+const NS_WINDOW_BELOW: isize = -1;
+
+/// Places the WebView above, or below, the other subviews of its superview.
+pub fn restack(webview: &WebView, parent_handle: u64, to_front: bool) -> Result<(), WebViewError> {
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    let mode = if to_front { NS_WINDOW_ABOVE } else { NS_WINDOW_BELOW };
+    unsafe {
+        let superview: *mut AnyObject = msg_send![view, superview];
+        let superview = superview.as_ref().ok_or(WebViewError::InvalidWindowHandle(parent_handle))?;
+        // Re-adding a subview moves it without detaching it from the window.
+        let relative_to: *const AnyObject = std::ptr::null();
+        let _: () = msg_send![
+            superview,
+            addSubview: view,
+            positioned: mode,
+            relativeTo: relative_to
+        ];
+    }
+    Ok(())
+}
+
+/// Lets AppKit keep the WebView's margins constant when its superview resizes.
+pub fn set_auto_resize(
+    webview: &WebView,
+    _id: u64,
+    _parent_handle: u64,
+    enabled: bool,
+) -> Result<(), WebViewError> {
+    let mask = if enabled {
+        NS_VIEW_WIDTH_SIZABLE | NS_VIEW_HEIGHT_SIZABLE
+    } else {
+        NS_VIEW_NOT_SIZABLE
+    };
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    unsafe {
+        let _: () = msg_send![view, setAutoresizingMask: mask];
+    }
+    Ok(())
+}
+
+/// Opaque `SecTrustRef` target.
+#[repr(C)]
+struct SecTrust {
+    _private: [u8; 0],
+}
+
+unsafe impl RefEncode for SecTrust {
+    const ENCODING_REF: Encoding = Encoding::Pointer(&Encoding::Struct("__SecTrust", &[]));
+}
+
+#[link(name = "Security", kind = "framework")]
+extern "C" {
+    fn SecTrustGetCertificateCount(trust: *const SecTrust) -> isize;
+    fn SecTrustGetCertificateAtIndex(trust: *const SecTrust, index: isize) -> *const c_void;
+    fn SecCertificateCopyData(certificate: *const c_void) -> *const c_void;
+    fn SecTrustEvaluateWithError(trust: *const SecTrust, error: *mut *const c_void) -> bool;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFDataGetLength(data: *const c_void) -> isize;
+    fn CFDataGetBytePtr(data: *const c_void) -> *const u8;
+    fn CFRelease(object: *const c_void);
+    fn CFErrorCopyDescription(error: *const c_void) -> *const c_void;
+}
+
+/// Reads the TLS certificate chain from `WKWebView.serverTrust`.
+pub fn certificate_chain(
+    webview: &WebView,
+    _url: &str,
+    on_chain: CertificateChainCompletion,
+) -> Result<(), WebViewError> {
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    let mut chain = Vec::new();
+    unsafe {
+        let trust: *const SecTrust = msg_send![view, serverTrust];
+        if trust.is_null() {
+            return Err(WebViewError::NotSecure);
+        }
+        for index in 0..SecTrustGetCertificateCount(trust) {
+            let certificate = SecTrustGetCertificateAtIndex(trust, index);
+            if certificate.is_null() {
+                continue;
+            }
+            let data = SecCertificateCopyData(certificate);
+            if data.is_null() {
+                continue;
+            }
+            let length = CFDataGetLength(data) as usize;
+            let bytes = std::slice::from_raw_parts(CFDataGetBytePtr(data), length);
+            chain.push(bytes.to_vec());
+            CFRelease(data);
+        }
+    }
+    on_chain(chain);
+    Ok(())
+}
+
+/// Toggles Web SQL through the private `WKPreferences` flag, when WebKit still has it.
+pub fn set_web_sql_enabled(webview: &WebView, enabled: bool) -> Result<(), WebViewError> {
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    unsafe {
+        let configuration: *mut AnyObject = msg_send![view, configuration];
+        let configuration = configuration.as_ref().ok_or(WebViewError::UnsupportedPlatform)?;
+        let preferences: *mut AnyObject = msg_send![configuration, preferences];
+        let preferences = preferences.as_ref().ok_or(WebViewError::UnsupportedPlatform)?;
+
+        if msg_send![preferences, respondsToSelector: sel!(_setWebSQLEnabled:)] {
+            let _: () = msg_send![preferences, _setWebSQLEnabled: Bool::new(enabled)];
+        } else if msg_send![preferences, respondsToSelector: sel!(_setWebSQLDisabled:)] {
+            let _: () = msg_send![preferences, _setWebSQLDisabled: Bool::new(!enabled)];
+        } else {
+            return Err(WebViewError::UnsupportedPlatform);
+        }
+    }
+    Ok(())
+}
+
+/// Sets the `allowFileAccessFromFileURLs` preference, which WebKit only exposes
+/// through key-value coding.
+pub fn set_allow_file_access(webview: &WebView, enabled: bool) -> Result<(), WebViewError> {
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    unsafe {
+        let configuration: *mut AnyObject = msg_send![view, configuration];
+        let configuration = configuration.as_ref().ok_or(WebViewError::UnsupportedPlatform)?;
+        let preferences: *mut AnyObject = msg_send![configuration, preferences];
+        let preferences = preferences.as_ref().ok_or(WebViewError::UnsupportedPlatform)?;
+        let number_cls = AnyClass::get(c"NSNumber").ok_or(WebViewError::UnsupportedPlatform)?;
+        let value: *mut AnyObject = msg_send![number_cls, numberWithBool: Bool::new(enabled)];
+        let key = NSString::from_str("allowFileAccessFromFileURLs");
+        let _: () = msg_send![preferences, setValue: value, forKey: &*key];
+    }
+    Ok(())
+}
+
+/// Sets `WKWebView.allowsMagnification`, which drives pinch and smart zoom.
+pub fn set_pinch_zoom_enabled(webview: &WebView, enabled: bool) -> Result<(), WebViewError> {
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    unsafe {
+        let _: () = msg_send![view, setAllowsMagnification: Bool::new(enabled)];
+    }
+    Ok(())
+}
+
+/// Sets `WKPreferences.javaScriptEnabled`, which the WebView shares with its
+/// configuration.
+pub fn set_javascript_enabled(webview: &WebView, enabled: bool) -> Result<(), WebViewError> {
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    unsafe {
+        let configuration: *mut AnyObject = msg_send![view, configuration];
+        let configuration = configuration.as_ref().ok_or(WebViewError::UnsupportedPlatform)?;
+        let preferences: *mut AnyObject = msg_send![configuration, preferences];
+        let preferences = preferences.as_ref().ok_or(WebViewError::UnsupportedPlatform)?;
+        let _: () = msg_send![preferences, setJavaScriptEnabled: Bool::new(enabled)];
+    }
+    Ok(())
+}
+
+/// Makes the WebView the first responder of its window.
+pub fn focus(webview: &WebView) -> Result<(), WebViewError> {
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    unsafe {
+        let window: *mut AnyObject = msg_send![view, window];
+        let Some(window) = window.as_ref() else {
+            return Err(WebViewError::Internal("webview is not in a window".to_string()));
+        };
+        let accepted: Bool = msg_send![window, makeFirstResponder: view];
+        if !accepted.as_bool() {
+            return Err(WebViewError::Internal("window refused to focus the webview".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Gives up the first responder status, leaving the window without a focused view.
+pub fn blur(webview: &WebView, _parent_handle: u64) -> Result<(), WebViewError> {
+    resign_focus(webview)
+}
+
+/// Goes back in the native session history; `false` if there is no previous page.
+pub fn go_back(webview: &WebView) -> Result<bool, WebViewError> {
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    unsafe {
+        let can_go_back: Bool = msg_send![view, canGoBack];
+        if !can_go_back.as_bool() {
+            return Ok(false);
+        }
+        let _: *mut AnyObject = msg_send![view, goBack];
+    }
+    Ok(true)
+}
+
+/// Goes forward in the native session history; `false` if there is no next page.
+pub fn go_forward(webview: &WebView) -> Result<bool, WebViewError> {
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    unsafe {
+        let can_go_forward: Bool = msg_send![view, canGoForward];
+        if !can_go_forward.as_bool() {
+            return Ok(false);
+        }
+        let _: *mut AnyObject = msg_send![view, goForward];
+    }
+    Ok(true)
+}
+
+/// Loads an `NSMutableURLRequest` built from the method, headers and body.
+pub fn load_request(
+    webview: &WebView,
+    method: &str,
+    url: &str,
+    headers: &[(String, String)],
+    body: Option<&[u8]>,
+) -> Result<(), WebViewError> {
+    let ns_url = NSURL::URLWithString(&NSString::from_str(url))
+        .ok_or_else(|| WebViewError::InvalidUrl(url.to_string()))?;
+    let request_cls =
+        AnyClass::get(c"NSMutableURLRequest").ok_or(WebViewError::UnsupportedPlatform)?;
+    let data_cls = AnyClass::get(c"NSData").ok_or(WebViewError::UnsupportedPlatform)?;
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    unsafe {
+        let request: *mut AnyObject = msg_send![request_cls, requestWithURL: &*ns_url];
+        let request = request
+            .as_ref()
+            .ok_or_else(|| WebViewError::Internal("NSMutableURLRequest failed".to_string()))?;
+        let _: () = msg_send![request, setHTTPMethod: &*NSString::from_str(method)];
+        for (name, value) in headers {
+            let _: () = msg_send![
+                request,
+                setValue: &*NSString::from_str(value),
+                forHTTPHeaderField: &*NSString::from_str(name)
+            ];
+        }
+        if let Some(body) = body {
+            let data: *mut AnyObject = msg_send![
+                data_cls,
+                dataWithBytes: body.as_ptr() as *const c_void,
+                length: body.len()
+            ];
+            let _: () = msg_send![request, setHTTPBody: data];
+        }
+        let _: *mut AnyObject = msg_send![view, loadRequest: request];
+    }
+    Ok(())
+}
+
+/// Reloads with `WKWebView.reloadFromOrigin`, revalidating every resource.
+pub fn reload_bypass_cache(webview: &WebView) -> Result<(), WebViewError> {
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    unsafe {
+        let _: *mut AnyObject = msg_send![view, reloadFromOrigin];
+    }
+    Ok(())
+}
+
+/// Sets the `NSAppearance` of the WebView, which drives `prefers-color-scheme`.
+///
+/// `Auto` clears it so the WebView follows the appearance of its window.
+pub fn set_color_scheme(webview: &WebView, scheme: ColorScheme) -> Result<(), WebViewError> {
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    let name = match scheme {
+        ColorScheme::Light => Some("NSAppearanceNameAqua"),
+        ColorScheme::Dark => Some("NSAppearanceNameDarkAqua"),
+        ColorScheme::Auto => None,
+    };
+    unsafe {
+        let appearance: *mut AnyObject = match name {
+            Some(name) => {
+                let class =
+                    AnyClass::get(c"NSAppearance").ok_or(WebViewError::UnsupportedPlatform)?;
+                let name = NSString::from_str(name);
+                msg_send![class, appearanceNamed: &*name]
+            }
+            None => std::ptr::null_mut(),
+        };
+        let _: () = msg_send![view, setAppearance: appearance];
+    }
+    Ok(())
+}
+
+/// Reports mixed content once the page has loaded, from `WKWebView.hasOnlySecureContent`.
+pub fn page_security_warnings(webview: &WebView) -> Vec<SslWarningType> {
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    let only_secure: Bool = unsafe { msg_send![view, hasOnlySecureContent] };
+    if only_secure.as_bool() {
+        Vec::new()
+    } else {
+        // WebKit does not tell passive and active mixed content apart here.
+        vec![SslWarningType::MixedContentDisplayed]
+    }
+}
+
+/// Sets the proxy on the data store of the WebView (macOS 14 and later).
+///
+/// WebKit only supports HTTP CONNECT and SOCKS5 proxies without a bypass list.
+pub fn with_proxy<'a>(
+    builder: WebViewBuilder<'a>,
+    proxy: &ProxyConfig,
+) -> Result<WebViewBuilder<'a>, WebViewError> {
+    let endpoint = ProxyEndpoint {
+        host: proxy.host.clone(),
+        port: proxy.port.to_string(),
+    };
+    let config = match proxy.proxy_type {
+        ProxyType::Http => wry::ProxyConfig::Http(endpoint),
+        ProxyType::Socks5 => wry::ProxyConfig::Socks5(endpoint),
+        ProxyType::Https | ProxyType::Socks4 => return Err(WebViewError::UnsupportedPlatform),
+    };
+    if !proxy.bypass_list.is_empty() {
+        eprintln!("[wrywebview] proxy bypass list is ignored on macOS");
+    }
+    Ok(builder.with_proxy_config(config))
+}
+
+/// Gives the WebView the persistent `WKWebsiteDataStore` named after `identifier`
+/// (macOS 14 and later), shared by the WebViews created with the same identifier.
+pub fn with_data_store_identifier<'a>(
+    builder: WebViewBuilder<'a>,
+    identifier: &str,
+) -> WebViewBuilder<'a> {
+    let digest = crate::platform::data_store_digest(identifier);
+    let mut uuid = [0u8; 16];
+    uuid.copy_from_slice(&digest[..16]);
+    builder.with_data_store_identifier(uuid)
+}
+
+/// The proxy of a `WKWebsiteDataStore` cannot change once the WebView is created.
+pub fn set_proxy(_webview: &WebView, _proxy: &ProxyConfig) -> Result<(), WebViewError> {
+    Err(WebViewError::NotSupportedAtRuntime)
+}
+
+/// `mediaTypesRequiringUserActionForPlayback` is read from the configuration when
+/// the WebView is created; the WebView only exposes a copy of it afterwards.
+pub fn set_autoplay_policy(
+    _webview: &WebView,
+    _policy: AutoplayPolicy,
+) -> Result<(), WebViewError> {
+    Err(WebViewError::NotSupportedAtRuntime)
+}
+
+/// `WKMediaAudioMuted` of the private `WKMediaMutedState` bitmask.
+const WK_MEDIA_AUDIO_MUTED: usize = 1;
+
+/// Mutes the page with the private `_setPageMuted:`, or mutes its media elements
+/// when WebKit does not provide it.
+pub fn set_muted(webview: &WebView, muted: bool) -> Result<(), WebViewError> {
+    let wk_webview = webview.webview();
+    let view: &AnyObject = &wk_webview;
+    unsafe {
+        if msg_send![view, respondsToSelector: sel!(_setPageMuted:)] {
+            let state = if muted { WK_MEDIA_AUDIO_MUTED } else { 0 };
+            let _: () = msg_send![view, _setPageMuted: state];
+            return Ok(());
+        }
+    }
+    webview
+        .evaluate_script(&crate::scripts::mute_media_script(muted))
+        .map_err(WebViewError::from)
 }
@@ -9,17 +9,268 @@ pub mod macos;
 #[cfg(target_os = "windows")]
 pub mod windows;
 
-#[cfg(all(not(target_os = "macos"), not(target_os = "linux")))]
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+
 use crate::error::WebViewError;
+#[cfg(not(target_os = "macos"))]
+use crate::{scripts, ColorScheme};
 
 #[cfg(target_os = "macos")]
-pub use macos::run_on_main_thread;
+pub use macos::{run_on_main_thread, run_on_main_thread_timeout};
+#[cfg(target_os = "windows")]
+pub use windows::{run_on_main_thread, run_on_main_thread_timeout};
+
+#[cfg(target_os = "linux")]
+pub use linux::{
+    blur, certificate_chain, clear_proxy, disable_hardware_acceleration, focus, go_back, go_forward,
+    install_handlers, load_request, move_to_parent, page_security_warnings, parent_size,
+    reload_bypass_cache, restack, set_allow_file_access, set_auto_resize, set_autoplay_policy,
+    set_javascript_enabled, set_muted, set_pinch_zoom_enabled, set_pointer_passthrough, set_proxy,
+    set_transparent, set_web_sql_enabled,
+};
+#[cfg(target_os = "macos")]
+pub use macos::{
+    blur, certificate_chain, focus, go_back, go_forward, install_handlers, load_request,
+    move_to_parent, page_security_warnings, parent_size, reload_bypass_cache, restack,
+    set_allow_file_access, set_auto_resize, set_autoplay_policy, set_color_scheme,
+    set_javascript_enabled, set_muted, set_pinch_zoom_enabled, set_pointer_passthrough, set_proxy,
+    set_web_sql_enabled, with_data_store_identifier, with_proxy,
+};
+#[cfg(target_os = "windows")]
+pub use windows::{
+    blur, browser_args, certificate_chain, focus, go_back, go_forward, install_handlers,
+    load_request, move_to_parent, page_security_warnings, parent_size, reload_bypass_cache, restack,
+    set_allow_file_access, set_auto_resize, set_autoplay_policy, set_javascript_enabled, set_muted,
+    set_pinch_zoom_enabled, set_pointer_passthrough, set_proxy, set_script_dialogs_intercepted,
+    set_web_sql_enabled, with_browser_args,
+};
+
+/// Receives the DER certificates of a TLS chain, starting with the server certificate.
+pub type CertificateChainCompletion = Box<dyn FnOnce(Vec<Vec<u8>>) + Send + 'static>;
+
+static HARDWARE_ACCELERATION: AtomicBool = AtomicBool::new(true);
+
+/// Allows or forbids GPU rendering for the WebViews created afterwards.
+pub fn set_hardware_acceleration_enabled(enabled: bool) {
+    HARDWARE_ACCELERATION.store(enabled, Ordering::Relaxed);
+}
+
+pub fn hardware_acceleration_enabled() -> bool {
+    HARDWARE_ACCELERATION.load(Ordering::Relaxed)
+}
+
+static REMOTE_DEBUG_PORT: OnceLock<u16> = OnceLock::new();
+
+/// Records the port of the remote inspector; it cannot change once chosen.
+pub fn set_remote_debugging_port(port: u16) -> Result<(), WebViewError> {
+    if *REMOTE_DEBUG_PORT.get_or_init(|| port) != port {
+        return Err(WebViewError::AlreadyInitialized);
+    }
+    #[cfg(target_os = "linux")]
+    linux::enable_remote_inspector(port);
+    Ok(())
+}
+
+pub fn remote_debugging_port() -> Option<u16> {
+    REMOTE_DEBUG_PORT.get().copied()
+}
+
+/// Returns a digest of a data store identifier, usable as a file or store name
+/// whatever characters the identifier contains.
+pub fn data_store_digest(identifier: &str) -> [u8; 32] {
+    Sha256::digest(identifier.as_bytes()).into()
+}
+
+/// Directory holding the cookies, cache and storage of a named data store, under
+/// the user's local data directory.
+pub fn data_store_directory(identifier: &str) -> PathBuf {
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_DATA_HOME").map(PathBuf::from).or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+        })
+    };
+    let name: String = data_store_digest(identifier)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    base.unwrap_or_else(std::env::temp_dir)
+        .join("wrywebview")
+        .join("data-stores")
+        .join(name)
+}
+
+/// Default time to wait for a closure posted to the UI thread, in milliseconds.
+const DEFAULT_MAIN_THREAD_TIMEOUT_MS: u64 = 10_000;
+
+static MAIN_THREAD_TIMEOUT_MS: AtomicU64 = AtomicU64::new(DEFAULT_MAIN_THREAD_TIMEOUT_MS);
+
+/// Sets how long callers wait for the UI thread; 0 waits forever.
+pub fn set_main_thread_timeout_ms(timeout_ms: u64) {
+    MAIN_THREAD_TIMEOUT_MS.store(timeout_ms, Ordering::Relaxed);
+}
+
+/// Returns how long callers wait for the UI thread, in milliseconds; 0 waits forever.
+pub fn main_thread_timeout_ms() -> u64 {
+    MAIN_THREAD_TIMEOUT_MS.load(Ordering::Relaxed)
+}
+
+const TASK_PENDING: u8 = 0;
+const TASK_STARTED: u8 = 1;
+const TASK_CANCELLED: u8 = 2;
+
+/// Shared between a caller and the closure it posted to the UI thread.
+///
+/// Exactly one side wins: either the UI thread starts the closure, or the
+/// caller cancels it after a timeout and the closure is skipped entirely. A
+/// closure that already started always runs to completion, so a timeout never
+/// leaves the UI thread halfway through an operation.
+#[derive(Clone, Default)]
+pub(crate) struct CancellationToken(Arc<AtomicU8>);
+
+impl CancellationToken {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Called on the UI thread before running the closure; `false` means skip it.
+    pub(crate) fn start(&self) -> bool {
+        self.transition(TASK_STARTED)
+    }
+
+    /// Called by the waiting thread; `false` means the closure already started.
+    pub(crate) fn cancel(&self) -> bool {
+        self.transition(TASK_CANCELLED)
+    }
+
+    fn transition(&self, to: u8) -> bool {
+        self.0
+            .compare_exchange(TASK_PENDING, to, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+}
+
+/// Waits up to `timeout_ms` (0 waits forever) for a closure posted to the UI
+/// thread.
+///
+/// On timeout the closure is cancelled if it has not started yet; otherwise it
+/// finishes on the UI thread and only its result is dropped.
+pub(crate) fn wait_for_result<R>(
+    result_rx: &Receiver<Result<R, WebViewError>>,
+    token: &CancellationToken,
+    timeout_ms: u64,
+    thread_name: &str,
+) -> Result<R, WebViewError> {
+    let stopped = || WebViewError::Internal(format!("{} stopped", thread_name));
+    if timeout_ms == 0 {
+        return result_rx.recv().map_err(|_| stopped())?;
+    }
+    match result_rx.recv_timeout(Duration::from_millis(timeout_ms)) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => {
+            if !token.cancel() {
+                eprintln!(
+                    "[wrywebview] {} timed out after {} ms while a task was running",
+                    thread_name, timeout_ms
+                );
+            }
+            Err(WebViewError::MainThreadTimeout(timeout_ms))
+        }
+        Err(RecvTimeoutError::Disconnected) => Err(stopped()),
+    }
+}
 
-/// Runs a closure on the main thread (no-op on non-macOS platforms).
-#[cfg(all(not(target_os = "macos"), not(target_os = "linux")))]
+/// Runs a closure on the main thread (no-op on unsupported platforms).
+///
+/// The closure runs on the calling thread, so it never times out.
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
 pub fn run_on_main_thread<F, R>(f: F) -> Result<R, WebViewError>
 where
     F: FnOnce() -> Result<R, WebViewError>,
 {
     f()
 }
+
+/// Runs a closure on the main thread (no-op on unsupported platforms).
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+pub fn run_on_main_thread_timeout<F, R>(f: F, _timeout_ms: u64) -> Result<R, WebViewError>
+where
+    F: FnOnce() -> Result<R, WebViewError>,
+{
+    f()
+}
+
+/// Disables the native JavaScript dialogs while a `JsDialogHandler` is set.
+///
+/// Only WebView2 needs this: it raises `ScriptDialogOpening` only when its default
+/// dialogs are disabled. The other platforms check for a handler in their callbacks.
+#[cfg(not(target_os = "windows"))]
+pub fn set_script_dialogs_intercepted(
+    _webview: &wry::WebView,
+    _intercepted: bool,
+) -> Result<(), WebViewError> {
+    Ok(())
+}
+
+/// Overrides `prefers-color-scheme` with a page script.
+///
+/// Neither WebKitGTK nor WebView2 has a per-WebView color scheme, so the script
+/// is also re-applied after each navigation while a scheme other than `Auto` is set.
+#[cfg(not(target_os = "macos"))]
+pub fn set_color_scheme(webview: &wry::WebView, scheme: ColorScheme) -> Result<(), WebViewError> {
+    webview
+        .evaluate_script(&scripts::color_scheme_script(scheme))
+        .map_err(WebViewError::from)
+}
+
+/// Opens `url` in the default browser of the OS.
+pub fn open_in_system_browser(url: &str) -> Result<(), WebViewError> {
+    #[cfg(target_os = "linux")]
+    let mut command = Command::new("xdg-open");
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        // Avoids `cmd /c start`, which would interpret shell metacharacters in the URL.
+        let mut command = Command::new("rundll32");
+        command.arg("url.dll,FileProtocolHandler");
+        command
+    };
+
+    command
+        .arg(url)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| WebViewError::Internal(format!("failed to open {}: {}", url, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Instant;
+
+    #[test]
+    fn wait_for_result_times_out_near_the_limit() {
+        // The sender stays alive, as for a UI thread that never gets to the task.
+        let (_result_tx, result_rx) = mpsc::sync_channel::<Result<(), WebViewError>>(1);
+        let token = CancellationToken::new();
+        let start = Instant::now();
+        let result = wait_for_result(&result_rx, &token, 100, "test thread");
+        let elapsed = start.elapsed();
+
+        assert!(matches!(result, Err(WebViewError::MainThreadTimeout(100))));
+        assert!(elapsed >= Duration::from_millis(100), "timed out after {:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(300), "timed out after {:?}", elapsed);
+        // The task had not started, so it was cancelled and will be skipped.
+        assert!(!token.start());
+    }
+}
@@ -1,16 +1,1391 @@
-//! Windows-specific message pump.
+//! Windows-specific message pump and WebView2 integration.
 
-/// Pumps the Windows message queue.
-pub fn pump_events() {
+use std::collections::VecDeque;
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread::{self, ThreadId};
+
+use base64::Engine;
+use webview2_com::Microsoft::Web::WebView2::Win32::{
+    COREWEBVIEW2_CONTEXT_MENU_ITEM_KIND, COREWEBVIEW2_CONTEXT_MENU_ITEM_KIND_COMMAND,
+    COREWEBVIEW2_CONTEXT_MENU_ITEM_KIND_SEPARATOR, COREWEBVIEW2_MOVE_FOCUS_REASON_PROGRAMMATIC,
+    COREWEBVIEW2_PERMISSION_KIND, COREWEBVIEW2_PERMISSION_KIND_CAMERA,
+    COREWEBVIEW2_PERMISSION_KIND_CLIPBOARD_READ, COREWEBVIEW2_PERMISSION_KIND_GEOLOCATION,
+    COREWEBVIEW2_PERMISSION_KIND_MICROPHONE, COREWEBVIEW2_PERMISSION_KIND_NOTIFICATIONS,
+    COREWEBVIEW2_PERMISSION_STATE_ALLOW, COREWEBVIEW2_PERMISSION_STATE_DENY,
+    COREWEBVIEW2_SCRIPT_DIALOG_KIND, COREWEBVIEW2_SCRIPT_DIALOG_KIND_ALERT,
+    COREWEBVIEW2_SCRIPT_DIALOG_KIND_BEFOREUNLOAD, COREWEBVIEW2_SCRIPT_DIALOG_KIND_CONFIRM,
+    COREWEBVIEW2_SCRIPT_DIALOG_KIND_PROMPT,
+    COREWEBVIEW2_SERVER_CERTIFICATE_ERROR_ACTION_ALWAYS_ALLOW, COREWEBVIEW2_WEB_ERROR_STATUS,
+    COREWEBVIEW2_WEB_ERROR_STATUS_CERTIFICATE_COMMON_NAME_IS_INCORRECT,
+    COREWEBVIEW2_WEB_ERROR_STATUS_CERTIFICATE_EXPIRED,
+    COREWEBVIEW2_WEB_ERROR_STATUS_CERTIFICATE_IS_INVALID,
+    COREWEBVIEW2_WEB_ERROR_STATUS_CERTIFICATE_REVOKED,
+    COREWEBVIEW2_WEB_ERROR_STATUS_CLIENT_CERTIFICATE_CONTAINS_ERRORS,
+    COREWEBVIEW2_WEB_ERROR_STATUS_OPERATION_CANCELED, COREWEBVIEW2_WEB_RESOURCE_CONTEXT,
+    COREWEBVIEW2_WEB_RESOURCE_CONTEXT_ALL, COREWEBVIEW2_WEB_RESOURCE_CONTEXT_DOCUMENT,
+    COREWEBVIEW2_WEB_RESOURCE_CONTEXT_FETCH, COREWEBVIEW2_WEB_RESOURCE_CONTEXT_FONT,
+    COREWEBVIEW2_WEB_RESOURCE_CONTEXT_IMAGE, COREWEBVIEW2_WEB_RESOURCE_CONTEXT_MEDIA,
+    COREWEBVIEW2_WEB_RESOURCE_CONTEXT_SCRIPT, COREWEBVIEW2_WEB_RESOURCE_CONTEXT_STYLESHEET,
+    COREWEBVIEW2_WEB_RESOURCE_CONTEXT_XML_HTTP_REQUEST, ICoreWebView2, ICoreWebView2_10,
+    ICoreWebView2_11, ICoreWebView2_14, ICoreWebView2_2, ICoreWebView2_4, ICoreWebView2_8,
+    ICoreWebView2ContextMenuItem, ICoreWebView2ContextMenuItemCollection,
+    ICoreWebView2Environment, ICoreWebView2Environment9, ICoreWebView2HttpRequestHeaders,
+    ICoreWebView2HttpResponseHeaders, ICoreWebView2Settings5,
+    ICoreWebView2WebResourceRequestedEventArgs,
+};
+use webview2_com::{
+    BasicAuthenticationRequestedEventHandler, BytesReceivedChangedEventHandler,
+    CallDevToolsProtocolMethodCompletedHandler, ContainsFullScreenElementChangedEventHandler,
+    ContextMenuRequestedEventHandler, CustomItemSelectedEventHandler,
+    DevToolsProtocolEventReceivedEventHandler, DownloadStartingEventHandler,
+    FocusChangedEventHandler, NavigationCompletedEventHandler, NavigationStartingEventHandler,
+    PermissionRequestedEventHandler, ScriptDialogOpeningEventHandler,
+    ServerCertificateErrorDetectedEventHandler, WebResourceRequestedEventHandler,
+    WebResourceResponseReceivedEventHandler,
+};
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::System::Com::IStream;
+use windows::Win32::UI::HiDpi::GetDpiForWindow;
+use windows::Win32::UI::Input::KeyboardAndMouse::SetFocus;
+use windows::Win32::UI::Shell::{
+    DefSubclassProc, RemoveWindowSubclass, SHCreateMemStream, SetWindowSubclass,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetClientRect, GetWindowLongPtrW, SetWindowLongPtrW, SetWindowPos, GWL_EXSTYLE, HWND_BOTTOM,
+    HWND_TOP, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, WM_SIZE, WS_EX_TRANSPARENT,
+};
+use windows_core::{Interface, HSTRING, PWSTR};
+use wry::{WebView, WebViewBuilder, WebViewBuilderExtWindows, WebViewExtWindows};
+
+use crate::error::WebViewError;
+use crate::platform::{CancellationToken, CertificateChainCompletion};
+use crate::state::WebViewState;
+use crate::{
+    AuthAnswer, AutoplayPolicy, ConsoleLevel, ContextMenuDecision, ContextMenuItem, HttpHeader,
+    InterceptAction, InterceptedRequest, InterceptedResponse, PermissionResource,
+    PermissionResponse, ProxyConfig, ResourceType, SslWarningType, TlsDecision,
+};
+
+type UiTask = Box<dyn FnOnce() + Send>;
+
+/// The thread that created the first WebView; WebView2 only accepts calls from it.
+static UI_THREAD: OnceLock<ThreadId> = OnceLock::new();
+
+/// Closures posted from other threads, run by the next `pump_events` on the UI thread.
+static UI_TASKS: Mutex<VecDeque<UiTask>> = Mutex::new(VecDeque::new());
+
+/// Runs a closure on the thread that owns the WebViews.
+///
+/// Called from that thread, or before any WebView exists, the closure runs
+/// inline. Otherwise it is queued for the next `pump_events` on the UI thread,
+/// and the call fails with `MainThreadTimeout` if the pump does not start it in
+/// time; the closure is then skipped.
+pub fn run_on_main_thread<F, R>(f: F) -> Result<R, WebViewError>
+where
+    F: FnOnce() -> Result<R, WebViewError> + Send + 'static,
+    R: Send + 'static,
+{
+    run_on_main_thread_timeout(f, crate::platform::main_thread_timeout_ms())
+}
+
+/// Runs a closure on the thread that owns the WebViews, waiting at most
+/// `timeout_ms` (0 waits forever) for the pump to start it.
+pub fn run_on_main_thread_timeout<F, R>(f: F, timeout_ms: u64) -> Result<R, WebViewError>
+where
+    F: FnOnce() -> Result<R, WebViewError> + Send + 'static,
+    R: Send + 'static,
+{
+    match UI_THREAD.get() {
+        Some(ui_thread) if *ui_thread != thread::current().id() => {}
+        _ => return f(),
+    }
+    let (result_tx, result_rx) = mpsc::sync_channel(1);
+    let token = CancellationToken::new();
+    let task_token = token.clone();
+    UI_TASKS
+        .lock()
+        .map_err(|_| WebViewError::Internal("ui task queue poisoned".to_string()))?
+        .push_back(Box::new(move || {
+            if task_token.start() {
+                let _ = result_tx.send(f());
+            }
+        }));
+    crate::platform::wait_for_result(&result_rx, &token, timeout_ms, "ui thread")
+}
+
+/// Runs the closures posted by `run_on_main_thread` from other threads.
+fn run_ui_tasks() {
+    if UI_THREAD.get() != Some(&thread::current().id()) {
+        return;
+    }
+    loop {
+        // The lock is released before the task runs, so tasks may post more tasks.
+        let task = match UI_TASKS.lock() {
+            Ok(mut tasks) => tasks.pop_front(),
+            Err(_) => None,
+        };
+        let Some(task) = task else {
+            break;
+        };
+        task();
+    }
+}
+
+/// Number of messages handled by a `pump_events` call without an explicit limit.
+const DEFAULT_PUMP_MESSAGE_LIMIT: u32 = 64;
+
+static PUMP_MESSAGE_LIMIT: AtomicU32 = AtomicU32::new(DEFAULT_PUMP_MESSAGE_LIMIT);
+
+/// Sets the number of messages handled by `pump_events(None)`.
+pub fn set_pump_message_limit(limit: u32) {
+    PUMP_MESSAGE_LIMIT.store(limit.max(1), Ordering::Relaxed);
+}
+
+/// Pumps the Windows message queue, handling at most `max_messages` messages
+/// (the limit set with `set_pump_message_limit` when `None`).
+///
+/// The pump runs inside the host's frame loop. Draining the queue until it is
+/// empty never returns while WebView2 keeps posting messages (resizes, input,
+/// timers), which starves the host's layout and composition of that frame;
+/// whatever is left is handled on the next tick.
+///
+/// Closures posted by `run_on_main_thread` from other threads run first.
+pub fn pump_events(max_messages: Option<u32>) {
     use windows::Win32::UI::WindowsAndMessaging::{
         DispatchMessageW, PeekMessageW, TranslateMessage, MSG, PM_REMOVE,
     };
 
+    run_ui_tasks();
+    let limit = max_messages.unwrap_or_else(|| PUMP_MESSAGE_LIMIT.load(Ordering::Relaxed));
     unsafe {
         let mut msg: MSG = std::mem::zeroed();
-        while PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool() {
+        for _ in 0..limit {
+            if !PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool() {
+                break;
+            }
             TranslateMessage(&msg);
             DispatchMessageW(&msg);
         }
     }
 }
+
+fn webview2_error(error: windows_core::Error) -> WebViewError {
+    WebViewError::Internal(format!("webview2 error: {}", error))
+}
+
+/// Connects the WebView2 events backing the handlers stored in `WebViewState`.
+pub fn install_handlers(webview: &WebView, state: &Arc<WebViewState>) -> Result<(), WebViewError> {
+    UI_THREAD.get_or_init(|| thread::current().id());
+    let core = unsafe { webview.controller().CoreWebView2() }.map_err(webview2_error)?;
+    install_request_header_filter(&core, Arc::clone(state))?;
+    install_request_interceptor(&core, Arc::clone(state))?;
+    install_permission_handler(&core, Arc::clone(state))?;
+    install_download_progress(&core, Arc::clone(state))?;
+    install_js_dialog_handler(&core, Arc::clone(state))?;
+    install_auth_challenge_handler(&core, Arc::clone(state))?;
+    install_certificate_error_handler(&core, Arc::clone(state))?;
+    install_main_response_observer(&core, Arc::clone(state))?;
+    install_error_page_observer(&core, Arc::clone(state))?;
+    install_fullscreen_handler(&core, Arc::clone(state))?;
+    install_context_menu_handler(webview, &core, Arc::clone(state))?;
+    install_console_observer(&core, Arc::clone(state))?;
+    install_focus_observer(webview, Arc::clone(state))?;
+    Ok(())
+}
+
+/// Adds the headers stored in `WebViewState` to every request issued by the WebView.
+fn install_request_header_filter(
+    core: &ICoreWebView2,
+    state: Arc<WebViewState>,
+) -> Result<(), WebViewError> {
+    unsafe {
+        core.AddWebResourceRequestedFilter(&HSTRING::from("*"), COREWEBVIEW2_WEB_RESOURCE_CONTEXT_ALL)
+            .map_err(webview2_error)?;
+
+        let mut token = 0;
+        core.add_WebResourceRequested(
+            &WebResourceRequestedEventHandler::create(Box::new(move |_, args| {
+                let Some(args) = args else {
+                    return Ok(());
+                };
+                let headers = state.request_headers().unwrap_or_default();
+                if headers.is_empty() {
+                    return Ok(());
+                }
+                let request_headers = args.Request()?.Headers()?;
+                for (name, value) in headers {
+                    request_headers.SetHeader(&HSTRING::from(name), &HSTRING::from(value))?;
+                }
+                Ok(())
+            })),
+            &mut token,
+        )
+        .map_err(webview2_error)?;
+    }
+    Ok(())
+}
+
+fn resource_type_from(context: COREWEBVIEW2_WEB_RESOURCE_CONTEXT) -> ResourceType {
+    match context {
+        COREWEBVIEW2_WEB_RESOURCE_CONTEXT_DOCUMENT => ResourceType::Document,
+        COREWEBVIEW2_WEB_RESOURCE_CONTEXT_STYLESHEET => ResourceType::Stylesheet,
+        COREWEBVIEW2_WEB_RESOURCE_CONTEXT_IMAGE => ResourceType::Image,
+        COREWEBVIEW2_WEB_RESOURCE_CONTEXT_MEDIA => ResourceType::Media,
+        COREWEBVIEW2_WEB_RESOURCE_CONTEXT_FONT => ResourceType::Font,
+        COREWEBVIEW2_WEB_RESOURCE_CONTEXT_SCRIPT => ResourceType::Script,
+        COREWEBVIEW2_WEB_RESOURCE_CONTEXT_XML_HTTP_REQUEST => ResourceType::XmlHttpRequest,
+        COREWEBVIEW2_WEB_RESOURCE_CONTEXT_FETCH => ResourceType::Fetch,
+        _ => ResourceType::Other,
+    }
+}
+
+fn request_headers(
+    headers: &ICoreWebView2HttpRequestHeaders,
+) -> windows_core::Result<Vec<HttpHeader>> {
+    let mut result = Vec::new();
+    unsafe {
+        let iterator = headers.GetIterator()?;
+        let mut has_current = Default::default();
+        iterator.HasCurrentHeader(&mut has_current)?;
+        while has_current.as_bool() {
+            let (mut name, mut value) = (PWSTR::null(), PWSTR::null());
+            iterator.GetCurrentHeader(&mut name, &mut value)?;
+            result.push(HttpHeader {
+                name: webview2_com::take_pwstr(name),
+                value: webview2_com::take_pwstr(value),
+            });
+            iterator.MoveNext(&mut has_current)?;
+        }
+    }
+    Ok(result)
+}
+
+/// Reads a request body to the end.
+fn read_stream(stream: &IStream) -> Vec<u8> {
+    let mut body = Vec::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let mut read = 0u32;
+        let result = unsafe {
+            stream.Read(
+                buffer.as_mut_ptr() as *mut c_void,
+                buffer.len() as u32,
+                Some(&mut read as *mut u32),
+            )
+        };
+        if result.is_err() || read == 0 {
+            return body;
+        }
+        body.extend_from_slice(&buffer[..read as usize]);
+    }
+}
+
+/// Answers a request with `status`, `headers` and `body` instead of the network.
+fn respond(
+    environment: &ICoreWebView2Environment,
+    args: &ICoreWebView2WebResourceRequestedEventArgs,
+    status: i32,
+    reason: &str,
+    headers: &[HttpHeader],
+    body: Option<&[u8]>,
+) -> windows_core::Result<()> {
+    let headers: Vec<String> =
+        headers.iter().map(|header| format!("{}: {}", header.name, header.value)).collect();
+    unsafe {
+        let content = body.and_then(|body| SHCreateMemStream(Some(body)));
+        let response = environment.CreateWebResourceResponse(
+            content.as_ref(),
+            status,
+            &HSTRING::from(reason),
+            &HSTRING::from(headers.join("\r\n")),
+        )?;
+        args.SetResponse(&response)
+    }
+}
+
+/// Carries out the decision of the `RequestInterceptor` for one request.
+fn apply_intercepted_response(
+    environment: &ICoreWebView2Environment,
+    args: &ICoreWebView2WebResourceRequestedEventArgs,
+    response: InterceptedResponse,
+) -> windows_core::Result<()> {
+    match response.action {
+        InterceptAction::Allow => {
+            let Some(headers) = response.modified_headers else {
+                return Ok(());
+            };
+            let request_headers = unsafe { args.Request()?.Headers()? };
+            for header in headers {
+                unsafe {
+                    request_headers
+                        .SetHeader(&HSTRING::from(header.name), &HSTRING::from(header.value))?;
+                }
+            }
+            Ok(())
+        }
+        InterceptAction::Block => respond(environment, args, 403, "Blocked", &[], None),
+        InterceptAction::Redirect => match response.modified_url {
+            Some(url) => {
+                let location = HttpHeader { name: "Location".to_string(), value: url };
+                respond(environment, args, 302, "Found", &[location], None)
+            }
+            None => {
+                eprintln!("[wrywebview] intercept redirect without url, blocked");
+                respond(environment, args, 403, "Blocked", &[], None)
+            }
+        },
+        InterceptAction::ModifyResponse => {
+            let headers = response.modified_headers.unwrap_or_default();
+            let body = response.injected_body.unwrap_or_default();
+            respond(environment, args, 200, "OK", &headers, Some(&body))
+        }
+    }
+}
+
+/// Passes the requests matching the filter of the `RequestInterceptor` to it.
+///
+/// The `*` filter added by `install_request_header_filter` already routes every
+/// request of the WebView here.
+fn install_request_interceptor(
+    core: &ICoreWebView2,
+    state: Arc<WebViewState>,
+) -> Result<(), WebViewError> {
+    let core_2: ICoreWebView2_2 = core.cast().map_err(webview2_error)?;
+    let environment = unsafe { core_2.Environment() }.map_err(webview2_error)?;
+    let mut token = 0;
+    unsafe {
+        core.add_WebResourceRequested(
+            &WebResourceRequestedEventHandler::create(Box::new(move |_, args| {
+                let Some(args) = args else {
+                    return Ok(());
+                };
+                let request = args.Request()?;
+                let mut uri = PWSTR::null();
+                request.Uri(&mut uri)?;
+                let url = webview2_com::take_pwstr(uri);
+                let mut context = COREWEBVIEW2_WEB_RESOURCE_CONTEXT::default();
+                args.ResourceContext(&mut context)?;
+                let resource_type = resource_type_from(context);
+                // Checked first, so requests nobody intercepts skip reading the headers and body.
+                if state.request_interceptor(&url, resource_type).is_none() {
+                    return Ok(());
+                }
+                let mut method = PWSTR::null();
+                request.Method(&mut method)?;
+                let intercepted = InterceptedRequest {
+                    url,
+                    method: webview2_com::take_pwstr(method),
+                    headers: request_headers(&request.Headers()?)?,
+                    body: request.Content().ok().map(|stream| read_stream(&stream)),
+                    resource_type,
+                };
+                let Some(response) = crate::intercept_request(&state, intercepted) else {
+                    return Ok(());
+                };
+                apply_intercepted_response(&environment, &args, response)
+            })),
+            &mut token,
+        )
+        .map_err(webview2_error)?;
+    }
+    Ok(())
+}
+
+fn permission_resource_from(kind: COREWEBVIEW2_PERMISSION_KIND) -> Option<PermissionResource> {
+    match kind {
+        COREWEBVIEW2_PERMISSION_KIND_CAMERA => Some(PermissionResource::Camera),
+        COREWEBVIEW2_PERMISSION_KIND_MICROPHONE => Some(PermissionResource::Microphone),
+        COREWEBVIEW2_PERMISSION_KIND_GEOLOCATION => Some(PermissionResource::Geolocation),
+        COREWEBVIEW2_PERMISSION_KIND_NOTIFICATIONS => Some(PermissionResource::Notifications),
+        COREWEBVIEW2_PERMISSION_KIND_CLIPBOARD_READ => Some(PermissionResource::ClipboardRead),
+        _ => None,
+    }
+}
+
+/// Forwards WebView2 permission requests to the registered `PermissionHandler`.
+fn install_permission_handler(
+    core: &ICoreWebView2,
+    state: Arc<WebViewState>,
+) -> Result<(), WebViewError> {
+    let mut token = 0;
+    unsafe {
+        core.add_PermissionRequested(
+            &PermissionRequestedEventHandler::create(Box::new(move |_, args| {
+                let Some(args) = args else {
+                    return Ok(());
+                };
+                let Some(handler) = state.permission_handler.get() else {
+                    return Ok(());
+                };
+                let mut kind = COREWEBVIEW2_PERMISSION_KIND::default();
+                args.PermissionKind(&mut kind)?;
+                let Some(resource) = permission_resource_from(kind) else {
+                    return Ok(());
+                };
+                match handler.on_request(state.id(), resource) {
+                    PermissionResponse::Allow => args.SetState(COREWEBVIEW2_PERMISSION_STATE_ALLOW)?,
+                    PermissionResponse::Deny => args.SetState(COREWEBVIEW2_PERMISSION_STATE_DENY)?,
+                    PermissionResponse::AskUser => {}
+                }
+                Ok(())
+            })),
+            &mut token,
+        )
+        .map_err(webview2_error)?;
+    }
+    Ok(())
+}
+
+/// Reports the progress of downloads to the `DownloadHandler`.
+fn install_download_progress(
+    core: &ICoreWebView2,
+    state: Arc<WebViewState>,
+) -> Result<(), WebViewError> {
+    let core: ICoreWebView2_4 = core.cast().map_err(webview2_error)?;
+    let mut token = 0;
+    unsafe {
+        core.add_DownloadStarting(
+            &DownloadStartingEventHandler::create(Box::new(move |_, args| {
+                let Some(args) = args else {
+                    return Ok(());
+                };
+                let operation = args.DownloadOperation()?;
+                let state = Arc::clone(&state);
+                let mut progress_token = 0;
+                operation.add_BytesReceivedChanged(
+                    &BytesReceivedChangedEventHandler::create(Box::new(move |operation, _| {
+                        let Some(operation) = operation else {
+                            return Ok(());
+                        };
+                        let Some(handler) = state.download_handler.get() else {
+                            return Ok(());
+                        };
+                        let mut received = 0i64;
+                        operation.BytesReceived(&mut received)?;
+                        let mut total = 0i64;
+                        operation.TotalBytesToReceive(&mut total)?;
+                        let total = (total > 0).then_some(total as u64);
+                        handler.on_progress(state.id(), received.max(0) as u64, total);
+                        Ok(())
+                    })),
+                    &mut progress_token,
+                )?;
+                Ok(())
+            })),
+            &mut token,
+        )
+        .map_err(webview2_error)?;
+    }
+    Ok(())
+}
+
+/// Forwards `ScriptDialogOpening` events to the registered `JsDialogHandler`.
+fn install_js_dialog_handler(
+    core: &ICoreWebView2,
+    state: Arc<WebViewState>,
+) -> Result<(), WebViewError> {
+    let mut token = 0;
+    unsafe {
+        core.add_ScriptDialogOpening(
+            &ScriptDialogOpeningEventHandler::create(Box::new(move |_, args| {
+                let Some(args) = args else {
+                    return Ok(());
+                };
+                let Some(handler) = state.js_dialog_handler.get() else {
+                    return Ok(());
+                };
+                let mut kind = COREWEBVIEW2_SCRIPT_DIALOG_KIND::default();
+                args.Kind(&mut kind)?;
+                let mut message = PWSTR::null();
+                args.Message(&mut message)?;
+                let message = webview2_com::take_pwstr(message);
+                match kind {
+                    COREWEBVIEW2_SCRIPT_DIALOG_KIND_ALERT => {
+                        handler.on_alert(state.id(), message);
+                        args.Accept()?;
+                    }
+                    COREWEBVIEW2_SCRIPT_DIALOG_KIND_CONFIRM
+                    | COREWEBVIEW2_SCRIPT_DIALOG_KIND_BEFOREUNLOAD => {
+                        if handler.on_confirm(state.id(), message) {
+                            args.Accept()?;
+                        }
+                    }
+                    COREWEBVIEW2_SCRIPT_DIALOG_KIND_PROMPT => {
+                        let mut default_value = PWSTR::null();
+                        args.DefaultText(&mut default_value)?;
+                        let default_value = webview2_com::take_pwstr(default_value);
+                        if let Some(text) = handler.on_prompt(state.id(), message, default_value) {
+                            args.SetResultText(&HSTRING::from(text))?;
+                            args.Accept()?;
+                        }
+                    }
+                    _ => {}
+                }
+                Ok(())
+            })),
+            &mut token,
+        )
+        .map_err(webview2_error)?;
+    }
+    Ok(())
+}
+
+/// Extracts the realm of a `WWW-Authenticate` challenge such as `Basic realm="Intranet"`.
+fn realm_from_challenge(challenge: &str) -> String {
+    let Some(start) = challenge.to_ascii_lowercase().find("realm=") else {
+        return String::new();
+    };
+    let value = &challenge[start + "realm=".len()..];
+    match value.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next().unwrap_or_default().to_string(),
+        None => value.split(',').next().unwrap_or_default().trim().to_string(),
+    }
+}
+
+/// Forwards `BasicAuthenticationRequested` events to the registered `AuthChallengeHandler`.
+fn install_auth_challenge_handler(
+    core: &ICoreWebView2,
+    state: Arc<WebViewState>,
+) -> Result<(), WebViewError> {
+    let core: ICoreWebView2_10 = core.cast().map_err(webview2_error)?;
+    let mut token = 0;
+    unsafe {
+        core.add_BasicAuthenticationRequested(
+            &BasicAuthenticationRequestedEventHandler::create(Box::new(move |_, args| {
+                let Some(args) = args else {
+                    return Ok(());
+                };
+                let mut uri = PWSTR::null();
+                args.Uri(&mut uri)?;
+                let uri = webview2_com::take_pwstr(uri);
+                let host = uri
+                    .parse::<wry::http::Uri>()
+                    .ok()
+                    .and_then(|uri| uri.host().map(str::to_string))
+                    .unwrap_or_default();
+                let mut challenge = PWSTR::null();
+                args.Challenge(&mut challenge)?;
+                let realm = realm_from_challenge(&webview2_com::take_pwstr(challenge));
+
+                // WebView2 does not tell proxy challenges apart from server ones.
+                match crate::answer_auth_challenge(&state, host, realm, false) {
+                    AuthAnswer::Default => {}
+                    AuthAnswer::Cancel => args.SetCancel(true)?,
+                    AuthAnswer::Respond(credentials) => {
+                        let response = args.Response()?;
+                        response.SetUserName(&HSTRING::from(credentials.username))?;
+                        response.SetPassword(&HSTRING::from(credentials.password))?;
+                    }
+                }
+                Ok(())
+            })),
+            &mut token,
+        )
+        .map_err(webview2_error)?;
+    }
+    Ok(())
+}
+
+fn certificate_error_description(status: COREWEBVIEW2_WEB_ERROR_STATUS) -> String {
+    let description = match status {
+        COREWEBVIEW2_WEB_ERROR_STATUS_CERTIFICATE_COMMON_NAME_IS_INCORRECT => {
+            "certificate common name does not match the host"
+        }
+        COREWEBVIEW2_WEB_ERROR_STATUS_CERTIFICATE_EXPIRED => "certificate has expired",
+        COREWEBVIEW2_WEB_ERROR_STATUS_CLIENT_CERTIFICATE_CONTAINS_ERRORS => {
+            "client certificate contains errors"
+        }
+        COREWEBVIEW2_WEB_ERROR_STATUS_CERTIFICATE_REVOKED => "certificate has been revoked",
+        COREWEBVIEW2_WEB_ERROR_STATUS_CERTIFICATE_IS_INVALID => "certificate is invalid",
+        other => return format!("certificate error (status {})", other.0),
+    };
+    description.to_string()
+}
+
+/// Reports certificate errors, including those of subresources, as SSL warnings
+/// and lets the `TlsErrorHandler` allow them.
+///
+/// Denied errors keep WebView2's default action, which cancels the request.
+fn install_certificate_error_handler(
+    core: &ICoreWebView2,
+    state: Arc<WebViewState>,
+) -> Result<(), WebViewError> {
+    let core: ICoreWebView2_14 = core.cast().map_err(webview2_error)?;
+    let mut token = 0;
+    unsafe {
+        core.add_ServerCertificateErrorDetected(
+            &ServerCertificateErrorDetectedEventHandler::create(Box::new(move |_, args| {
+                let Some(args) = args else {
+                    return Ok(());
+                };
+                let mut uri = PWSTR::null();
+                args.RequestUri(&mut uri)?;
+                let url = webview2_com::take_pwstr(uri);
+                crate::report_ssl_warning(&state, url.clone(), SslWarningType::InvalidCertificate);
+
+                let mut status = COREWEBVIEW2_WEB_ERROR_STATUS::default();
+                args.ErrorStatus(&mut status)?;
+                let description = certificate_error_description(status);
+                if crate::decide_tls_error(&state, url, description) == TlsDecision::Allow {
+                    args.SetAction(COREWEBVIEW2_SERVER_CERTIFICATE_ERROR_ACTION_ALWAYS_ALLOW)?;
+                }
+                Ok(())
+            })),
+            &mut token,
+        )
+        .map_err(webview2_error)?;
+    }
+    Ok(())
+}
+
+/// Reports the failed navigations that WebView2 replaces with its error page.
+fn install_error_page_observer(
+    core: &ICoreWebView2,
+    state: Arc<WebViewState>,
+) -> Result<(), WebViewError> {
+    let mut token = 0;
+    unsafe {
+        core.add_NavigationCompleted(
+            &NavigationCompletedEventHandler::create(Box::new(move |sender, args| {
+                let (Some(sender), Some(args)) = (sender, args) else {
+                    return Ok(());
+                };
+                let mut success = Default::default();
+                args.IsSuccess(&mut success)?;
+                if success.as_bool() {
+                    return Ok(());
+                }
+                let mut status = COREWEBVIEW2_WEB_ERROR_STATUS::default();
+                args.WebErrorStatus(&mut status)?;
+                if status == COREWEBVIEW2_WEB_ERROR_STATUS_OPERATION_CANCELED {
+                    return Ok(());
+                }
+                let mut uri = PWSTR::null();
+                sender.Source(&mut uri)?;
+                crate::report_error_page(&state, webview2_com::take_pwstr(uri), status.0);
+                Ok(())
+            })),
+            &mut token,
+        )
+        .map_err(webview2_error)?;
+    }
+    Ok(())
+}
+
+/// Forwards `ContainsFullScreenElementChanged` events to the registered `FullscreenHandler`.
+fn install_fullscreen_handler(
+    core: &ICoreWebView2,
+    state: Arc<WebViewState>,
+) -> Result<(), WebViewError> {
+    let mut token = 0;
+    unsafe {
+        core.add_ContainsFullScreenElementChanged(
+            &ContainsFullScreenElementChangedEventHandler::create(Box::new(move |sender, _| {
+                let (Some(sender), Some(handler)) = (sender, state.fullscreen_handler.get())
+                else {
+                    return Ok(());
+                };
+                let mut fullscreen = Default::default();
+                sender.ContainsFullScreenElement(&mut fullscreen)?;
+                if fullscreen.as_bool() {
+                    handler.on_enter_fullscreen(state.id());
+                } else {
+                    handler.on_exit_fullscreen(state.id());
+                }
+                Ok(())
+            })),
+            &mut token,
+        )
+        .map_err(webview2_error)?;
+    }
+    Ok(())
+}
+
+/// Converts a `Runtime.consoleAPICalled` event into a console message.
+fn console_message(parameters: &str) -> Option<(ConsoleLevel, String, String, u32)> {
+    let event: serde_json::Value = serde_json::from_str(parameters).ok()?;
+    let level = match event["type"].as_str()? {
+        "log" | "debug" => ConsoleLevel::Log,
+        "info" => ConsoleLevel::Info,
+        "warning" => ConsoleLevel::Warn,
+        "error" | "assert" => ConsoleLevel::Error,
+        _ => return None,
+    };
+    let message = event["args"]
+        .as_array()
+        .map(|args| {
+            args.iter()
+                .map(|arg| match &arg["value"] {
+                    serde_json::Value::String(value) => value.clone(),
+                    serde_json::Value::Null => arg["description"]
+                        .as_str()
+                        .or_else(|| arg["type"].as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    value => value.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_default();
+    let frame = &event["stackTrace"]["callFrames"][0];
+    let source = frame["url"].as_str().unwrap_or_default().to_string();
+    // DevTools line numbers are zero-based.
+    let line = frame["lineNumber"].as_u64().map_or(0, |line| line as u32 + 1);
+    Some((level, message, source, line))
+}
+
+/// Keeps `WebViewState::has_focus` in sync with the focus of the WebView2 controller.
+fn install_focus_observer(webview: &WebView, state: Arc<WebViewState>) -> Result<(), WebViewError> {
+    let controller = webview.controller();
+    let state_for_lost = Arc::clone(&state);
+    let mut token = 0;
+    unsafe {
+        controller
+            .add_GotFocus(
+                &FocusChangedEventHandler::create(Box::new(move |_, _| {
+                    state.has_focus.store(true, Ordering::Release);
+                    Ok(())
+                })),
+                &mut token,
+            )
+            .map_err(webview2_error)?;
+        controller
+            .add_LostFocus(
+                &FocusChangedEventHandler::create(Box::new(move |_, _| {
+                    state_for_lost.has_focus.store(false, Ordering::Release);
+                    Ok(())
+                })),
+                &mut token,
+            )
+            .map_err(webview2_error)?;
+    }
+    Ok(())
+}
+
+/// Reports console calls to the `ConsoleHandler` through the DevTools
+/// `Runtime.consoleAPICalled` event, which also covers calls made during loads.
+fn install_console_observer(
+    core: &ICoreWebView2,
+    state: Arc<WebViewState>,
+) -> Result<(), WebViewError> {
+    let mut token = 0;
+    unsafe {
+        let receiver = core
+            .GetDevToolsProtocolEventReceiver(&HSTRING::from("Runtime.consoleAPICalled"))
+            .map_err(webview2_error)?;
+        receiver
+            .add_DevToolsProtocolEventReceived(
+                &DevToolsProtocolEventReceivedEventHandler::create(Box::new(move |_, args| {
+                    let Some(args) = args else {
+                        return Ok(());
+                    };
+                    if !state.console_handler.is_set() {
+                        return Ok(());
+                    }
+                    let mut parameters = PWSTR::null();
+                    args.ParameterObjectAsJson(&mut parameters)?;
+                    let parameters = webview2_com::take_pwstr(parameters);
+                    if let Some((level, message, source, line)) = console_message(&parameters) {
+                        crate::report_console_message(&state, level, message, source, line);
+                    }
+                    Ok(())
+                })),
+                &mut token,
+            )
+            .map_err(webview2_error)?;
+        core.CallDevToolsProtocolMethod(
+            &HSTRING::from("Runtime.enable"),
+            &HSTRING::from("{}"),
+            &CallDevToolsProtocolMethodCompletedHandler::create(Box::new(|error, _result| {
+                if let Err(e) = error {
+                    eprintln!("[wrywebview] Runtime.enable failed: {}", e);
+                }
+                Ok(())
+            })),
+        )
+        .map_err(webview2_error)?;
+    }
+    Ok(())
+}
+
+/// Returns the items of a context menu, without separators.
+fn context_menu_items(
+    collection: &ICoreWebView2ContextMenuItemCollection,
+) -> windows_core::Result<Vec<ContextMenuItem>> {
+    let mut items = Vec::new();
+    unsafe {
+        let mut count = 0;
+        collection.Count(&mut count)?;
+        for index in 0..count {
+            let item = collection.GetValueAtIndex(index)?;
+            let mut kind = COREWEBVIEW2_CONTEXT_MENU_ITEM_KIND::default();
+            item.Kind(&mut kind)?;
+            if kind == COREWEBVIEW2_CONTEXT_MENU_ITEM_KIND_SEPARATOR {
+                continue;
+            }
+            let (mut label, mut name) = (PWSTR::null(), PWSTR::null());
+            item.Label(&mut label)?;
+            item.Name(&mut name)?;
+            let mut enabled = Default::default();
+            item.IsEnabled(&mut enabled)?;
+            items.push(ContextMenuItem {
+                title: webview2_com::take_pwstr(label),
+                action_id: webview2_com::take_pwstr(name),
+                enabled: enabled.as_bool(),
+            });
+        }
+    }
+    Ok(items)
+}
+
+/// Creates a custom context menu item reporting its selection to the handler.
+fn custom_context_menu_item(
+    environment: &ICoreWebView2Environment9,
+    item: ContextMenuItem,
+    state: Arc<WebViewState>,
+) -> windows_core::Result<ICoreWebView2ContextMenuItem> {
+    unsafe {
+        let menu_item = environment.CreateContextMenuItem(
+            &HSTRING::from(item.title),
+            None,
+            COREWEBVIEW2_CONTEXT_MENU_ITEM_KIND_COMMAND,
+        )?;
+        menu_item.SetIsEnabled(item.enabled)?;
+        let action_id = item.action_id;
+        let mut token = 0;
+        menu_item.add_CustomItemSelected(
+            &CustomItemSelectedEventHandler::create(Box::new(move |_, _| {
+                crate::context_menu_item_selected(&state, action_id.clone());
+                Ok(())
+            })),
+            &mut token,
+        )?;
+        Ok(menu_item)
+    }
+}
+
+/// Forwards `ContextMenuRequested` events to the registered `ContextMenuHandler`.
+fn install_context_menu_handler(
+    webview: &WebView,
+    core: &ICoreWebView2,
+    state: Arc<WebViewState>,
+) -> Result<(), WebViewError> {
+    let core: ICoreWebView2_11 = core.cast().map_err(webview2_error)?;
+    let environment: ICoreWebView2Environment9 =
+        webview.environment().cast().map_err(webview2_error)?;
+    let mut token = 0;
+    unsafe {
+        core.add_ContextMenuRequested(
+            &ContextMenuRequestedEventHandler::create(Box::new(move |_, args| {
+                let Some(args) = args else {
+                    return Ok(());
+                };
+                if !state.context_menu_handler.is_set() {
+                    return Ok(());
+                }
+                let mut location = Default::default();
+                args.Location(&mut location)?;
+                let collection = args.MenuItems()?;
+                let items = context_menu_items(&collection)?;
+                match crate::decide_context_menu(&state, location.x, location.y, items) {
+                    ContextMenuDecision::ShowDefault => {}
+                    ContextMenuDecision::Suppress => args.SetHandled(true)?,
+                    ContextMenuDecision::ShowCustom(items) => {
+                        let mut count = 0;
+                        collection.Count(&mut count)?;
+                        for _ in 0..count {
+                            collection.RemoveValueAtIndex(0)?;
+                        }
+                        for (index, item) in items.into_iter().enumerate() {
+                            let menu_item =
+                                custom_context_menu_item(&environment, item, Arc::clone(&state))?;
+                            collection.InsertValueAtIndex(index as u32, &menu_item)?;
+                        }
+                    }
+                }
+                Ok(())
+            })),
+            &mut token,
+        )
+        .map_err(webview2_error)?;
+    }
+    Ok(())
+}
+
+fn response_headers(
+    headers: &ICoreWebView2HttpResponseHeaders,
+) -> windows_core::Result<Vec<(String, String)>> {
+    let mut result = Vec::new();
+    unsafe {
+        let iterator = headers.GetIterator()?;
+        let mut has_current = Default::default();
+        iterator.HasCurrentHeader(&mut has_current)?;
+        while has_current.as_bool() {
+            let (mut name, mut value) = (PWSTR::null(), PWSTR::null());
+            iterator.GetCurrentHeader(&mut name, &mut value)?;
+            result.push((webview2_com::take_pwstr(name), webview2_com::take_pwstr(value)));
+            iterator.MoveNext(&mut has_current)?;
+        }
+    }
+    Ok(result)
+}
+
+/// Caches the response of the main document.
+///
+/// `WebResourceResponseReceived` covers every resource, so responses are matched
+/// against the URI of the last `NavigationStarting` event (raised again for redirects).
+fn install_main_response_observer(
+    core: &ICoreWebView2,
+    state: Arc<WebViewState>,
+) -> Result<(), WebViewError> {
+    let navigation_uri = Arc::new(Mutex::new(String::new()));
+    let navigation_uri_for_response = Arc::clone(&navigation_uri);
+    let core_2: ICoreWebView2_2 = core.cast().map_err(webview2_error)?;
+    let mut token = 0;
+    unsafe {
+        core.add_NavigationStarting(
+            &NavigationStartingEventHandler::create(Box::new(move |_, args| {
+                let Some(args) = args else {
+                    return Ok(());
+                };
+                let mut uri = PWSTR::null();
+                args.Uri(&mut uri)?;
+                if let Ok(mut navigation_uri) = navigation_uri.lock() {
+                    *navigation_uri = webview2_com::take_pwstr(uri);
+                }
+                Ok(())
+            })),
+            &mut token,
+        )
+        .map_err(webview2_error)?;
+
+        core_2
+            .add_WebResourceResponseReceived(
+                &WebResourceResponseReceivedEventHandler::create(Box::new(move |_, args| {
+                    let Some(args) = args else {
+                        return Ok(());
+                    };
+                    let mut uri = PWSTR::null();
+                    args.Request()?.Uri(&mut uri)?;
+                    let uri = webview2_com::take_pwstr(uri);
+                    let is_main_document = navigation_uri_for_response
+                        .lock()
+                        .map(|navigation_uri| *navigation_uri == uri)
+                        .unwrap_or(false);
+                    if !is_main_document {
+                        return Ok(());
+                    }
+                    let response = args.Response()?;
+                    let mut status = 0;
+                    response.StatusCode(&mut status)?;
+                    let headers = response_headers(&response.Headers()?)?;
+                    crate::record_main_response(&state, status as u16, headers);
+                    Ok(())
+                })),
+                &mut token,
+            )
+            .map_err(webview2_error)?;
+    }
+    Ok(())
+}
+
+/// WebView2 reports certificate errors as they happen and has no mixed content
+/// event, so there is nothing left to check once the page has loaded.
+pub fn page_security_warnings(_webview: &WebView) -> Vec<SslWarningType> {
+    Vec::new()
+}
+
+/// Disables the default WebView2 dialogs so `ScriptDialogOpening` is raised.
+pub fn set_script_dialogs_intercepted(
+    webview: &WebView,
+    intercepted: bool,
+) -> Result<(), WebViewError> {
+    unsafe {
+        let core = webview.controller().CoreWebView2().map_err(webview2_error)?;
+        core.Settings()
+            .and_then(|settings| settings.SetAreDefaultScriptDialogsEnabled(!intercepted))
+            .map_err(webview2_error)
+    }
+}
+
+pub fn set_pinch_zoom_enabled(webview: &WebView, enabled: bool) -> Result<(), WebViewError> {
+    unsafe {
+        let core = webview.controller().CoreWebView2().map_err(webview2_error)?;
+        let settings: ICoreWebView2Settings5 = core
+            .Settings()
+            .and_then(|settings| settings.cast())
+            .map_err(webview2_error)?;
+        settings.SetIsPinchZoomEnabled(enabled).map_err(webview2_error)
+    }
+}
+
+pub fn set_javascript_enabled(webview: &WebView, enabled: bool) -> Result<(), WebViewError> {
+    unsafe {
+        let core = webview.controller().CoreWebView2().map_err(webview2_error)?;
+        core.Settings()
+            .and_then(|settings| settings.SetIsScriptEnabled(enabled))
+            .map_err(webview2_error)
+    }
+}
+
+fn hwnd_from(parent_handle: u64) -> HWND {
+    HWND(parent_handle as isize as *mut c_void)
+}
+
+/// Converts physical pixels of `hwnd` to logical pixels.
+fn to_logical(hwnd: HWND, width: i32, height: i32) -> (i32, i32) {
+    let dpi = unsafe { GetDpiForWindow(hwnd) };
+    if dpi == 0 {
+        return (width, height);
+    }
+    let scale = dpi as f64 / 96.0;
+    (
+        (width as f64 / scale).round() as i32,
+        (height as f64 / scale).round() as i32,
+    )
+}
+
+/// Returns the logical size of the parent window's client area.
+pub fn parent_size(_webview: &WebView, parent_handle: u64) -> Option<(i32, i32)> {
+    let hwnd = hwnd_from(parent_handle);
+    let mut rect = RECT::default();
+    unsafe { GetClientRect(hwnd, &mut rect) }.ok()?;
+    Some(to_logical(hwnd, rect.right - rect.left, rect.bottom - rect.top))
+}
+
+/// Moves the WebView into another parent window.
+pub fn move_to_parent(
+    webview: &WebView,
+    _old_parent_handle: u64,
+    new_parent_handle: u64,
+) -> Result<(), WebViewError> {
+    webview
+        .reparent(new_parent_handle as isize)
+        .map_err(WebViewError::from)
+}
+
+/// Places the WebView above, or below, the other child windows of its parent.
+pub fn restack(webview: &WebView, _parent_handle: u64, to_front: bool) -> Result<(), WebViewError> {
+    let insert_after = if to_front { HWND_TOP } else { HWND_BOTTOM };
+    unsafe {
+        // The controller is hosted in wry's container window, a child of the parent.
+        let container = webview
+            .controller()
+            .ParentWindow()
+            .map_err(webview2_error)?;
+        SetWindowPos(
+            container,
+            Some(insert_after),
+            0,
+            0,
+            0,
+            0,
+            SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+        )
+        .map_err(|e| WebViewError::Internal(format!("SetWindowPos failed: {}", e)))
+    }
+}
+
+/// Sets `WS_EX_TRANSPARENT` on wry's container window so hit-testing skips it.
+pub fn set_pointer_passthrough(webview: &WebView, passthrough: bool) -> Result<(), WebViewError> {
+    unsafe {
+        let container = webview
+            .controller()
+            .ParentWindow()
+            .map_err(webview2_error)?;
+        let style = GetWindowLongPtrW(container, GWL_EXSTYLE);
+        let style = if passthrough {
+            style | WS_EX_TRANSPARENT.0 as isize
+        } else {
+            style & !(WS_EX_TRANSPARENT.0 as isize)
+        };
+        SetWindowLongPtrW(container, GWL_EXSTYLE, style);
+    }
+    Ok(())
+}
+
+unsafe extern "system" fn auto_resize_subclass_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    subclass_id: usize,
+    _ref_data: usize,
+) -> LRESULT {
+    if msg == WM_SIZE {
+        let width = (lparam.0 & 0xffff) as i32;
+        let height = ((lparam.0 >> 16) & 0xffff) as i32;
+        let (width, height) = to_logical(hwnd, width, height);
+        if let Err(e) = crate::resize_to_parent(subclass_id as u64, width, height) {
+            eprintln!("[wrywebview] auto_resize id={} failed: {}", subclass_id, e);
+        }
+    }
+    DefSubclassProc(hwnd, msg, wparam, lparam)
+}
+
+/// Subclasses the parent window to follow its `WM_SIZE` messages.
+///
+/// The WebView ID is used as the subclass ID so several WebViews can share a parent.
+pub fn set_auto_resize(
+    _webview: &WebView,
+    id: u64,
+    parent_handle: u64,
+    enabled: bool,
+) -> Result<(), WebViewError> {
+    let hwnd = hwnd_from(parent_handle);
+    unsafe {
+        if enabled {
+            if !SetWindowSubclass(hwnd, Some(auto_resize_subclass_proc), id as usize, 0).as_bool() {
+                return Err(WebViewError::Internal("SetWindowSubclass failed".to_string()));
+            }
+        } else {
+            let _ = RemoveWindowSubclass(hwnd, Some(auto_resize_subclass_proc), id as usize);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the `scheme://host[:port]` origin of a URL.
+fn url_origin(url: &str) -> Option<String> {
+    let uri: wry::http::Uri = url.parse().ok()?;
+    Some(format!("{}://{}", uri.scheme_str()?, uri.authority()?))
+}
+
+/// Decodes the base64 DER certificates of a `Network.getCertificate` result.
+fn certificates_from_devtools_result(result: &str) -> Vec<Vec<u8>> {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(result) else {
+        return Vec::new();
+    };
+    value["tableNames"]
+        .as_array()
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(|name| name.as_str())
+                .filter_map(|name| base64::engine::general_purpose::STANDARD.decode(name).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Reads the TLS certificate chain through the DevTools `Network.getCertificate` method.
+///
+/// WebView2 has no direct API for the certificate of a successful connection,
+/// so `on_chain` is called asynchronously once the DevTools call completes.
+pub fn certificate_chain(
+    webview: &WebView,
+    url: &str,
+    on_chain: CertificateChainCompletion,
+) -> Result<(), WebViewError> {
+    let origin = url_origin(url).ok_or(WebViewError::NotSecure)?;
+    let parameters = serde_json::json!({ "origin": origin }).to_string();
+    let core = unsafe { webview.controller().CoreWebView2() }.map_err(webview2_error)?;
+    unsafe {
+        core.CallDevToolsProtocolMethod(
+            &HSTRING::from("Network.getCertificate"),
+            &HSTRING::from(parameters),
+            &CallDevToolsProtocolMethodCompletedHandler::create(Box::new(move |error, result| {
+                if let Err(e) = error {
+                    eprintln!("[wrywebview] Network.getCertificate failed: {}", e);
+                }
+                on_chain(certificates_from_devtools_result(&result));
+                Ok(())
+            })),
+        )
+        .map_err(webview2_error)?;
+    }
+    Ok(())
+}
+
+/// Reloads through the DevTools `Page.reload` method with `ignoreCache`.
+///
+/// WebView2 has no hard reload API; `ICoreWebView2::Reload` may use the cache.
+/// Moves the keyboard focus into the WebView.
+///
+/// `SetFocus` on the controller's window does not reach the page; WebView2 takes
+/// the focus through `MoveFocus`.
+pub fn focus(webview: &WebView) -> Result<(), WebViewError> {
+    unsafe {
+        webview
+            .controller()
+            .MoveFocus(COREWEBVIEW2_MOVE_FOCUS_REASON_PROGRAMMATIC)
+            .map_err(webview2_error)
+    }
+}
+
+/// Gives the keyboard focus back to the parent window.
+pub fn blur(_webview: &WebView, parent_handle: u64) -> Result<(), WebViewError> {
+    unsafe {
+        SetFocus(Some(hwnd_from(parent_handle)))
+            .map_err(|e| WebViewError::Internal(format!("SetFocus failed: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Goes back in the native session history; `false` if there is no previous page.
+pub fn go_back(webview: &WebView) -> Result<bool, WebViewError> {
+    let core = unsafe { webview.controller().CoreWebView2() }.map_err(webview2_error)?;
+    unsafe {
+        let mut can_go_back = Default::default();
+        core.CanGoBack(&mut can_go_back).map_err(webview2_error)?;
+        if !can_go_back.as_bool() {
+            return Ok(false);
+        }
+        core.GoBack().map_err(webview2_error)?;
+    }
+    Ok(true)
+}
+
+/// Goes forward in the native session history; `false` if there is no next page.
+pub fn go_forward(webview: &WebView) -> Result<bool, WebViewError> {
+    let core = unsafe { webview.controller().CoreWebView2() }.map_err(webview2_error)?;
+    unsafe {
+        let mut can_go_forward = Default::default();
+        core.CanGoForward(&mut can_go_forward).map_err(webview2_error)?;
+        if !can_go_forward.as_bool() {
+            return Ok(false);
+        }
+        core.GoForward().map_err(webview2_error)?;
+    }
+    Ok(true)
+}
+
+/// Navigates with a `WebResourceRequest` built from the method, headers and body.
+pub fn load_request(
+    webview: &WebView,
+    method: &str,
+    url: &str,
+    headers: &[(String, String)],
+    body: Option<&[u8]>,
+) -> Result<(), WebViewError> {
+    let core = unsafe { webview.controller().CoreWebView2() }.map_err(webview2_error)?;
+    let core_2: ICoreWebView2_2 = core.cast().map_err(webview2_error)?;
+    let headers: Vec<String> =
+        headers.iter().map(|(name, value)| format!("{}: {}", name, value)).collect();
+    unsafe {
+        let environment = core_2.Environment().map_err(webview2_error)?;
+        let content = body.and_then(|body| SHCreateMemStream(Some(body)));
+        let request = environment
+            .CreateWebResourceRequest(
+                &HSTRING::from(url),
+                &HSTRING::from(method),
+                content.as_ref(),
+                &HSTRING::from(headers.join("\r\n")),
+            )
+            .map_err(webview2_error)?;
+        core_2.NavigateWithWebResourceRequest(&request).map_err(webview2_error)?;
+    }
+    Ok(())
+}
+
+pub fn reload_bypass_cache(webview: &WebView) -> Result<(), WebViewError> {
+    let parameters = serde_json::json!({ "ignoreCache": true }).to_string();
+    let core = unsafe { webview.controller().CoreWebView2() }.map_err(webview2_error)?;
+    unsafe {
+        core.CallDevToolsProtocolMethod(
+            &HSTRING::from("Page.reload"),
+            &HSTRING::from(parameters),
+            &CallDevToolsProtocolMethodCompletedHandler::create(Box::new(|error, _result| {
+                if let Err(e) = error {
+                    eprintln!("[wrywebview] Page.reload failed: {}", e);
+                }
+                Ok(())
+            })),
+        )
+        .map_err(webview2_error)
+    }
+}
+
+/// Chromium removed Web SQL; it can only be re-enabled with a browser argument
+/// when the WebView2 environment is created, not per WebView at runtime.
+pub fn set_web_sql_enabled(_webview: &WebView, _enabled: bool) -> Result<(), WebViewError> {
+    Err(WebViewError::UnsupportedPlatform)
+}
+
+/// Browser arguments wry passes to WebView2 when none are given.
+const DEFAULT_BROWSER_ARGS: &str = "--disable-features=msWebOOUI,msPdfOOUI,msSmartScreenProtection";
+
+/// Returns the browser arguments carrying the proxy, the GPU, the file access and
+/// the remote debugging settings, or `None` when wry's defaults apply.
+///
+/// The arguments belong to the WebView2 environment, which every WebView using
+/// the same data store shares, so all of them must be created with the same
+/// settings.
+pub fn browser_args(
+    proxy: Option<&ProxyConfig>,
+    hardware_acceleration: bool,
+    allow_file_access: bool,
+) -> Option<String> {
+    let remote_debugging_port = crate::platform::remote_debugging_port();
+    if proxy.is_none()
+        && hardware_acceleration
+        && !allow_file_access
+        && remote_debugging_port.is_none()
+    {
+        return None;
+    }
+    let mut args = DEFAULT_BROWSER_ARGS.to_string();
+    if let Some(proxy) = proxy {
+        args.push_str(&format!(" --proxy-server={}", proxy.url()));
+        if !proxy.bypass_list.is_empty() {
+            args.push_str(&format!(" --proxy-bypass-list={}", proxy.bypass_list.join(";")));
+        }
+    }
+    if !hardware_acceleration {
+        args.push_str(" --disable-gpu");
+    }
+    if allow_file_access {
+        args.push_str(" --allow-file-access-from-files");
+    }
+    if let Some(port) = remote_debugging_port {
+        args.push_str(&format!(" --remote-debugging-port={}", port));
+    }
+    Some(args)
+}
+
+/// Passes the settings of `browser_args` to WebView2.
+pub fn with_browser_args<'a>(
+    builder: WebViewBuilder<'a>,
+    proxy: Option<&ProxyConfig>,
+    hardware_acceleration: bool,
+    allow_file_access: bool,
+) -> WebViewBuilder<'a> {
+    match browser_args(proxy, hardware_acceleration, allow_file_access) {
+        Some(args) => builder.with_additional_browser_args(args),
+        None => builder,
+    }
+}
+
+/// File access is a browser argument of the WebView2 environment, fixed once it
+/// is created.
+pub fn set_allow_file_access(_webview: &WebView, _enabled: bool) -> Result<(), WebViewError> {
+    Err(WebViewError::NotSupportedAtRuntime)
+}
+
+/// The browser arguments of a WebView2 environment are fixed once it is created.
+pub fn set_proxy(_webview: &WebView, _proxy: &ProxyConfig) -> Result<(), WebViewError> {
+    Err(WebViewError::NotSupportedAtRuntime)
+}
+
+/// `ICoreWebView2Settings` has no autoplay setting; the policy is a browser
+/// argument of the WebView2 environment, fixed once it is created.
+pub fn set_autoplay_policy(
+    _webview: &WebView,
+    _policy: AutoplayPolicy,
+) -> Result<(), WebViewError> {
+    Err(WebViewError::NotSupportedAtRuntime)
+}
+
+pub fn set_muted(webview: &WebView, muted: bool) -> Result<(), WebViewError> {
+    unsafe {
+        let core: ICoreWebView2_8 = webview
+            .controller()
+            .CoreWebView2()
+            .and_then(|core| core.cast())
+            .map_err(webview2_error)?;
+        core.SetIsMuted(muted).map_err(webview2_error)
+    }
+}
@@ -0,0 +1,474 @@
+//! JavaScript snippets injected into WebView pages.
+//!
+//! Every helper installs itself under the `window.__wrywebview` namespace and is
+//! idempotent, so it can be re-evaluated after each navigation.
+
+use crate::ColorScheme;
+
+/// Prefix of IPC messages posted by injected scripts rather than by the page.
+pub const INTERNAL_MESSAGE_PREFIX: &str = "__wrywebview:";
+
+/// Minimum delay between two animation frame notifications (caps them at 60 per second).
+pub const ANIMATION_FRAME_INTERVAL_MS: f64 = 1000.0 / 60.0;
+
+/// Serializes the main frame's document.
+pub const PAGE_SOURCE_SCRIPT: &str =
+    "document.documentElement ? document.documentElement.outerHTML : \"\"";
+
+/// Leaves fullscreen if an element of the page is displayed fullscreen.
+pub const EXIT_FULLSCREEN_SCRIPT: &str =
+    "document.fullscreenElement && document.exitFullscreen && document.exitFullscreen();";
+
+/// Evaluates to the text currently selected in the page.
+pub const SELECTED_TEXT_SCRIPT: &str = "window.getSelection().toString()";
+
+/// Deselects any text selected in the page.
+pub const CLEAR_SELECTION_SCRIPT: &str = "window.getSelection().removeAllRanges();";
+
+/// Selects the content of the first element matching `selector`, replacing the
+/// current selection.
+///
+/// Evaluates to "ok", "missing" if nothing matches, or "invalid" if `selector`
+/// is not a valid CSS selector.
+pub fn select_text_script(selector: &str) -> String {
+    format!(
+        r#"(function () {{
+  var element;
+  try {{
+    element = document.querySelector({selector});
+  }} catch (e) {{
+    return "invalid";
+  }}
+  if (!element) return "missing";
+  var range = document.createRange();
+  range.selectNodeContents(element);
+  var selection = window.getSelection();
+  selection.removeAllRanges();
+  selection.addRange(range);
+  return "ok";
+}})()"#,
+        selector = js_string(selector)
+    )
+}
+
+/// Scrolls the first element matching `selector` to the top of the viewport.
+///
+/// Evaluates to "ok", "missing" or "invalid", like `select_text_script`.
+pub fn scroll_to_element_script(selector: &str, smooth: bool) -> String {
+    format!(
+        r#"(function () {{
+  var element;
+  try {{
+    element = document.querySelector({selector});
+  }} catch (e) {{
+    return "invalid";
+  }}
+  if (!element) return "missing";
+  element.scrollIntoView({{ behavior: "{behavior}", block: "start" }});
+  return "ok";
+}})()"#,
+        selector = js_string(selector),
+        behavior = if smooth { "smooth" } else { "auto" }
+    )
+}
+
+/// Posts the length of the session history and the index of the current entry
+/// (-1 without the Navigation API) to native code as JSON.
+pub fn history_script() -> String {
+    format!(
+        r#"(function () {{
+  var entry = window.navigation && window.navigation.currentEntry;
+  window.ipc.postMessage({prefix} + "history:" + JSON.stringify({{
+    length: window.history.length,
+    index: entry ? entry.index : -1
+  }}));
+}})();"#,
+        prefix = js_string(INTERNAL_MESSAGE_PREFIX)
+    )
+}
+
+/// Splits an internal IPC message into its kind and payload.
+///
+/// Returns `None` for regular page messages, which are queued for the host.
+pub fn parse_internal_message(message: &str) -> Option<(&str, &str)> {
+    message
+        .strip_prefix(INTERNAL_MESSAGE_PREFIX)
+        .and_then(|rest| rest.split_once(':'))
+}
+
+/// Encodes a string as a JavaScript string literal (double quoted).
+pub fn js_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{2028}' => out.push_str("\\u2028"),
+            '\u{2029}' => out.push_str("\\u2029"),
+            '<' => out.push_str("\\u003c"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Encodes name/value pairs as a JavaScript array of `[name, value]` arrays.
+fn js_pairs(pairs: &[(String, String)]) -> String {
+    let entries: Vec<String> = pairs
+        .iter()
+        .map(|(name, value)| format!("[{},{}]", js_string(name), js_string(value)))
+        .collect();
+    format!("[{}]", entries.join(","))
+}
+
+/// Adds the given headers to every `fetch` and `XMLHttpRequest` issued by the page.
+pub fn request_headers_script(headers: &[(String, String)]) -> String {
+    format!(
+        r#"(function () {{
+  var ns = window.__wrywebview = window.__wrywebview || {{}};
+  ns.requestHeaders = {headers};
+  if (ns.requestHeadersInstalled) return;
+  ns.requestHeadersInstalled = true;
+  if (window.fetch) {{
+    var originalFetch = window.fetch;
+    window.fetch = function (input, init) {{
+      var extra = ns.requestHeaders || [];
+      if (extra.length) {{
+        init = Object.assign({{}}, init || {{}});
+        var base = init.headers || (input instanceof Request ? input.headers : undefined);
+        var merged = new Headers(base);
+        extra.forEach(function (h) {{ merged.set(h[0], h[1]); }});
+        init.headers = merged;
+      }}
+      return originalFetch.call(this, input, init);
+    }};
+  }}
+  var originalSend = XMLHttpRequest.prototype.send;
+  XMLHttpRequest.prototype.send = function (body) {{
+    var xhr = this;
+    (ns.requestHeaders || []).forEach(function (h) {{
+      try {{ xhr.setRequestHeader(h[0], h[1]); }} catch (e) {{}}
+    }});
+    return originalSend.call(this, body);
+  }};
+}})();"#,
+        headers = js_pairs(headers)
+    )
+}
+
+/// Posts the `requestAnimationFrame` timestamp to native code, throttled to 60 fps.
+///
+/// Passing `enabled = false` lets the running loop stop at its next frame.
+pub fn animation_frame_script(enabled: bool) -> String {
+    format!(
+        r#"(function () {{
+  var ns = window.__wrywebview = window.__wrywebview || {{}};
+  ns.animationFrames = {enabled};
+  if (!ns.animationFrames || ns.animationFrameInstalled) return;
+  ns.animationFrameInstalled = true;
+  var last = -Infinity;
+  function tick(timestamp) {{
+    if (!ns.animationFrames) {{
+      ns.animationFrameInstalled = false;
+      return;
+    }}
+    // Allow 1 ms of jitter so a 60 Hz display is not throttled to 30 fps.
+    if (timestamp - last >= {interval} - 1) {{
+      last = timestamp;
+      window.ipc.postMessage({prefix} + "frame:" + timestamp);
+    }}
+    window.requestAnimationFrame(tick);
+  }}
+  window.requestAnimationFrame(tick);
+}})();"#,
+        enabled = enabled,
+        interval = ANIMATION_FRAME_INTERVAL_MS,
+        prefix = js_string(INTERNAL_MESSAGE_PREFIX)
+    )
+}
+
+/// Posts every `layout-shift` performance entry to native code as JSON.
+///
+/// Passing `enabled = false` mutes the installed observer.
+pub fn layout_shift_script(enabled: bool) -> String {
+    format!(
+        r##"(function () {{
+  var ns = window.__wrywebview = window.__wrywebview || {{}};
+  ns.layoutShifts = {enabled};
+  if (!ns.layoutShifts || ns.layoutShiftInstalled) return;
+  if (!window.PerformanceObserver ||
+      (PerformanceObserver.supportedEntryTypes || []).indexOf("layout-shift") < 0) return;
+  ns.layoutShiftInstalled = true;
+  function selector(node) {{
+    var parts = [];
+    while (node && node.nodeType === 1) {{
+      if (node.id) {{
+        parts.unshift("#" + CSS.escape(node.id));
+        break;
+      }}
+      var part = node.localName;
+      var parent = node.parentElement;
+      if (parent) {{
+        part += ":nth-child(" + (Array.prototype.indexOf.call(parent.children, node) + 1) + ")";
+      }}
+      parts.unshift(part);
+      node = parent;
+    }}
+    return parts.join(" > ");
+  }}
+  function rect(r) {{
+    return {{ x: r.x, y: r.y, width: r.width, height: r.height }};
+  }}
+  new PerformanceObserver(function (list) {{
+    if (!ns.layoutShifts) return;
+    list.getEntries().forEach(function (entry) {{
+      window.ipc.postMessage({prefix} + "layout-shift:" + JSON.stringify({{
+        value: entry.value,
+        had_recent_input: entry.hadRecentInput,
+        sources: (entry.sources || []).map(function (source) {{
+          return {{
+            element_selector: selector(source.node),
+            current_rect: rect(source.currentRect),
+            previous_rect: rect(source.previousRect)
+          }};
+        }})
+      }}));
+    }});
+  }}).observe({{ type: "layout-shift", buffered: true }});
+}})();"##,
+        enabled = enabled,
+        prefix = js_string(INTERNAL_MESSAGE_PREFIX)
+    )
+}
+
+/// Posts the `console` calls of the page to native code as JSON, then forwards
+/// them to the original methods.
+///
+/// Passing `enabled = false` stops posting.
+pub fn console_script(enabled: bool) -> String {
+    format!(
+        r#"(function () {{
+  var ns = window.__wrywebview = window.__wrywebview || {{}};
+  ns.console = {enabled};
+  if (ns.consoleInstalled) return;
+  ns.consoleInstalled = true;
+  function text(value) {{
+    if (typeof value === "string") return value;
+    if (value instanceof Error) return value.stack || String(value);
+    try {{
+      var json = JSON.stringify(value);
+      if (json !== undefined) return json;
+    }} catch (e) {{}}
+    return String(value);
+  }}
+  function caller() {{
+    // The first frame with a URL belongs to the page; injected code has none.
+    var frame = /((?:https?|file|about|data|blob):[^\s()]*?):(\d+):\d+/;
+    var match = frame.exec(new Error().stack || "");
+    return match ? {{ source: match[1], line: parseInt(match[2], 10) }} : {{ source: "", line: 0 }};
+  }}
+  [["log", "log"], ["debug", "log"], ["info", "info"], ["warn", "warn"], ["error", "error"]]
+    .forEach(function (method) {{
+      var original = console[method[0]];
+      console[method[0]] = function () {{
+        if (ns.console) {{
+          var location = caller();
+          window.ipc.postMessage({prefix} + "console:" + JSON.stringify({{
+            level: method[1],
+            message: Array.prototype.map.call(arguments, text).join(" "),
+            source: location.source,
+            line: location.line
+          }}));
+        }}
+        return original.apply(console, arguments);
+      }};
+    }});
+}})();"#,
+        enabled = enabled,
+        prefix = js_string(INTERNAL_MESSAGE_PREFIX)
+    )
+}
+
+/// Hides the scrollbars of the page with a style sheet; content still scrolls.
+///
+/// Passing `visible = true` removes the style sheet.
+pub fn scrollbar_script(visible: bool) -> String {
+    format!(
+        r#"(function () {{
+  var style = document.getElementById("__wrywebview-scrollbars");
+  if ({visible}) {{
+    if (style) style.remove();
+    return;
+  }}
+  if (style) return;
+  style = document.createElement("style");
+  style.id = "__wrywebview-scrollbars";
+  style.textContent = "::-webkit-scrollbar {{ display: none; }} " +
+    "html, body {{ scrollbar-width: none; }}";
+  document.documentElement.appendChild(style);
+}})();"#,
+        visible = visible
+    )
+}
+
+/// Exposes the safe area insets as the `--safe-area-inset-*` CSS variables of the root.
+pub fn content_insets_script((top, left, bottom, right): (i32, i32, i32, i32)) -> String {
+    format!(
+        r#"(function () {{
+  var style = document.documentElement.style;
+  style.setProperty("--safe-area-inset-top", "{top}px");
+  style.setProperty("--safe-area-inset-left", "{left}px");
+  style.setProperty("--safe-area-inset-bottom", "{bottom}px");
+  style.setProperty("--safe-area-inset-right", "{right}px");
+}})();"#,
+        top = top,
+        left = left,
+        bottom = bottom,
+        right = right
+    )
+}
+
+/// Forces `prefers-color-scheme` to the given scheme, or restores it for `Auto`.
+///
+/// Rewrites the media rules of the style sheets readable by the page, overrides
+/// `window.matchMedia` and sets the root `color-scheme` so form controls follow.
+pub fn color_scheme_script(scheme: ColorScheme) -> String {
+    let scheme = match scheme {
+        ColorScheme::Light => "\"light\"",
+        ColorScheme::Dark => "\"dark\"",
+        ColorScheme::Auto => "null",
+    };
+    format!(
+        r#"(function () {{
+  var ns = window.__wrywebview = window.__wrywebview || {{}};
+  ns.colorScheme = {scheme};
+  function override(text) {{
+    if (!ns.colorScheme) return text;
+    return text.replace(/\(\s*prefers-color-scheme\s*:\s*(light|dark)\s*\)/g, function (_, value) {{
+      return value === ns.colorScheme ? "(min-width: 0px)" : "(max-width: -1px)";
+    }});
+  }}
+  if (!ns.colorSchemeInstalled) {{
+    ns.colorSchemeInstalled = true;
+    var originalMatchMedia = window.matchMedia;
+    window.matchMedia = function (query) {{
+      return originalMatchMedia.call(window, override(String(query)));
+    }};
+  }}
+  function rewrite(rules) {{
+    for (var i = 0; i < rules.length; i++) {{
+      var rule = rules[i];
+      if (rule.media && rule.cssRules) {{
+        if (rule.__wrywebviewMedia === undefined) rule.__wrywebviewMedia = rule.media.mediaText;
+        rule.media.mediaText = override(rule.__wrywebviewMedia);
+      }}
+      if (rule.cssRules) rewrite(rule.cssRules);
+    }}
+  }}
+  Array.prototype.forEach.call(document.styleSheets, function (sheet) {{
+    try {{ rewrite(sheet.cssRules); }} catch (e) {{}}
+  }});
+  document.documentElement.style.colorScheme = ns.colorScheme || "";
+}})();"#,
+        scheme = scheme
+    )
+}
+
+/// Intercepts clicks on `<input type="file">` and asks native code for the files.
+///
+/// Used on Windows, where WebView2 has no file chooser event. Passing
+/// `enabled = false` restores the native picker.
+pub fn file_chooser_script(enabled: bool) -> String {
+    format!(
+        r#"(function () {{
+  var ns = window.__wrywebview = window.__wrywebview || {{}};
+  ns.fileChooser = {enabled};
+  if (ns.fileChooserInstalled) return;
+  ns.fileChooserInstalled = true;
+  document.addEventListener("click", function (event) {{
+    var input = event.target;
+    if (!ns.fileChooser || !(input instanceof HTMLInputElement) ||
+        input.type !== "file" || input.disabled) return;
+    event.preventDefault();
+    ns.fileChooserInput = input;
+    window.ipc.postMessage({prefix} + "file-chooser:" +
+      JSON.stringify({{ accept: input.accept, multiple: input.multiple }}));
+  }}, true);
+  ns.fileChooserResult = function (files) {{
+    var input = ns.fileChooserInput;
+    ns.fileChooserInput = null;
+    if (!input) return;
+    if (!files.length) {{
+      input.dispatchEvent(new Event("cancel"));
+      return;
+    }}
+    var transfer = new DataTransfer();
+    files.forEach(function (file) {{
+      var binary = atob(file[1]);
+      var bytes = new Uint8Array(binary.length);
+      for (var i = 0; i < binary.length; i++) bytes[i] = binary.charCodeAt(i);
+      transfer.items.add(new File([bytes], file[0]));
+    }});
+    input.files = transfer.files;
+    input.dispatchEvent(new Event("input", {{ bubbles: true }}));
+    input.dispatchEvent(new Event("change", {{ bubbles: true }}));
+  }};
+}})();"#,
+        enabled = enabled,
+        prefix = js_string(INTERNAL_MESSAGE_PREFIX)
+    )
+}
+
+/// Hands the chosen files, as `(name, base64 contents)` pairs, to the pending file input.
+pub fn file_chooser_result_script(files: &[(String, String)]) -> String {
+    format!(
+        "window.__wrywebview && window.__wrywebview.fileChooserResult && \
+         window.__wrywebview.fileChooserResult({});",
+        js_pairs(files)
+    )
+}
+
+/// Mutes or unmutes the audio and video elements currently in the page.
+#[cfg(target_os = "macos")]
+pub fn mute_media_script(muted: bool) -> String {
+    format!(
+        "document.querySelectorAll(\"audio, video\").forEach(function (media) {{ \
+         media.muted = {}; }});",
+        muted
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn js_string_escapes_quotes_and_backslashes() {
+        assert_eq!(js_string(r#"a"b\c"#), r#""a\"b\\c""#);
+    }
+
+    #[test]
+    fn js_string_cannot_close_a_script_element() {
+        assert_eq!(js_string("</script>"), r#""\u003c/script>""#);
+    }
+
+    #[test]
+    fn js_string_escapes_line_terminators_and_control_characters() {
+        assert_eq!(js_string("\u{2028}\u{2029}"), r#""\u2028\u2029""#);
+        assert_eq!(js_string("\n\r\t\u{0}\u{1f}"), r#""\n\r\t\u0000\u001f""#);
+    }
+
+    #[test]
+    fn js_string_round_trips() {
+        // Every escape is also valid JSON, so a JSON parser recovers the input.
+        let value = "quote\" backslash\\ </script> \u{2028} \u{7} é 🦀";
+        let decoded: String = serde_json::from_str(&js_string(value)).unwrap();
+        assert_eq!(decoded, value);
+    }
+}
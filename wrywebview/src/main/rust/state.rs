@@ -1,53 +1,323 @@
 //! WebView state management and registry.
 
+use std::cell::{RefCell, RefMut};
 use std::collections::VecDeque;
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::{Arc, Mutex, OnceLock};
-use std::thread::ThreadId;
+use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use wry::WebView;
+use arc_swap::ArcSwap;
+use wry::{WebContext, WebView};
 
 use crate::error::WebViewError;
+use crate::scripts;
+use crate::{
+    AnimationFrameHandler, AuthChallengeHandler, AutoplayPolicy, ColorScheme, ConsoleHandler,
+    ContextMenuHandler, DownloadHandler, ErrorPageHandler, FileChooserHandler, FullscreenHandler,
+    JsDialogHandler, LayoutShiftCallback, LinkOpenMode, NewWindowHandler, NewWindowOpenedListener,
+    PermissionHandler, ProxyConfig, RequestFilter, RequestInterceptor, ResourceType, SslWarning,
+    SslWarningObserver, TlsErrorHandler, WebViewConfig, WebViewEvent, WebViewInfo,
+};
+
+/// Maximum number of SSL warnings kept per page.
+const MAX_SSL_WARNINGS: usize = 256;
+
+/// Number of events buffered for a subscriber before new ones are dropped.
+const EVENT_QUEUE_CAPACITY: usize = 256;
+
+/// A callback registered by the host application, replaceable at any time.
+pub struct HandlerSlot<T: ?Sized> {
+    handler: Mutex<Option<Arc<T>>>,
+}
+
+impl<T: ?Sized> HandlerSlot<T> {
+    fn new() -> Self {
+        Self {
+            handler: Mutex::new(None),
+        }
+    }
+
+    pub fn set(&self, handler: Option<Arc<T>>) -> Result<(), WebViewError> {
+        let mut current = self
+            .handler
+            .lock()
+            .map_err(|_| WebViewError::Internal("handler lock poisoned".to_string()))?;
+        *current = handler;
+        Ok(())
+    }
+
+    /// Returns the current handler, if any. A poisoned slot behaves as empty.
+    pub fn get(&self) -> Option<Arc<T>> {
+        self.handler.lock().ok().and_then(|handler| handler.clone())
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.get().is_some()
+    }
+}
 
 /// Tracks the loading state and current URL of a WebView.
+///
+/// The `AtomicBool` flags are stored with `Release` and loaded with `Acquire`. Each flag
+/// stands alone and the data it describes sits behind its own lock, so the single total
+/// order of `SeqCst` buys nothing over pairwise ordering. The numeric fields are only read
+/// back as values and use `Relaxed`; see the notes on each.
 pub struct WebViewState {
+    /// The configuration the WebView was created with. Settings changed since,
+    /// like `javascript_enabled`, are tracked by the fields below.
+    pub creation_config: WebViewConfig,
+    /// `Relaxed`: set before the state is published through the `STATES` lock, which
+    /// orders it for every reader.
+    id: AtomicU64,
+    /// When the WebView was registered, in milliseconds since the UNIX epoch.
+    created_at: AtomicU64,
     pub is_loading: AtomicBool,
-    pub current_url: Mutex<String>,
-    pub page_title: Mutex<String>,
+    /// Estimated load progress, as the bits of an `f64` between 0.0 and 1.0.
+    ///
+    /// `Relaxed`: a progress reading guards no other data.
+    load_progress: AtomicU64,
+    /// Page zoom factor as last set by `set_zoom`, as the bits of an `f64`.
+    zoom: AtomicU64,
+    /// Read on every `get_url` poll but written only on navigation, so reads
+    /// are a lock-free pointer load.
+    pub current_url: ArcSwap<String>,
+    /// Read on every `get_title` poll but written only on title changes.
+    pub page_title: RwLock<String>,
     history: Mutex<Vec<String>>,
     history_index: Mutex<isize>,
+    /// `history.length` of the page, as of the last finished load.
+    ///
+    /// `Relaxed`, like `history_offset`: the two are stored separately, so no ordering
+    /// would make a reader see them as one consistent pair.
+    history_length: AtomicU32,
+    /// Index of the current entry in the page history, as of the last finished
+    /// load, or -1 if the engine does not report it.
+    history_offset: AtomicI32,
     ipc_messages: Mutex<VecDeque<String>>,
+    /// Sender of the `subscribe_events` stream, if someone subscribed.
+    event_tx: Mutex<Option<SyncSender<WebViewEvent>>>,
+    request_headers: Mutex<Vec<(String, String)>>,
+    /// Scripts registered by the host to run after every page load, by script ID.
+    persistent_scripts: Mutex<Vec<(u64, String)>>,
+    parent_handle: Mutex<u64>,
+    pub auto_resize: AtomicBool,
+    pub is_muted: AtomicBool,
+    pub show_scrollbars: AtomicBool,
+    pub javascript_enabled: AtomicBool,
+    pub pinch_zoom_enabled: AtomicBool,
+    pub allow_file_access: AtomicBool,
+    /// Whether DevTools were opened through `open_devtools` and not closed since.
+    pub devtools_open: AtomicBool,
+    /// `Relaxed`: the swap is the only access and only its own result matters.
+    initial_script_ran: AtomicBool,
+    /// Whether the WebView receives pointer events, or lets them through to the views below.
+    pub pointer_events_enabled: AtomicBool,
+    /// Whether the WebView holds the keyboard focus of its window.
+    pub has_focus: AtomicBool,
+    color_scheme: Mutex<ColorScheme>,
+    /// Stacking position among sibling WebViews; higher values are closer to the front.
+    ///
+    /// `Relaxed`: informational; the native stacking call is what takes effect.
+    pub z_index: AtomicI32,
+    /// Margins kept between the parent edges and the WebView: (top, left, bottom, right).
+    auto_resize_insets: Mutex<(i32, i32, i32, i32)>,
+    /// Scale used to convert logical bounds to physical pixels, instead of the platform's.
+    scale_factor: Mutex<Option<f64>>,
+    /// Latest bounds passed to `set_bounds` and not yet applied: (x, y, width, height).
+    pending_bounds: Mutex<Option<(i32, i32, i32, i32)>>,
+    /// Why the last `apply_pending_bounds` failed, until the next `set_bounds` reports it.
+    bounds_error: Mutex<Option<String>>,
+    /// Size limits applied to every bounds change, in logical pixels; a maximum of 0
+    /// means unlimited.
+    ///
+    /// `Relaxed`, for the same reason as `history_length`: a width and its height are
+    /// stored separately.
+    min_width: AtomicI32,
+    min_height: AtomicI32,
+    max_width: AtomicI32,
+    max_height: AtomicI32,
+    /// Safe area insets exposed to the page as CSS variables: (top, left, bottom, right).
+    content_insets: Mutex<(i32, i32, i32, i32)>,
+    pub animation_frame_handler: HandlerSlot<dyn AnimationFrameHandler>,
+    pub permission_handler: HandlerSlot<dyn PermissionHandler>,
+    pub download_handler: HandlerSlot<dyn DownloadHandler>,
+    pub js_dialog_handler: HandlerSlot<dyn JsDialogHandler>,
+    pub layout_shift_callback: HandlerSlot<dyn LayoutShiftCallback>,
+    pub console_handler: HandlerSlot<dyn ConsoleHandler>,
+    pub file_chooser_handler: HandlerSlot<dyn FileChooserHandler>,
+    pub fullscreen_handler: HandlerSlot<dyn FullscreenHandler>,
+    pub context_menu_handler: HandlerSlot<dyn ContextMenuHandler>,
+    link_open_mode: Mutex<Option<LinkOpenMode>>,
+    pub new_window_opened_listener: HandlerSlot<dyn NewWindowOpenedListener>,
+    pub new_window_handler: HandlerSlot<dyn NewWindowHandler>,
+    pub auth_challenge_handler: HandlerSlot<dyn AuthChallengeHandler>,
+    /// Challenges answered per host and realm since the last finished page load.
+    auth_attempts: Mutex<HashMap<(String, String), u32>>,
+    ssl_warnings: Mutex<Vec<SslWarning>>,
+    pub ssl_warning_observer: HandlerSlot<dyn SslWarningObserver>,
+    /// Status and headers of the response for the current main document.
+    main_response: Mutex<Option<(u16, Vec<(String, String)>)>>,
+    pub tls_error_handler: HandlerSlot<dyn TlsErrorHandler>,
+    pub error_page_handler: HandlerSlot<dyn ErrorPageHandler>,
+    /// Interceptor set by `intercept_network_request`, with the requests it wants.
+    request_interceptor: Mutex<Option<(RequestFilter, Arc<dyn RequestInterceptor>)>>,
+    group_id: Mutex<Option<u64>>,
+    proxy: Mutex<Option<ProxyConfig>>,
+    /// `None` until a policy is requested, meaning the engine default applies.
+    autoplay_policy: Mutex<Option<AutoplayPolicy>>,
 }
 
 impl WebViewState {
-    /// Creates a new WebViewState with the given initial URL.
-    pub fn new(url: String) -> Self {
+    /// Creates a new WebViewState seeded from the creation-time configuration.
+    ///
+    /// A WebView starting on `about:blank` is not reported as loading: the blank
+    /// page can finish before the host first polls `is_loading`, which would
+    /// otherwise briefly read `true`.
+    pub fn new(config: &WebViewConfig) -> Self {
+        let starts_blank = config.url == "about:blank";
         Self {
-            is_loading: AtomicBool::new(true),
-            current_url: Mutex::new(url),
-            page_title: Mutex::new(String::new()),
+            creation_config: config.clone(),
+            id: AtomicU64::new(0),
+            created_at: AtomicU64::new(0),
+            is_loading: AtomicBool::new(!starts_blank),
+            load_progress: AtomicU64::new(0f64.to_bits()),
+            zoom: AtomicU64::new(1f64.to_bits()),
+            current_url: ArcSwap::from_pointee(config.url.clone()),
+            page_title: RwLock::new(String::new()),
             history: Mutex::new(Vec::new()),
             history_index: Mutex::new(-1),
+            history_length: AtomicU32::new(0),
+            history_offset: AtomicI32::new(-1),
             ipc_messages: Mutex::new(VecDeque::new()),
+            event_tx: Mutex::new(None),
+            request_headers: Mutex::new(Vec::new()),
+            persistent_scripts: Mutex::new(Vec::new()),
+            parent_handle: Mutex::new(0),
+            auto_resize: AtomicBool::new(false),
+            is_muted: AtomicBool::new(false),
+            show_scrollbars: AtomicBool::new(true),
+            javascript_enabled: AtomicBool::new(config.javascript_enabled),
+            pinch_zoom_enabled: AtomicBool::new(config.pinch_to_zoom),
+            allow_file_access: AtomicBool::new(config.allow_file_access),
+            devtools_open: AtomicBool::new(false),
+            initial_script_ran: AtomicBool::new(false),
+            pointer_events_enabled: AtomicBool::new(true),
+            has_focus: AtomicBool::new(false),
+            color_scheme: Mutex::new(ColorScheme::Auto),
+            z_index: AtomicI32::new(0),
+            auto_resize_insets: Mutex::new((0, 0, 0, 0)),
+            scale_factor: Mutex::new(None),
+            pending_bounds: Mutex::new(None),
+            bounds_error: Mutex::new(None),
+            min_width: AtomicI32::new(0),
+            min_height: AtomicI32::new(0),
+            max_width: AtomicI32::new(0),
+            max_height: AtomicI32::new(0),
+            content_insets: Mutex::new((0, 0, 0, 0)),
+            animation_frame_handler: HandlerSlot::new(),
+            permission_handler: HandlerSlot::new(),
+            download_handler: HandlerSlot::new(),
+            js_dialog_handler: HandlerSlot::new(),
+            layout_shift_callback: HandlerSlot::new(),
+            console_handler: HandlerSlot::new(),
+            file_chooser_handler: HandlerSlot::new(),
+            fullscreen_handler: HandlerSlot::new(),
+            context_menu_handler: HandlerSlot::new(),
+            link_open_mode: Mutex::new(None),
+            new_window_opened_listener: HandlerSlot::new(),
+            new_window_handler: HandlerSlot::new(),
+            auth_challenge_handler: HandlerSlot::new(),
+            auth_attempts: Mutex::new(HashMap::new()),
+            ssl_warnings: Mutex::new(Vec::new()),
+            ssl_warning_observer: HandlerSlot::new(),
+            main_response: Mutex::new(None),
+            tls_error_handler: HandlerSlot::new(),
+            error_page_handler: HandlerSlot::new(),
+            request_interceptor: Mutex::new(None),
+            group_id: Mutex::new(None),
+            proxy: Mutex::new(config.proxy.clone()),
+            autoplay_policy: Mutex::new(config.autoplay_policy),
         }
     }
 
+    /// Returns the registry ID of the WebView, or 0 if it is not registered yet.
+    pub fn id(&self) -> u64 {
+        self.id.load(Ordering::Relaxed)
+    }
+
+    /// Returns when the WebView was registered, in milliseconds since the UNIX epoch.
+    pub fn created_at(&self) -> u64 {
+        self.created_at.load(Ordering::Relaxed)
+    }
+
     pub fn update_current_url(&self, url: String) -> Result<(), WebViewError> {
-        {
-            let mut current = self
-                .current_url
-                .lock()
-                .map_err(|_| WebViewError::Internal("url lock poisoned".to_string()))?;
-            *current = url.clone();
-        }
+        self.current_url.store(Arc::new(url.clone()));
         self.update_history(url)
     }
 
+    pub fn zoom(&self) -> f64 {
+        f64::from_bits(self.zoom.load(Ordering::Relaxed))
+    }
+
+    pub fn set_zoom(&self, zoom: f64) {
+        self.zoom.store(zoom.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn load_progress(&self) -> f64 {
+        f64::from_bits(self.load_progress.load(Ordering::Relaxed))
+    }
+
+    pub fn set_load_progress(&self, progress: f64) {
+        let progress = progress.clamp(0.0, 1.0);
+        self.load_progress.store(progress.to_bits(), Ordering::Relaxed);
+        self.emit_event(WebViewEvent::LoadProgress { progress });
+    }
+
+    /// Replaces the event subscriber and returns the new receiving end.
+    pub fn subscribe_events(&self) -> Result<Receiver<WebViewEvent>, WebViewError> {
+        let (tx, rx) = mpsc::sync_channel(EVENT_QUEUE_CAPACITY);
+        let mut event_tx = self
+            .event_tx
+            .lock()
+            .map_err(|_| WebViewError::Internal("event sender lock poisoned".to_string()))?;
+        *event_tx = Some(tx);
+        Ok(rx)
+    }
+
+    /// Sends an event to the subscriber without blocking; dropped if its queue is full.
+    pub fn emit_event(&self, event: WebViewEvent) {
+        let Ok(mut event_tx) = self.event_tx.lock() else {
+            return;
+        };
+        let Some(tx) = event_tx.as_ref() else {
+            return;
+        };
+        match tx.try_send(event) {
+            Ok(()) | Err(TrySendError::Full(_)) => {}
+            Err(TrySendError::Disconnected(_)) => *event_tx = None,
+        }
+    }
+
+    /// Returns the initial script the first time it is called, then `None`.
+    pub fn take_initial_script(&self) -> Option<&str> {
+        let script = self.creation_config.initial_script.as_deref()?;
+        (!self.initial_script_ran.swap(true, Ordering::Relaxed)).then_some(script)
+    }
+
+    /// Records the history position reported by the page.
+    pub fn set_history_position(&self, length: u32, offset: i32) {
+        self.history_length.store(length, Ordering::Relaxed);
+        self.history_offset.store(offset, Ordering::Relaxed);
+    }
+
     pub fn update_page_title(&self, title: String) -> Result<(), WebViewError> {
         let mut page_title = self
             .page_title
-            .lock()
+            .write()
             .map_err(|_| WebViewError::Internal("title lock poisoned".to_string()))?;
         *page_title = title;
         Ok(())
@@ -70,6 +340,426 @@ impl WebViewState {
         Ok(queue.drain(..).collect())
     }
 
+    /// Adds headers to every outgoing request, replacing existing headers with the same name.
+    pub fn add_request_headers(&self, headers: Vec<(String, String)>) -> Result<(), WebViewError> {
+        let mut current = self
+            .request_headers
+            .lock()
+            .map_err(|_| WebViewError::Internal("request headers lock poisoned".to_string()))?;
+        for (name, value) in headers {
+            current.retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+            current.push((name, value));
+        }
+        Ok(())
+    }
+
+    pub fn clear_request_headers(&self) -> Result<(), WebViewError> {
+        let mut current = self
+            .request_headers
+            .lock()
+            .map_err(|_| WebViewError::Internal("request headers lock poisoned".to_string()))?;
+        current.clear();
+        Ok(())
+    }
+
+    pub fn request_headers(&self) -> Result<Vec<(String, String)>, WebViewError> {
+        let current = self
+            .request_headers
+            .lock()
+            .map_err(|_| WebViewError::Internal("request headers lock poisoned".to_string()))?;
+        Ok(current.clone())
+    }
+
+    /// Registers a script to run after every page load and returns its script ID.
+    pub fn add_persistent_script(&self, script: String) -> Result<u64, WebViewError> {
+        let script_id = NEXT_SCRIPT_ID.fetch_add(1, Ordering::Relaxed);
+        let mut scripts = self
+            .persistent_scripts
+            .lock()
+            .map_err(|_| WebViewError::Internal("persistent scripts lock poisoned".to_string()))?;
+        scripts.push((script_id, script));
+        Ok(script_id)
+    }
+
+    /// Unregisters a persistent script; a no-op if the ID is unknown.
+    pub fn remove_persistent_script(&self, script_id: u64) -> Result<(), WebViewError> {
+        let mut scripts = self
+            .persistent_scripts
+            .lock()
+            .map_err(|_| WebViewError::Internal("persistent scripts lock poisoned".to_string()))?;
+        scripts.retain(|(existing, _)| *existing != script_id);
+        Ok(())
+    }
+
+    pub fn color_scheme(&self) -> Result<ColorScheme, WebViewError> {
+        let scheme = self
+            .color_scheme
+            .lock()
+            .map_err(|_| WebViewError::Internal("color scheme lock poisoned".to_string()))?;
+        Ok(*scheme)
+    }
+
+    pub fn set_color_scheme(&self, scheme: ColorScheme) -> Result<(), WebViewError> {
+        let mut current = self
+            .color_scheme
+            .lock()
+            .map_err(|_| WebViewError::Internal("color scheme lock poisoned".to_string()))?;
+        *current = scheme;
+        Ok(())
+    }
+
+    pub fn parent_handle(&self) -> Result<u64, WebViewError> {
+        let handle = self
+            .parent_handle
+            .lock()
+            .map_err(|_| WebViewError::Internal("parent handle lock poisoned".to_string()))?;
+        Ok(*handle)
+    }
+
+    pub fn set_parent_handle(&self, parent_handle: u64) -> Result<(), WebViewError> {
+        let mut handle = self
+            .parent_handle
+            .lock()
+            .map_err(|_| WebViewError::Internal("parent handle lock poisoned".to_string()))?;
+        *handle = parent_handle;
+        Ok(())
+    }
+
+    pub fn auto_resize_insets(&self) -> Result<(i32, i32, i32, i32), WebViewError> {
+        let insets = self
+            .auto_resize_insets
+            .lock()
+            .map_err(|_| WebViewError::Internal("auto resize insets lock poisoned".to_string()))?;
+        Ok(*insets)
+    }
+
+    pub fn set_auto_resize_insets(&self, insets: (i32, i32, i32, i32)) -> Result<(), WebViewError> {
+        let mut current = self
+            .auto_resize_insets
+            .lock()
+            .map_err(|_| WebViewError::Internal("auto resize insets lock poisoned".to_string()))?;
+        *current = insets;
+        Ok(())
+    }
+
+    /// Replaces the pending bounds; only the latest ones are applied.
+    pub fn set_pending_bounds(&self, bounds: (i32, i32, i32, i32)) -> Result<(), WebViewError> {
+        let mut pending = self
+            .pending_bounds
+            .lock()
+            .map_err(|_| WebViewError::Internal("pending bounds lock poisoned".to_string()))?;
+        *pending = Some(bounds);
+        Ok(())
+    }
+
+    pub fn take_pending_bounds(&self) -> Result<Option<(i32, i32, i32, i32)>, WebViewError> {
+        let mut pending = self
+            .pending_bounds
+            .lock()
+            .map_err(|_| WebViewError::Internal("pending bounds lock poisoned".to_string()))?;
+        Ok(pending.take())
+    }
+
+    pub fn set_bounds_error(&self, message: String) -> Result<(), WebViewError> {
+        let mut error = self
+            .bounds_error
+            .lock()
+            .map_err(|_| WebViewError::Internal("bounds error lock poisoned".to_string()))?;
+        *error = Some(message);
+        Ok(())
+    }
+
+    pub fn take_bounds_error(&self) -> Result<Option<String>, WebViewError> {
+        let mut error = self
+            .bounds_error
+            .lock()
+            .map_err(|_| WebViewError::Internal("bounds error lock poisoned".to_string()))?;
+        Ok(error.take())
+    }
+
+    pub fn set_min_size(&self, width: i32, height: i32) {
+        self.min_width.store(width, Ordering::Relaxed);
+        self.min_height.store(height, Ordering::Relaxed);
+    }
+
+    pub fn set_max_size(&self, width: i32, height: i32) {
+        self.max_width.store(width, Ordering::Relaxed);
+        self.max_height.store(height, Ordering::Relaxed);
+    }
+
+    /// Clamps a size to the min and max sizes; the minimum wins if they conflict.
+    pub fn clamp_size(&self, width: f64, height: f64) -> (f64, f64) {
+        fn clamp(value: f64, min: i32, max: i32) -> f64 {
+            let value = if max > 0 { value.min(max.into()) } else { value };
+            value.max(min.into())
+        }
+        (
+            clamp(
+                width,
+                self.min_width.load(Ordering::Relaxed),
+                self.max_width.load(Ordering::Relaxed),
+            ),
+            clamp(
+                height,
+                self.min_height.load(Ordering::Relaxed),
+                self.max_height.load(Ordering::Relaxed),
+            ),
+        )
+    }
+
+    pub fn scale_factor(&self) -> Result<Option<f64>, WebViewError> {
+        let scale = self
+            .scale_factor
+            .lock()
+            .map_err(|_| WebViewError::Internal("scale factor lock poisoned".to_string()))?;
+        Ok(*scale)
+    }
+
+    pub fn set_scale_factor(&self, scale_factor: Option<f64>) -> Result<(), WebViewError> {
+        let mut current = self
+            .scale_factor
+            .lock()
+            .map_err(|_| WebViewError::Internal("scale factor lock poisoned".to_string()))?;
+        *current = scale_factor;
+        Ok(())
+    }
+
+    pub fn content_insets(&self) -> Result<(i32, i32, i32, i32), WebViewError> {
+        let insets = self
+            .content_insets
+            .lock()
+            .map_err(|_| WebViewError::Internal("content insets lock poisoned".to_string()))?;
+        Ok(*insets)
+    }
+
+    pub fn set_content_insets(&self, insets: (i32, i32, i32, i32)) -> Result<(), WebViewError> {
+        let mut current = self
+            .content_insets
+            .lock()
+            .map_err(|_| WebViewError::Internal("content insets lock poisoned".to_string()))?;
+        *current = insets;
+        Ok(())
+    }
+
+    pub fn link_open_mode(&self) -> Result<Option<LinkOpenMode>, WebViewError> {
+        let mode = self
+            .link_open_mode
+            .lock()
+            .map_err(|_| WebViewError::Internal("link open mode lock poisoned".to_string()))?;
+        Ok(mode.clone())
+    }
+
+    pub fn set_link_open_mode(&self, mode: Option<LinkOpenMode>) -> Result<(), WebViewError> {
+        let mut current = self
+            .link_open_mode
+            .lock()
+            .map_err(|_| WebViewError::Internal("link open mode lock poisoned".to_string()))?;
+        *current = mode;
+        Ok(())
+    }
+
+    pub fn set_request_interceptor(
+        &self,
+        interceptor: Option<(RequestFilter, Arc<dyn RequestInterceptor>)>,
+    ) -> Result<(), WebViewError> {
+        let mut current = self
+            .request_interceptor
+            .lock()
+            .map_err(|_| WebViewError::Internal("request interceptor lock poisoned".to_string()))?;
+        *current = interceptor;
+        Ok(())
+    }
+
+    /// Returns the request interceptor if its filter matches the request. A
+    /// poisoned lock behaves as no interceptor.
+    pub fn request_interceptor(
+        &self,
+        url: &str,
+        resource_type: ResourceType,
+    ) -> Option<Arc<dyn RequestInterceptor>> {
+        let current = self.request_interceptor.lock().ok()?;
+        let (filter, interceptor) = current.as_ref()?;
+        filter.matches(url, resource_type).then(|| Arc::clone(interceptor))
+    }
+
+    /// Records a challenge for `host` and `realm`, returning how many were recorded before.
+    pub fn record_auth_attempt(&self, host: &str, realm: &str) -> Result<u32, WebViewError> {
+        let mut attempts = self
+            .auth_attempts
+            .lock()
+            .map_err(|_| WebViewError::Internal("auth attempts lock poisoned".to_string()))?;
+        let count = attempts
+            .entry((host.to_string(), realm.to_string()))
+            .or_insert(0);
+        let previous = *count;
+        *count += 1;
+        Ok(previous)
+    }
+
+    pub fn reset_auth_attempts(&self) -> Result<(), WebViewError> {
+        let mut attempts = self
+            .auth_attempts
+            .lock()
+            .map_err(|_| WebViewError::Internal("auth attempts lock poisoned".to_string()))?;
+        attempts.clear();
+        Ok(())
+    }
+
+    /// Records a warning, dropping the oldest once `MAX_SSL_WARNINGS` are stored.
+    pub fn push_ssl_warning(&self, warning: SslWarning) -> Result<(), WebViewError> {
+        let mut warnings = self
+            .ssl_warnings
+            .lock()
+            .map_err(|_| WebViewError::Internal("ssl warnings lock poisoned".to_string()))?;
+        if warnings.len() >= MAX_SSL_WARNINGS {
+            warnings.remove(0);
+        }
+        warnings.push(warning);
+        Ok(())
+    }
+
+    pub fn ssl_warnings(&self) -> Result<Vec<SslWarning>, WebViewError> {
+        let warnings = self
+            .ssl_warnings
+            .lock()
+            .map_err(|_| WebViewError::Internal("ssl warnings lock poisoned".to_string()))?;
+        Ok(warnings.clone())
+    }
+
+    pub fn clear_ssl_warnings(&self) -> Result<(), WebViewError> {
+        let mut warnings = self
+            .ssl_warnings
+            .lock()
+            .map_err(|_| WebViewError::Internal("ssl warnings lock poisoned".to_string()))?;
+        warnings.clear();
+        Ok(())
+    }
+
+    pub fn main_response(&self) -> Result<Option<(u16, Vec<(String, String)>)>, WebViewError> {
+        let response = self
+            .main_response
+            .lock()
+            .map_err(|_| WebViewError::Internal("main response lock poisoned".to_string()))?;
+        Ok(response.clone())
+    }
+
+    pub fn set_main_response(
+        &self,
+        response: Option<(u16, Vec<(String, String)>)>,
+    ) -> Result<(), WebViewError> {
+        let mut current = self
+            .main_response
+            .lock()
+            .map_err(|_| WebViewError::Internal("main response lock poisoned".to_string()))?;
+        *current = response;
+        Ok(())
+    }
+
+    pub fn proxy(&self) -> Result<Option<ProxyConfig>, WebViewError> {
+        let proxy = self
+            .proxy
+            .lock()
+            .map_err(|_| WebViewError::Internal("proxy lock poisoned".to_string()))?;
+        Ok(proxy.clone())
+    }
+
+    pub fn set_proxy(&self, proxy: Option<ProxyConfig>) -> Result<(), WebViewError> {
+        let mut current = self
+            .proxy
+            .lock()
+            .map_err(|_| WebViewError::Internal("proxy lock poisoned".to_string()))?;
+        *current = proxy;
+        Ok(())
+    }
+
+    pub fn autoplay_policy(&self) -> Result<Option<AutoplayPolicy>, WebViewError> {
+        let policy = self
+            .autoplay_policy
+            .lock()
+            .map_err(|_| WebViewError::Internal("autoplay lock poisoned".to_string()))?;
+        Ok(*policy)
+    }
+
+    pub fn set_autoplay_policy(&self, policy: Option<AutoplayPolicy>) -> Result<(), WebViewError> {
+        let mut current = self
+            .autoplay_policy
+            .lock()
+            .map_err(|_| WebViewError::Internal("autoplay lock poisoned".to_string()))?;
+        *current = policy;
+        Ok(())
+    }
+
+    pub fn group_id(&self) -> Result<Option<u64>, WebViewError> {
+        let group_id = self
+            .group_id
+            .lock()
+            .map_err(|_| WebViewError::Internal("group lock poisoned".to_string()))?;
+        Ok(*group_id)
+    }
+
+    pub fn set_group_id(&self, group_id: Option<u64>) -> Result<(), WebViewError> {
+        let mut current = self
+            .group_id
+            .lock()
+            .map_err(|_| WebViewError::Internal("group lock poisoned".to_string()))?;
+        *current = group_id;
+        Ok(())
+    }
+
+    /// Scripts that must be re-evaluated after every page load.
+    pub fn page_scripts(&self) -> Result<Vec<String>, WebViewError> {
+        let mut result = Vec::new();
+
+        // WebView2 injects request headers natively (see `platform::windows`).
+        if cfg!(not(target_os = "windows")) {
+            let headers = self.request_headers()?;
+            if !headers.is_empty() {
+                result.push(scripts::request_headers_script(&headers));
+            }
+        }
+
+        if self.animation_frame_handler.is_set() {
+            result.push(scripts::animation_frame_script(true));
+        }
+
+        if self.layout_shift_callback.is_set() {
+            result.push(scripts::layout_shift_script(true));
+        }
+
+        if !cfg!(target_os = "windows") && self.console_handler.is_set() {
+            result.push(scripts::console_script(true));
+        }
+
+        if !self.show_scrollbars.load(Ordering::Acquire) {
+            result.push(scripts::scrollbar_script(false));
+        }
+
+        let content_insets = self.content_insets()?;
+        if content_insets != (0, 0, 0, 0) {
+            result.push(scripts::content_insets_script(content_insets));
+        }
+
+        // The native appearance survives navigations on macOS; elsewhere a script overrides it.
+        let color_scheme = self.color_scheme()?;
+        if !cfg!(target_os = "macos") && color_scheme != ColorScheme::Auto {
+            result.push(scripts::color_scheme_script(color_scheme));
+        }
+
+        // WebView2 has no file chooser event, so file inputs are intercepted in the page.
+        if cfg!(target_os = "windows") && self.file_chooser_handler.is_set() {
+            result.push(scripts::file_chooser_script(true));
+        }
+
+        // Host scripts run last, in registration order, once the helpers are installed.
+        let persistent = self
+            .persistent_scripts
+            .lock()
+            .map_err(|_| WebViewError::Internal("persistent scripts lock poisoned".to_string()))?;
+        result.extend(persistent.iter().map(|(_, script)| script.clone()));
+
+        Ok(result)
+    }
+
     pub fn can_go_back(&self) -> Result<bool, WebViewError> {
         let history = self
             .history
@@ -98,6 +788,57 @@ impl WebViewState {
         Ok(idx < history.len().saturating_sub(1))
     }
 
+    /// Returns the URL `offset` entries away from the current one in the tracked
+    /// history, or `None` past either end.
+    pub fn history_entry(&self, offset: i32) -> Result<Option<String>, WebViewError> {
+        let history = self
+            .history
+            .lock()
+            .map_err(|_| WebViewError::Internal("history lock poisoned".to_string()))?;
+        let index = self
+            .history_index
+            .lock()
+            .map_err(|_| WebViewError::Internal("history index lock poisoned".to_string()))?;
+        if *index < 0 {
+            return Ok(None);
+        }
+        let target = *index + offset as isize;
+        if target < 0 {
+            return Ok(None);
+        }
+        Ok(history.get(target as usize).cloned())
+    }
+
+    /// Returns the cached navigation state, read while holding every lock involved.
+    ///
+    /// The URL is not behind a lock; it is loaded once up front.
+    pub fn info(&self) -> Result<WebViewInfo, WebViewError> {
+        let url = self.current_url.load_full();
+        let title = self
+            .page_title
+            .read()
+            .map_err(|_| WebViewError::Internal("title lock poisoned".to_string()))?;
+        let history = self
+            .history
+            .lock()
+            .map_err(|_| WebViewError::Internal("history lock poisoned".to_string()))?;
+        let index = self
+            .history_index
+            .lock()
+            .map_err(|_| WebViewError::Internal("history index lock poisoned".to_string()))?;
+        Ok(WebViewInfo {
+            url: url.as_ref().clone(),
+            title: title.clone(),
+            is_loading: self.is_loading.load(Ordering::Acquire),
+            can_go_back: *index > 0 && !history.is_empty(),
+            can_go_forward: *index >= 0 && (*index as usize) < history.len().saturating_sub(1),
+            progress: self.load_progress(),
+            created_at: self.created_at(),
+            history_length: self.history_length.load(Ordering::Relaxed),
+            history_offset: self.history_offset.load(Ordering::Relaxed),
+        })
+    }
+
     fn update_history(&self, new_url: String) -> Result<(), WebViewError> {
         let mut history = self
             .history
@@ -137,109 +878,394 @@ impl WebViewState {
     }
 }
 
-/// Entry in the WebView registry containing the pointer and metadata.
-pub struct WebViewEntry {
-    pub ptr: *mut WebView,
-    pub thread_id: ThreadId,
-    pub state: Arc<WebViewState>,
+/// Next WebView ID. IDs start at 1: 0 means "not registered" (see `WebViewState::id`).
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Default maximum number of WebViews registered at once.
+const DEFAULT_MAX_WEBVIEWS: u32 = 64;
+
+static MAX_WEBVIEWS: AtomicU32 = AtomicU32::new(DEFAULT_MAX_WEBVIEWS);
+
+thread_local! {
+    /// WebViews created on this thread. Native WebViews may only be used on the
+    /// thread that created them, so their lookups need no lock, and they are
+    /// always destroyed on that thread.
+    static WEBVIEWS: RefCell<HashMap<u64, Rc<WebView>>> = RefCell::new(HashMap::new());
+}
+
+thread_local! {
+    /// Contexts of the named data stores used on this thread. They are leaked on
+    /// purpose: WebViews created later with the same identifier must share them.
+    static WEB_CONTEXTS: RefCell<HashMap<String, &'static RefCell<WebContext>>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Returns the web context of the data store named `identifier`, creating it on
+/// first use. The context stays borrowed until the returned guard is dropped.
+pub fn web_context(identifier: &str) -> Result<RefMut<'static, WebContext>, WebViewError> {
+    let context = WEB_CONTEXTS.with(|contexts| {
+        *contexts
+            .borrow_mut()
+            .entry(identifier.to_string())
+            .or_insert_with(|| {
+                let directory = crate::platform::data_store_directory(identifier);
+                Box::leak(Box::new(RefCell::new(WebContext::new(Some(directory)))))
+            })
+    });
+    context
+        .try_borrow_mut()
+        .map_err(|_| WebViewError::Internal("web context already in use".to_string()))
+}
+
+static STATES: OnceLock<RwLock<HashMap<u64, Arc<WebViewState>>>> = OnceLock::new();
+
+/// Returns the global registry of WebView states, readable from any thread.
+fn states() -> &'static RwLock<HashMap<u64, Arc<WebViewState>>> {
+    STATES.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Returns whether a WebView is registered, on any thread.
+fn is_registered(id: u64) -> Result<bool, WebViewError> {
+    let states = states()
+        .read()
+        .map_err(|_| WebViewError::Internal("webview registry lock poisoned".to_string()))?;
+    Ok(states.contains_key(&id))
+}
+
+static NEXT_GROUP_ID: AtomicU64 = AtomicU64::new(1);
+static WEBVIEW_GROUPS: OnceLock<Mutex<HashMap<u64, Vec<u64>>>> = OnceLock::new();
+
+/// Returns the global registry of WebView groups and their members.
+fn webview_groups() -> &'static Mutex<HashMap<u64, Vec<u64>>> {
+    WEBVIEW_GROUPS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Returns the name of the data store owned by a group.
+pub fn group_data_store_identifier(group_id: u64) -> String {
+    format!("wrywebview-group-{}", group_id)
+}
+
+/// Creates an empty WebView group.
+pub fn create_group() -> Result<u64, WebViewError> {
+    let group_id = NEXT_GROUP_ID.fetch_add(1, Ordering::Relaxed);
+    let mut groups = webview_groups()
+        .lock()
+        .map_err(|_| WebViewError::Internal("group registry lock poisoned".to_string()))?;
+    groups.insert(group_id, Vec::new());
+    Ok(group_id)
+}
+
+/// Returns the members of a group.
+pub fn group_members(group_id: u64) -> Result<Vec<u64>, WebViewError> {
+    let groups = webview_groups()
+        .lock()
+        .map_err(|_| WebViewError::Internal("group registry lock poisoned".to_string()))?;
+    groups
+        .get(&group_id)
+        .cloned()
+        .ok_or(WebViewError::GroupNotFound(group_id))
 }
 
-impl Clone for WebViewEntry {
-    fn clone(&self) -> Self {
-        WebViewEntry {
-            ptr: self.ptr,
-            thread_id: self.thread_id,
-            state: Arc::clone(&self.state),
+/// Adds a WebView to a group, leaving the group it was in before.
+pub fn join_group(group_id: u64, state: &WebViewState) -> Result<(), WebViewError> {
+    let id = state.id();
+    {
+        let mut groups = webview_groups()
+            .lock()
+            .map_err(|_| WebViewError::Internal("group registry lock poisoned".to_string()))?;
+        if !groups.contains_key(&group_id) {
+            return Err(WebViewError::GroupNotFound(group_id));
+        }
+        if let Some(previous) = state.group_id()?.and_then(|previous| groups.get_mut(&previous)) {
+            previous.retain(|member| *member != id);
+        }
+        let members = groups.entry(group_id).or_default();
+        if !members.contains(&id) {
+            members.push(id);
         }
     }
+    state.set_group_id(Some(group_id))
 }
 
-// The raw pointer is only dereferenced on the creating thread (checked at runtime).
-unsafe impl Send for WebViewEntry {}
-unsafe impl Sync for WebViewEntry {}
+/// Removes a WebView from a group; a no-op if it is not a member.
+pub fn leave_group(group_id: u64, state: &WebViewState) -> Result<(), WebViewError> {
+    let id = state.id();
+    {
+        let mut groups = webview_groups()
+            .lock()
+            .map_err(|_| WebViewError::Internal("group registry lock poisoned".to_string()))?;
+        let members = groups
+            .get_mut(&group_id)
+            .ok_or(WebViewError::GroupNotFound(group_id))?;
+        members.retain(|member| *member != id);
+    }
+    if state.group_id()? == Some(group_id) {
+        state.set_group_id(None)?;
+    }
+    Ok(())
+}
 
-static NEXT_ID: AtomicU64 = AtomicU64::new(1);
-static WEBVIEWS: OnceLock<Mutex<HashMap<u64, WebViewEntry>>> = OnceLock::new();
+fn unix_time_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
 
-/// Returns the global WebView registry.
-pub fn webviews() -> &'static Mutex<HashMap<u64, WebViewEntry>> {
-    WEBVIEWS.get_or_init(|| Mutex::new(HashMap::new()))
+/// Returns whether a WebView has been created, even if it was destroyed since.
+pub fn any_created() -> bool {
+    NEXT_ID.load(Ordering::Relaxed) > 1
 }
 
 /// Generates a new unique WebView ID.
-pub fn next_id() -> u64 {
-    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+///
+/// The counter never wraps around, so an ID cannot be handed out twice; it fails
+/// with `IdSpaceExhausted` instead.
+pub fn next_id() -> Result<u64, WebViewError> {
+    NEXT_ID
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |id| id.checked_add(1))
+        .map_err(|_| WebViewError::IdSpaceExhausted)
+}
+
+static NEXT_SCRIPT_ID: AtomicU64 = AtomicU64::new(1);
+static FRONT_Z_INDEX: AtomicI32 = AtomicI32::new(0);
+static BACK_Z_INDEX: AtomicI32 = AtomicI32::new(0);
+
+/// Returns a z-index above every z-index handed out so far.
+pub fn next_front_z_index() -> i32 {
+    FRONT_Z_INDEX.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Returns a z-index below every z-index handed out so far.
+pub fn next_back_z_index() -> i32 {
+    BACK_Z_INDEX.fetch_sub(1, Ordering::Relaxed) - 1
 }
 
 /// Executes a closure with access to the WebView, ensuring thread safety.
+///
+/// The registry borrow ends before `f` runs, so `f` may create or destroy
+/// WebViews, including this one: it is then dropped once `f` returns.
 pub fn with_webview<F, R>(id: u64, f: F) -> Result<R, WebViewError>
 where
     F: FnOnce(&WebView) -> Result<R, WebViewError>,
 {
-    let (ptr, thread_id) = {
-        let map = webviews()
-            .lock()
-            .map_err(|_| WebViewError::Internal("webview registry lock poisoned".to_string()))?;
-        let entry = map.get(&id).ok_or(WebViewError::WebViewNotFound(id))?;
-        (entry.ptr, entry.thread_id)
+    let webview = WEBVIEWS.with(|webviews| webviews.borrow().get(&id).cloned());
+    let Some(webview) = webview else {
+        return Err(if is_registered(id)? {
+            WebViewError::WrongThread(id)
+        } else {
+            WebViewError::WebViewNotFound(id)
+        });
     };
 
-    if thread_id != std::thread::current().id() {
-        return Err(WebViewError::WrongThread(id));
-    }
+    f(&webview)
+}
 
-    let webview = unsafe { &*ptr };
-    f(webview)
+/// Like `with_webview`, for closures that cannot fail.
+pub fn with_webview_infallible<F, R>(id: u64, f: F) -> Result<R, WebViewError>
+where
+    F: FnOnce(&WebView) -> R,
+{
+    with_webview(id, |webview| Ok(f(webview)))
 }
 
 /// Retrieves the state for a WebView by ID.
 pub fn get_state(id: u64) -> Result<Arc<WebViewState>, WebViewError> {
-    let map = webviews()
-        .lock()
+    let states = states()
+        .read()
         .map_err(|_| WebViewError::Internal("webview registry lock poisoned".to_string()))?;
-    let entry = map.get(&id).ok_or(WebViewError::WebViewNotFound(id))?;
-    Ok(Arc::clone(&entry.state))
+    states
+        .get(&id)
+        .cloned()
+        .ok_or(WebViewError::WebViewNotFound(id))
 }
 
-/// Registers a new WebView in the global registry.
-pub fn register(webview: WebView, state: Arc<WebViewState>) -> Result<u64, WebViewError> {
-    let id = next_id();
-    let entry = WebViewEntry {
-        ptr: Box::into_raw(Box::new(webview)),
-        thread_id: std::thread::current().id(),
-        state,
-    };
+/// Sets the maximum number of WebViews registered at once (at least 1).
+pub fn set_max_webviews(max: u32) {
+    MAX_WEBVIEWS.store(max.max(1), Ordering::Relaxed);
+}
 
-    let mut map = webviews()
-        .lock()
+fn check_capacity(count: usize) -> Result<(), WebViewError> {
+    if count >= MAX_WEBVIEWS.load(Ordering::Relaxed) as usize {
+        return Err(WebViewError::TooManyWebViews(count as u32));
+    }
+    Ok(())
+}
+
+/// Fails with `TooManyWebViews` if the registry is full, so callers can bail
+/// out before creating the native WebView.
+pub fn ensure_capacity() -> Result<(), WebViewError> {
+    let states = states()
+        .read()
         .map_err(|_| WebViewError::Internal("webview registry lock poisoned".to_string()))?;
-    map.insert(id, entry);
+    check_capacity(states.len())
+}
+
+/// A built WebView that is not registered yet.
+///
+/// Dropping it without `take()` hides and destroys the WebView. That path never touches the
+/// registry, so it is safe even when the registry lock is poisoned.
+pub struct PendingWebView(Option<WebView>);
+
+impl PendingWebView {
+    pub fn new(webview: WebView) -> Self {
+        Self(Some(webview))
+    }
+
+    /// Returns the WebView, leaving nothing to clean up on drop.
+    fn take(mut self) -> WebView {
+        self.0.take().expect("pending webview already taken")
+    }
+}
+
+impl Deref for PendingWebView {
+    type Target = WebView;
+
+    fn deref(&self) -> &WebView {
+        self.0.as_ref().expect("pending webview already taken")
+    }
+}
+
+impl Drop for PendingWebView {
+    fn drop(&mut self) {
+        let Some(webview) = self.0.take() else {
+            return;
+        };
+        eprintln!("[wrywebview] discarding unregistered webview");
+        // Hidden first so a half-configured page never shows while the engine tears down.
+        if let Err(e) = webview.set_visible(false) {
+            eprintln!("[wrywebview] discarded webview not hidden: {}", e);
+        }
+        drop(webview);
+    }
+}
+
+/// Registers a new WebView, owned by the current thread.
+///
+/// Fails with `TooManyWebViews` if the registry is full; the WebView is then
+/// destroyed.
+pub fn register(webview: PendingWebView, state: Arc<WebViewState>) -> Result<u64, WebViewError> {
+    let id = {
+        let mut states = states()
+            .write()
+            .map_err(|_| WebViewError::Internal("webview registry lock poisoned".to_string()))?;
+        check_capacity(states.len())?;
+        let id = next_id()?;
+        state.id.store(id, Ordering::Relaxed);
+        state.created_at.store(unix_time_ms(), Ordering::Relaxed);
+        states.insert(id, state);
+        id
+    };
+
+    let webview = webview.take();
+    WEBVIEWS.with(|webviews| webviews.borrow_mut().insert(id, Rc::new(webview)));
     Ok(id)
 }
 
+/// Returns the IDs of all registered WebViews, in creation order.
+///
+/// The registry lock is released before returning, so callers may destroy the
+/// listed WebViews while iterating.
+pub fn registered_ids() -> Vec<u64> {
+    let mut ids: Vec<u64> = match states().read() {
+        Ok(states) => states.keys().copied().collect(),
+        Err(_) => return Vec::new(),
+    };
+    ids.sort_unstable();
+    ids
+}
+
+/// Returns the states of all registered WebViews, in no particular order.
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+pub fn registered_states() -> Vec<Arc<WebViewState>> {
+    match states().read() {
+        Ok(states) => states.values().cloned().collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
 /// Removes and destroys a WebView from the registry.
 pub fn unregister(id: u64) -> Result<(), WebViewError> {
-    let entry = {
-        let mut map = webviews()
-            .lock()
+    let webview = WEBVIEWS.with(|webviews| webviews.borrow_mut().remove(&id));
+    let Some(webview) = webview else {
+        return if is_registered(id)? {
+            Err(WebViewError::WrongThread(id))
+        } else {
+            Ok(())
+        };
+    };
+
+    {
+        let mut states = states()
+            .write()
             .map_err(|_| WebViewError::Internal("webview registry lock poisoned".to_string()))?;
+        states.remove(&id);
+    }
 
-        let Some(entry) = map.get(&id) else {
-            return Ok(());
-        };
+    // Dropped outside of the registry borrow: destroying the WebView may run
+    // callbacks that look up the registry.
+    drop(webview);
+    Ok(())
+}
 
-        if entry.thread_id != std::thread::current().id() {
-            return Err(WebViewError::WrongThread(id));
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        map.remove(&id)
-    };
+    fn state() -> WebViewState {
+        WebViewState::new(&WebViewConfig::from_url("about:blank".to_string()))
+    }
 
-    if let Some(entry) = entry {
-        unsafe {
-            drop(Box::from_raw(entry.ptr));
-        }
+    #[test]
+    fn clamps_size_below_min() {
+        let state = state();
+        state.set_min_size(200, 100);
+        assert_eq!(state.clamp_size(50.0, 20.0), (200.0, 100.0));
     }
 
-    Ok(())
+    #[test]
+    fn clamps_size_above_max() {
+        let state = state();
+        state.set_max_size(800, 600);
+        assert_eq!(state.clamp_size(1000.0, 900.0), (800.0, 600.0));
+    }
+
+    #[test]
+    fn clamps_size_between_min_and_max() {
+        let state = state();
+        state.set_min_size(200, 100);
+        state.set_max_size(800, 600);
+        assert_eq!(state.clamp_size(50.0, 900.0), (200.0, 600.0));
+        assert_eq!(state.clamp_size(400.0, 300.0), (400.0, 300.0));
+    }
+
+    #[test]
+    fn min_size_wins_over_conflicting_max() {
+        let state = state();
+        state.set_min_size(500, 500);
+        state.set_max_size(300, 300);
+        assert_eq!(state.clamp_size(400.0, 100.0), (500.0, 500.0));
+    }
+
+    #[test]
+    fn history_entry_stays_within_the_tracked_history() {
+        let state = state();
+        state.update_history("https://a.test/".to_string()).unwrap();
+        state.update_history("https://b.test/".to_string()).unwrap();
+        assert_eq!(state.history_entry(-1).unwrap().as_deref(), Some("https://a.test/"));
+        assert_eq!(state.history_entry(0).unwrap().as_deref(), Some("https://b.test/"));
+        assert_eq!(state.history_entry(-2).unwrap(), None);
+        assert_eq!(state.history_entry(1).unwrap(), None);
+    }
+
+    #[test]
+    fn next_id_fails_instead_of_wrapping() {
+        let saved = NEXT_ID.swap(u64::MAX - 1, Ordering::Relaxed);
+        let last = next_id();
+        let exhausted = next_id();
+        NEXT_ID.store(saved, Ordering::Relaxed);
+
+        assert_eq!(last.unwrap(), u64::MAX - 1);
+        assert!(matches!(exhausted, Err(WebViewError::IdSpaceExhausted)));
+    }
 }
@@ -0,0 +1,594 @@
+//! Creates real WebViews in a bare X11 window and checks how they behave.
+//!
+//! Run under a display server; on CI without one, start Xvfb first. The tests are
+//! skipped when no display can be opened, and on macOS and Windows, which have
+//! no window to host the WebView without a running application.
+
+#![cfg(target_os = "linux")]
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use composewebview_wry::{
+    add_webview_to_group, blur, can_go_back, create_webview, create_webview_group, destroy_webview,
+    evaluate_javascript, focus, get_created_at, get_muted, get_page_source,
+    get_response_headers_for_main_resource, get_title, get_url, get_webview_info, has_focus,
+    is_loading, is_on_gtk_thread, is_pointer_events_passthrough, list_webviews, load_url, mute,
+    pump_gtk_events, reload, remove_webview_from_group, scroll_to_element, set_javascript_enabled,
+    set_max_webviews, set_pointer_events_passthrough, set_proxy, set_scrollbar_visibility,
+    subscribe_events, unmute, HttpHeader, JavaScriptCallback, NativeWebViewBuilder, ProxyConfig,
+    ProxyType, ResponseHeadersCallback, WebViewError, WebViewEvent, WebViewPool,
+};
+
+/// Serializes the tests, which share the WebView registry and its limit.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+/// Opens a mapped top-level X11 window and returns its ID, or `None` without a display.
+///
+/// The display connection is kept open so the window outlives the test.
+fn open_window() -> Option<u64> {
+    unsafe {
+        let display = x11::xlib::XOpenDisplay(std::ptr::null());
+        if display.is_null() {
+            return None;
+        }
+        let screen = x11::xlib::XDefaultScreen(display);
+        let root = x11::xlib::XRootWindow(display, screen);
+        let window = x11::xlib::XCreateSimpleWindow(display, root, 0, 0, 400, 300, 0, 0, 0);
+        x11::xlib::XMapWindow(display, window);
+        x11::xlib::XFlush(display);
+        Some(window as u64)
+    }
+}
+
+/// Takes the test lock and opens a window to host WebViews, or returns `None`
+/// without a display.
+fn setup() -> Option<(MutexGuard<'static, ()>, u64)> {
+    let serial = SERIAL.lock().unwrap_or_else(PoisonError::into_inner);
+    // Compositing needs a GPU, which virtual displays lack.
+    std::env::set_var("WEBKIT_DISABLE_COMPOSITING_MODE", "1");
+    Some((serial, open_window()?))
+}
+
+struct ResultSender(Mutex<Sender<String>>);
+
+impl JavaScriptCallback for ResultSender {
+    fn on_result(&self, result: String) {
+        let _ = self.0.lock().unwrap().send(result);
+    }
+}
+
+/// Evaluates `script` and returns its JSON-encoded result.
+fn evaluate(id: u64, script: &str) -> String {
+    let (result_tx, result_rx) = mpsc::channel();
+    let callback = Box::new(ResultSender(Mutex::new(result_tx)));
+    evaluate_javascript(id, script.to_string(), callback).unwrap();
+    result_rx.recv_timeout(Duration::from_secs(10)).unwrap()
+}
+
+/// Serves a blank HTML page over HTTP on a local port and returns its URL, for
+/// tests that need a real origin (cookies, storage).
+fn serve_page() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: 0\r\n\
+                  Connection: close\r\n\r\n",
+            );
+        }
+    });
+    url
+}
+
+/// Waits for a WebView created straight on its page, for which `is_loading`
+/// stays true until the page has loaded.
+fn wait_until_loaded(id: u64) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while is_loading(id).unwrap() {
+        assert!(Instant::now() < deadline, "page did not finish loading");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn loads_about_blank() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let id = create_webview(window, 0, 0, 400, 300, "about:blank".to_string()).unwrap();
+    let events = subscribe_events(id).unwrap();
+    load_url(id, "about:blank".to_string()).unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        assert!(!remaining.is_zero(), "page did not finish loading");
+        let event = events.recv_timeout(remaining.as_millis() as u64).unwrap();
+        if let Some(WebViewEvent::PageLoaded { url }) = event {
+            assert_eq!(url, "about:blank");
+            break;
+        }
+    }
+
+    assert!(!is_loading(id).unwrap());
+    assert_eq!(get_url(id).unwrap(), "about:blank");
+    destroy_webview(id).unwrap();
+}
+
+#[test]
+fn scrolls_to_anchor() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let page = r#"data:text/html,<div style="height:5000px"></div><a id="target">Target</a>"#;
+    let id = create_webview(window, 0, 0, 400, 300, page.to_string()).unwrap();
+    wait_until_loaded(id);
+
+    assert_eq!(evaluate(id, "window.scrollY"), "0");
+    scroll_to_element(id, "#target".to_string(), false).unwrap();
+    // The page is too short to bring the target to the very top, so it scrolls to the end.
+    let scroll_y: f64 = evaluate(id, "window.scrollY").parse().unwrap();
+    assert!(scroll_y >= 4700.0, "scrolled to {}", scroll_y);
+    assert!(matches!(
+        scroll_to_element(id, "#missing".to_string(), false),
+        Err(WebViewError::ElementNotFound(selector)) if selector == "#missing"
+    ));
+    assert!(matches!(
+        scroll_to_element(id, "<script>".to_string(), true),
+        Err(WebViewError::InvalidArgument(_))
+    ));
+    destroy_webview(id).unwrap();
+}
+
+#[test]
+fn pool_reuses_released_webviews() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let pool = WebViewPool::new(1);
+    let start = Instant::now();
+    let id = pool.clone().acquire(window, 400, 300, "about:blank".to_string()).unwrap();
+    let created_in = start.elapsed();
+    pool.clone().release(id).unwrap();
+    assert_eq!(pool.idle_count().unwrap(), 1);
+    assert!(matches!(pool.clone().release(id), Err(WebViewError::InvalidArgument(_))));
+
+    let start = Instant::now();
+    let reused = pool.clone().acquire(window, 400, 300, "about:blank".to_string()).unwrap();
+    let reused_in = start.elapsed();
+    assert_eq!(reused, id);
+    assert!(
+        reused_in < created_in,
+        "reuse took {:?}, creation {:?}",
+        reused_in,
+        created_in
+    );
+    destroy_webview(reused).unwrap();
+}
+
+#[test]
+fn reads_page_source() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let page = r#"data:text/html,<p id="known">Known fragment</p>"#;
+    let id = create_webview(window, 0, 0, 400, 300, page.to_string()).unwrap();
+    wait_until_loaded(id);
+
+    let source = get_page_source(id).unwrap();
+    assert!(source.contains(r#"<p id="known">Known fragment</p>"#), "source: {}", source);
+    destroy_webview(id).unwrap();
+}
+
+#[test]
+fn limits_the_number_of_webviews() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let create = || create_webview(window, 0, 0, 400, 300, "about:blank".to_string());
+    set_max_webviews(2);
+    let first = create().unwrap();
+    let second = create().unwrap();
+    let over_limit = create();
+    destroy_webview(first).unwrap();
+    let third = create();
+    set_max_webviews(64);
+
+    assert!(matches!(over_limit, Err(WebViewError::TooManyWebViews(2))));
+    let third = third.unwrap();
+    destroy_webview(second).unwrap();
+    destroy_webview(third).unwrap();
+}
+
+#[test]
+fn focuses_and_blurs() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let id = create_webview(window, 0, 0, 400, 300, "about:blank".to_string()).unwrap();
+    assert!(!has_focus(id).unwrap());
+    focus(id).unwrap();
+    assert!(has_focus(id).unwrap());
+    blur(id).unwrap();
+    assert!(!has_focus(id).unwrap());
+
+    // A click-through WebView leaves the focus to the views below.
+    set_pointer_events_passthrough(id, true).unwrap();
+    focus(id).unwrap();
+    assert!(!has_focus(id).unwrap());
+    destroy_webview(id).unwrap();
+}
+
+#[test]
+fn spawned_threads_are_not_the_gtk_thread() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    // Starts the GTK thread, so there is a real thread to compare against.
+    let id = create_webview(window, 0, 0, 400, 300, "about:blank".to_string()).unwrap();
+    assert!(!std::thread::spawn(is_on_gtk_thread).join().unwrap());
+    destroy_webview(id).unwrap();
+}
+
+#[test]
+fn pumps_gtk_events_quickly() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let id = create_webview(window, 0, 0, 400, 300, "about:blank".to_string()).unwrap();
+    let start = Instant::now();
+    pump_gtk_events();
+    let elapsed = start.elapsed();
+    assert!(elapsed < Duration::from_millis(100), "pumped for {:?}", elapsed);
+    destroy_webview(id).unwrap();
+}
+
+#[test]
+fn starts_idle_on_about_blank() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let id = create_webview(window, 0, 0, 400, 300, "about:blank".to_string()).unwrap();
+    assert!(!is_loading(id).unwrap());
+    destroy_webview(id).unwrap();
+}
+
+#[test]
+fn polls_events_until_the_page_loads() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let id = create_webview(window, 0, 0, 400, 300, "about:blank".to_string()).unwrap();
+    let events = subscribe_events(id).unwrap();
+    let page = "data:text/html,Polled";
+    load_url(id, page.to_string()).unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        assert!(Instant::now() < deadline, "page did not finish loading");
+        match events.poll_event().unwrap() {
+            Some(WebViewEvent::PageLoaded { url }) => {
+                assert_eq!(url, page);
+                break;
+            }
+            Some(_) => {}
+            None => std::thread::sleep(Duration::from_millis(10)),
+        }
+    }
+    destroy_webview(id).unwrap();
+}
+
+#[test]
+fn lists_live_webviews() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let create = || create_webview(window, 0, 0, 400, 300, "about:blank".to_string()).unwrap();
+    let ids = [create(), create(), create()];
+    let listed = list_webviews();
+    assert!(ids.iter().all(|id| listed.contains(id)), "listed: {:?}", listed);
+
+    destroy_webview(ids[1]).unwrap();
+    let listed = list_webviews();
+    assert!(listed.contains(&ids[0]) && listed.contains(&ids[2]), "listed: {:?}", listed);
+    assert!(!listed.contains(&ids[1]));
+    destroy_webview(ids[0]).unwrap();
+    destroy_webview(ids[2]).unwrap();
+}
+
+#[test]
+fn mutes_and_unmutes() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let id = create_webview(window, 0, 0, 400, 300, "about:blank".to_string()).unwrap();
+    assert!(!get_muted(id).unwrap());
+    mute(id).unwrap();
+    assert!(get_muted(id).unwrap());
+    unmute(id).unwrap();
+    assert!(!get_muted(id).unwrap());
+    destroy_webview(id).unwrap();
+}
+
+#[test]
+fn hides_scrollbars_with_a_style_sheet() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let id = create_webview(window, 0, 0, 400, 300, "data:text/html,Scrolled".to_string()).unwrap();
+    wait_until_loaded(id);
+
+    set_scrollbar_visibility(id, false).unwrap();
+    let source = get_page_source(id).unwrap();
+    assert!(source.contains("::-webkit-scrollbar { display: none; }"), "source: {}", source);
+    set_scrollbar_visibility(id, true).unwrap();
+    assert!(!get_page_source(id).unwrap().contains("__wrywebview-scrollbars"));
+    destroy_webview(id).unwrap();
+}
+
+#[test]
+fn disables_and_enables_javascript() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let id = create_webview(window, 0, 0, 400, 300, "data:text/html,Scripted".to_string()).unwrap();
+    wait_until_loaded(id);
+
+    set_javascript_enabled(id, false).unwrap();
+    reload(id).unwrap();
+    wait_until_loaded(id);
+    // wry reports a failed evaluation with an empty result.
+    assert_eq!(evaluate(id, "1+1"), "");
+
+    set_javascript_enabled(id, true).unwrap();
+    reload(id).unwrap();
+    wait_until_loaded(id);
+    assert_eq!(evaluate(id, "1+1"), "2");
+    destroy_webview(id).unwrap();
+}
+
+#[test]
+fn reports_consistent_info_after_a_navigation() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let id = create_webview(window, 0, 0, 400, 300, "about:blank".to_string()).unwrap();
+    let events = subscribe_events(id).unwrap();
+    let page = "data:text/html,<title>Info</title>";
+    load_url(id, page.to_string()).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        assert!(!remaining.is_zero(), "page did not finish loading");
+        let event = events.recv_timeout(remaining.as_millis() as u64).unwrap();
+        if let Some(WebViewEvent::PageLoaded { .. }) = event {
+            break;
+        }
+    }
+
+    let info = get_webview_info(id).unwrap();
+    assert_eq!(info.url, page);
+    assert_eq!(info.url, get_url(id).unwrap());
+    assert_eq!(info.title, "Info");
+    assert_eq!(info.title, get_title(id).unwrap());
+    assert!(!info.is_loading);
+    assert_eq!(info.progress, 1.0);
+    assert_eq!(info.can_go_back, can_go_back(id).unwrap());
+    assert!(!info.can_go_forward);
+    destroy_webview(id).unwrap();
+}
+
+#[test]
+fn tracks_pointer_events_passthrough() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let id = create_webview(window, 0, 0, 400, 300, "about:blank".to_string()).unwrap();
+    assert!(!is_pointer_events_passthrough(id).unwrap());
+    set_pointer_events_passthrough(id, true).unwrap();
+    assert!(is_pointer_events_passthrough(id).unwrap());
+    focus(id).unwrap();
+    assert!(!has_focus(id).unwrap());
+
+    set_pointer_events_passthrough(id, false).unwrap();
+    assert!(!is_pointer_events_passthrough(id).unwrap());
+    focus(id).unwrap();
+    assert!(has_focus(id).unwrap());
+    destroy_webview(id).unwrap();
+}
+
+#[test]
+fn records_the_creation_time() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let now = || {
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        since_epoch.as_millis() as u64
+    };
+    let before = now();
+    let id = create_webview(window, 0, 0, 400, 300, "about:blank".to_string()).unwrap();
+    let created_at = get_created_at(id).unwrap();
+    assert!(created_at.abs_diff(before) < 1000, "created at {}, before {}", created_at, before);
+    assert!(created_at.abs_diff(now()) < 1000, "created at {}", created_at);
+    destroy_webview(id).unwrap();
+}
+
+#[test]
+fn builds_with_the_native_builder() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let page = "data:text/html,Built";
+    let id = NativeWebViewBuilder::new()
+        .with_url(page)
+        .with_bounds(0, 0, 400, 300)
+        .with_user_agent("BuilderTest/1.0")
+        .with_javascript_enabled(true)
+        .build(window)
+        .unwrap();
+    wait_until_loaded(id);
+
+    assert_eq!(get_url(id).unwrap(), page);
+    assert_eq!(evaluate(id, "navigator.userAgent"), r#""BuilderTest/1.0""#);
+    destroy_webview(id).unwrap();
+}
+
+struct UnexpectedHeaders;
+
+impl ResponseHeadersCallback for UnexpectedHeaders {
+    fn on_headers(&self, status: u16, _headers: Vec<HttpHeader>) {
+        panic!("unexpected response with status {}", status);
+    }
+}
+
+#[test]
+fn reports_a_missing_main_response() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    // about:blank is not fetched, so no response is ever recorded.
+    let id = create_webview(window, 0, 0, 400, 300, "about:blank".to_string()).unwrap();
+    let result = get_response_headers_for_main_resource(id, Arc::new(UnexpectedHeaders));
+    assert!(matches!(result, Err(WebViewError::NoMainResponse(missing)) if missing == id));
+    destroy_webview(id).unwrap();
+}
+
+#[test]
+fn isolates_the_sessions_of_groups() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let page = serve_page();
+    let (group, other_group) = (create_webview_group().unwrap(), create_webview_group().unwrap());
+    let create = |group_id| {
+        let builder = NativeWebViewBuilder::new().with_url(&page).with_bounds(0, 0, 400, 300);
+        let id = builder.with_group(group_id).build(window).unwrap();
+        wait_until_loaded(id);
+        id
+    };
+    let (first, second, isolated) = (create(group), create(group), create(other_group));
+
+    evaluate(first, "document.cookie = 'session=first'");
+    assert_eq!(evaluate(second, "document.cookie"), r#""session=first""#);
+    assert_eq!(evaluate(isolated, "document.cookie"), r#""""#);
+
+    assert!(matches!(
+        add_webview_to_group(other_group, first),
+        Err(WebViewError::DataStoreMismatch(id, group_id)) if id == first && group_id == other_group
+    ));
+    remove_webview_from_group(group, first).unwrap();
+    add_webview_to_group(group, first).unwrap();
+    for id in [first, second, isolated] {
+        destroy_webview(id).unwrap();
+    }
+}
+
+struct StatusRecorder(Mutex<Option<u16>>);
+
+impl ResponseHeadersCallback for StatusRecorder {
+    fn on_headers(&self, status: u16, _headers: Vec<HttpHeader>) {
+        *self.0.lock().unwrap() = Some(status);
+    }
+}
+
+#[test]
+fn keeps_proxies_to_their_own_webviews() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    // Nothing listens on port 1, so pages cannot load through this proxy.
+    let unreachable = ProxyConfig {
+        proxy_type: ProxyType::Http,
+        host: "127.0.0.1".to_string(),
+        port: 1,
+        bypass_list: Vec::new(),
+        username: None,
+        password: None,
+    };
+    let builder = || NativeWebViewBuilder::new().with_bounds(0, 0, 400, 300);
+    let proxied = builder().with_proxy(unreachable.clone()).build(window).unwrap();
+    let page = serve_page();
+    let direct = builder().with_url(&page).build(window).unwrap();
+    wait_until_loaded(direct);
+    let status = Arc::new(StatusRecorder(Mutex::new(None)));
+    get_response_headers_for_main_resource(direct, status.clone()).unwrap();
+    assert_eq!(*status.0.lock().unwrap(), Some(200));
+
+    assert!(matches!(
+        set_proxy(direct, unreachable.clone()),
+        Err(WebViewError::SharedSettingsConflict(_))
+    ));
+    set_proxy(proxied, unreachable.clone()).unwrap();
+
+    let group = create_webview_group().unwrap();
+    let grouped = builder().with_group(group).with_proxy(unreachable).build(window).unwrap();
+    assert!(matches!(
+        builder().with_group(group).build(window),
+        Err(WebViewError::SharedSettingsConflict(_))
+    ));
+    for id in [proxied, direct, grouped] {
+        destroy_webview(id).unwrap();
+    }
+}
+
+#[test]
+fn destroys_from_another_thread() {
+    let Some((_serial, window)) = setup() else {
+        eprintln!("skipped: no X11 display");
+        return;
+    };
+
+    let id = create_webview(window, 0, 0, 400, 300, "about:blank".to_string()).unwrap();
+    std::thread::spawn(move || destroy_webview(id)).join().unwrap().unwrap();
+    assert!(matches!(get_url(id), Err(WebViewError::WebViewNotFound(missing)) if missing == id));
+}
@@ -0,0 +1,25 @@
+//! Checks how GTK initialization fails without a display server.
+//!
+//! GTK is initialized once per process, so this lives in its own test binary,
+//! apart from the tests in `headless.rs` that need a display.
+
+#![cfg(target_os = "linux")]
+
+use composewebview_wry::{create_webview, WebViewError};
+
+#[test]
+fn reports_a_missing_display() {
+    // Runs before GTK is initialized: this binary has no other test.
+    std::env::remove_var("DISPLAY");
+    std::env::remove_var("WAYLAND_DISPLAY");
+
+    let result = create_webview(1, 0, 0, 400, 300, "about:blank".to_string());
+    match result {
+        Err(WebViewError::PlatformInitFailed(message)) => assert!(
+            message.contains("neither DISPLAY nor WAYLAND_DISPLAY is set"),
+            "message: {}",
+            message
+        ),
+        other => panic!("expected PlatformInitFailed, got {:?}", other),
+    }
+}